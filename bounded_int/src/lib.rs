@@ -9,7 +9,11 @@
 
 pub use crate::iter::{BoundedIntRange, BoundedIntRangeInclusive};
 use serde::{Deserialize, Serialize};
-use std::convert::{TryFrom, TryInto};
+use std::{
+    convert::{TryFrom, TryInto},
+    error::Error,
+    fmt,
+};
 
 /// Contains the [`Iterator`] implementations for the range types.
 pub mod iter;
@@ -44,7 +48,11 @@ pub mod ops;
 ///
 /// assert_eq!(invalid, Err(BoundedIntError::TooHigh(100)));
 /// ```
+///
+/// Deserializing a value outside of the bounds fails, just like
+/// [`BoundedInt::new`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "i32", into = "i32")]
 pub struct BoundedInt</* T: Integer, */ const LOW: i32, const HIGH: i32>(pub(crate) i32);
 
 /// Error returned when trying to convert an [`i32`] to a [`BoundedInt`].
@@ -79,6 +87,17 @@ pub enum BoundedIntError {
     TooLow(i32),
 }
 
+impl fmt::Display for BoundedIntError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoundedIntError::TooHigh(n) => f.write_fmt(format_args!("{} is too high", n)),
+            BoundedIntError::TooLow(n) => f.write_fmt(format_args!("{} is too low", n)),
+        }
+    }
+}
+
+impl Error for BoundedIntError {}
+
 // TODO: Move assertions to where clause once `const_evaluatable_checked` is
 // stabilized
 impl<const LOW: i32, const HIGH: i32> BoundedInt<{ LOW }, { HIGH }> {
@@ -374,10 +393,26 @@ impl<const LOW: i32, const HIGH: i32> TryFrom<i32> for BoundedInt<{ LOW }, { HIG
     }
 }
 
+impl<const LOW: i32, const HIGH: i32> From<BoundedInt<{ LOW }, { HIGH }>> for i32 {
+    fn from(value: BoundedInt<{ LOW }, { HIGH }>) -> Self {
+        value.0
+    }
+}
+
 #[cfg(test)]
 mod test_bounded_int {
     use super::*;
 
+    #[test]
+    fn test_deserialize_checks_bounds() {
+        let value: BoundedInt<0, 10> = serde_json::from_str("7").unwrap();
+        assert_eq!(value, BoundedInt(7));
+        assert_eq!(serde_json::to_string(&value).unwrap(), "7");
+
+        let err = serde_json::from_str::<BoundedInt<0, 10>>("11").unwrap_err();
+        assert_eq!(err.to_string(), BoundedIntError::TooHigh(11).to_string());
+    }
+
     #[test]
     fn test_range_to() {
         let start = BoundedInt::<20, 25>::new(20).unwrap();
@@ -1,5 +1,5 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion, SamplingMode};
-use dungeon::{Dungeon, DungeonType};
+use dungeon::{Dungeon, DungeonType, GenerationConfig, Seed};
 use std::{convert::TryInto, num::NonZeroU16};
 
 fn bench_dungeon_generation(c: &mut Criterion) {
@@ -8,33 +8,39 @@ fn bench_dungeon_generation(c: &mut Criterion) {
     group.sample_size(20);
     group.bench_function("50 by 100", |b| {
         b.iter(|| {
-            let _ = Dungeon::new(
+            let _ = Dungeon::with_seed(
                 black_box(50.try_into().unwrap()),
                 black_box(100.try_into().unwrap()),
                 NonZeroU16::new(10).unwrap(),
                 DungeonType::Cave,
+                &GenerationConfig::default(),
+                Seed::new(0),
                 false,
             );
         })
     });
     group.bench_function("100 by 200", |b| {
         b.iter(|| {
-            let _ = Dungeon::new(
+            let _ = Dungeon::with_seed(
                 black_box(100.try_into().unwrap()),
                 black_box(200.try_into().unwrap()),
                 NonZeroU16::new(10).unwrap(),
                 DungeonType::Cave,
+                &GenerationConfig::default(),
+                Seed::new(0),
                 false,
             );
         })
     });
     group.bench_function("50 by 50, 100 floors", |b| {
         b.iter(|| {
-            let _ = Dungeon::new(
+            let _ = Dungeon::with_seed(
                 black_box(50.try_into().unwrap()),
                 black_box(50.try_into().unwrap()),
                 NonZeroU16::new(100).unwrap(),
                 DungeonType::Cave,
+                &GenerationConfig::default(),
+                Seed::new(0),
                 false,
             );
         })
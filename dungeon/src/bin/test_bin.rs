@@ -1,6 +1,6 @@
 use std::{convert::TryInto, num::NonZeroU16};
 
-use dungeon::{Dungeon, DungeonType};
+use dungeon::{Dungeon, DungeonType, GenerationConfig};
 
 fn main() {
     let _d = Dungeon::new(
@@ -8,6 +8,7 @@ fn main() {
         150.try_into().unwrap(),
         NonZeroU16::new(10).unwrap(),
         DungeonType::Cave,
+        &GenerationConfig::default(),
         true,
     );
 }
@@ -1,4 +1,4 @@
-use std::{collections::HashSet, fmt};
+use std::{collections::BTreeSet, fmt};
 
 use crate::Point;
 
@@ -7,9 +7,10 @@ use crate::Point;
 pub(crate) struct Border {
     /// A unique, opaque ID assigned to each border in the floor.
     pub(crate) id: BorderId,
-    /// The points of the border. Stored in a [`HashSet`] for easy access
-    /// and so that there are no duplicates.
-    pub(crate) points: HashSet<Point>,
+    /// The points of the border. Stored in a [`BTreeSet`] for easy access
+    /// and so that there are no duplicates. The set is ordered so that
+    /// iterating over it is deterministic.
+    pub(crate) points: BTreeSet<Point>,
 }
 
 impl fmt::Debug for Border {
@@ -32,6 +33,6 @@ impl BorderId {
 
 impl fmt::Debug for BorderId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(&*format!("{}", self.0))
+        f.write_str(&format!("{}", self.0))
     }
 }
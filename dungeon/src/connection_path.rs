@@ -1,9 +1,10 @@
-use std::{collections::HashSet, iter};
+use std::{collections::BTreeSet, iter};
 
 use crate::{border::BorderId, Point};
 
 /// A connection between two borders with a path drawn between them.
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 pub(crate) struct ConnectionPath {
     /// The [`BorderId`] of the [`Border`] that the path starts at.
     ///
@@ -84,6 +85,6 @@ pub(crate) enum ConnectionPathLength {
         end: Point,
         /// the points of the path between the start and the end, excluding
         /// start and end.
-        points: HashSet<Point>,
+        points: BTreeSet<Point>,
     },
 }
@@ -0,0 +1,328 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bounded_int::BoundedInt;
+
+use crate::{DungeonTile, Floor, Point, MAX_FLOOR_SIZE, MIN_FLOOR_SIZE};
+
+/// A grid of walking distances to the closest of a set of goals, also known as
+/// a 'Dijkstra map'. Create one with [`Floor::distance_map`].
+///
+/// Every tile that can reach a goal without walking over any
+/// [solid](DungeonTile::is_solid) tiles or through any
+/// [locked](DungeonTile::is_locked) secret doors has a value, where lower
+/// values are closer to a goal. Following the values downhill (see
+/// [`DistanceMap::downhill`]) leads to the closest goal, which is how monsters
+/// chase the player; following the values of a [flee map](DistanceMap::flee)
+/// downhill leads away from the goals instead.
+///
+/// Steps are only taken up, down, left and right.
+///
+/// See <http://www.roguebasin.com/index.php/The_Incredible_Power_of_Dijkstra_Maps>
+///
+/// # Examples
+/// ```rust
+/// use dungeon::{DungeonType, Floor, FloorId, GenerationConfig, Seed};
+/// use std::convert::TryInto;
+///
+/// let floor = Floor::with_seed(
+///     FloorId::new(0),
+///     DungeonType::Cave,
+///     50.try_into().unwrap(),
+///     50.try_into().unwrap(),
+///     &GenerationConfig::default(),
+///     Seed::new(0),
+///     false,
+/// );
+///
+/// let (entrance, exit) = (floor.entrance().unwrap(), floor.exit().unwrap());
+/// let to_exit = floor.distance_map([exit]);
+///
+/// // walk from the entrance to the exit, one step at a time
+/// let mut position = entrance;
+/// while let Some(next) = to_exit.downhill(position) {
+///     position = next;
+/// }
+///
+/// assert_eq!(position, exit);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistanceMap {
+    /// The width of the map.
+    width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+    /// The height of the map.
+    height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+    /// The value of every tile, row by row. [`None`] for tiles that can't
+    /// reach any of the goals.
+    values: Vec<Option<i32>>,
+}
+
+impl DistanceMap {
+    /// What the distances are multiplied by to create a [flee
+    /// map](DistanceMap::flee). Values below `-1` make fleeing monsters prefer
+    /// running past the goal to a far away spot over getting cornered close
+    /// by.
+    pub const FLEE_COEFFICIENT: f64 = -1.2;
+
+    /// Creates the distance map for the tiles, with the goals at the provided
+    /// points. Goals that are solid, locked or out of bounds are ignored.
+    pub(crate) fn new(
+        tiles: &[DungeonTile],
+        width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        goals: impl IntoIterator<Item = Point>,
+    ) -> Self {
+        Self::with_walkable(width, height, goals, |index| {
+            !tiles[index].is_solid() && !tiles[index].is_locked()
+        })
+    }
+
+    /// Creates the distance map for the tiles like [`DistanceMap::new`], but
+    /// walking through locked doors as if their keys had already been picked
+    /// up.
+    pub(crate) fn through_locked_doors(
+        tiles: &[DungeonTile],
+        width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        goals: impl IntoIterator<Item = Point>,
+    ) -> Self {
+        Self::with_walkable(width, height, goals, |index| !tiles[index].is_solid())
+    }
+
+    /// Creates the distance map over the walkable tiles, with the goals at the
+    /// provided points. Goals that aren't walkable or are out of bounds are
+    /// ignored.
+    fn with_walkable(
+        width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        goals: impl IntoIterator<Item = Point>,
+        walkable: impl Fn(usize) -> bool,
+    ) -> Self {
+        let mut map = Self {
+            width,
+            height,
+            values: vec![None; (width.as_unbounded() * height.as_unbounded()) as usize],
+        };
+
+        let goals = goals
+            .into_iter()
+            .filter_map(|goal| goal.index_in(width, height))
+            .filter(|&index| walkable(index))
+            .map(|index| (index, 0))
+            .collect();
+
+        map.values = map.relax(goals, walkable);
+        map
+    }
+
+    /// Returns the value of the tile at the point, or [`None`] if it can't
+    /// reach any of the goals (or is outside of the map).
+    #[must_use]
+    pub fn get(&self, point: Point) -> Option<i32> {
+        point
+            .index_in(self.width, self.height)
+            .and_then(|index| self.values[index])
+    }
+
+    /// Returns the values of every tile, row by row.
+    #[must_use]
+    pub fn values(&self) -> &[Option<i32>] {
+        &self.values
+    }
+
+    /// Returns the neighbour of the point with the lowest value, if it's lower
+    /// than the value of the point itself. Returns [`None`] if the point is a
+    /// goal (or otherwise has no lower neighbours), or if it can't reach any
+    /// of the goals.
+    ///
+    /// Ties are broken in the order up, down, left, right, so the same point
+    /// always rolls the same way.
+    #[must_use]
+    pub fn downhill(&self, from: Point) -> Option<Point> {
+        let index = from.index_in(self.width, self.height)?;
+        let value = self.values[index]?;
+
+        let width = self.width.as_unbounded() as usize;
+        let height = self.height.as_unbounded() as usize;
+        let (next, _) = Point::neighbour_indices(index, width, height)
+            .filter_map(|neighbour| self.values[neighbour].map(|value| (neighbour, value)))
+            .filter(|&(_, neighbour_value)| neighbour_value < value)
+            .min_by_key(|&(_, value)| value)?;
+
+        Point::from_index(next, width).ok()
+    }
+
+    /// Creates a flee map from the distance map: following it downhill leads
+    /// away from the goals, while still avoiding dead ends close to them.
+    ///
+    /// Every value is multiplied by [`DistanceMap::FLEE_COEFFICIENT`], and
+    /// then the map is rescanned so that the values flow smoothly again.
+    #[must_use]
+    pub fn flee(&self) -> Self {
+        let initial = self
+            .values
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| {
+                value.map(|value| (index, (f64::from(value) * Self::FLEE_COEFFICIENT) as i32))
+            })
+            .collect();
+
+        Self {
+            width: self.width,
+            height: self.height,
+            values: self.relax(initial, |index| self.values[index].is_some()),
+        }
+    }
+
+    /// Runs dijkstra's algorithm out from the initial values over the
+    /// walkable tiles, so that every tile ends up with the lowest value of
+    /// it's neighbours plus one (or it's initial value, if that is lower).
+    fn relax(
+        &self,
+        initial: Vec<(usize, i32)>,
+        walkable: impl Fn(usize) -> bool,
+    ) -> Vec<Option<i32>> {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.height.as_unbounded() as usize;
+
+        let mut values = vec![None; self.values.len()];
+        let mut heap = BinaryHeap::new();
+
+        for (index, value) in initial {
+            if values[index].is_none_or(|current| value < current) {
+                values[index] = Some(value);
+                heap.push(Reverse((value, index)));
+            }
+        }
+
+        while let Some(Reverse((value, index))) = heap.pop() {
+            if values[index] != Some(value) {
+                continue;
+            }
+
+            for neighbour in Point::neighbour_indices(index, width, height) {
+                if walkable(neighbour)
+                    && values[neighbour].is_none_or(|current| value + 1 < current)
+                {
+                    values[neighbour] = Some(value + 1);
+                    heap.push(Reverse((value + 1, neighbour)));
+                }
+            }
+        }
+
+        values
+    }
+}
+
+impl Floor {
+    /// Creates a [`DistanceMap`] of the walking distance from every tile to
+    /// the closest of the goals.
+    #[must_use]
+    pub fn distance_map(&self, goals: impl IntoIterator<Item = Point>) -> DistanceMap {
+        DistanceMap::new(&self.data, self.width, self.height, goals)
+    }
+}
+
+#[cfg(test)]
+mod test_distance_map {
+    use std::{convert::TryInto, num::NonZeroU16};
+
+    use super::*;
+    use crate::{
+        test_utils::{floor_from_rows, point},
+        BspGenerator, DrunkardsWalkGenerator, Dungeon, DungeonType, FloorAlgorithm, FloorId,
+        GenerationConfig, GenerationError, MazeGenerator, Seed, WfcGenerator,
+    };
+
+    #[test]
+    fn test_distance_maps() {
+        let floor = floor_from_rows(&[
+            "##########",
+            "#....#...#",
+            "#....#...#",
+            "#....#####",
+            "#........#",
+            "#........#",
+            "#........#",
+            "#........#",
+            "#........#",
+            "##########",
+        ]);
+
+        let map = floor.distance_map([point(1, 1)]);
+        assert_eq!(map.get(point(1, 1)), Some(0));
+        assert_eq!(map.get(point(8, 8)), Some(14));
+        assert_eq!(map.get(point(0, 0)), None);
+        // walled off from the goal
+        assert_eq!(map.get(point(1, 7)), None);
+
+        // the closest goal wins
+        let two_goals = floor.distance_map([point(1, 1), point(8, 8)]);
+        assert_eq!(two_goals.get(point(8, 7)), Some(1));
+        assert_eq!(two_goals.get(point(2, 1)), Some(1));
+
+        let mut position = point(8, 8);
+        let mut steps = 0;
+        while let Some(next) = map.downhill(position) {
+            assert_eq!(map.get(next), map.get(position).map(|value| value - 1));
+            position = next;
+            steps += 1;
+        }
+        assert_eq!((position, steps), (point(1, 1), 14));
+
+        // fleeing always leads further away from the goal
+        let flee = map.flee();
+        let mut position = point(2, 2);
+        while let Some(next) = flee.downhill(position) {
+            position = next;
+        }
+        assert!(map.get(position).unwrap() > map.get(point(2, 2)).unwrap());
+        assert_eq!(flee.get(point(1, 7)), None);
+    }
+
+    #[test]
+    fn test_min_exit_distance() {
+        let config = |min_exit_distance| GenerationConfig {
+            algorithms: vec![
+                FloorAlgorithm::CellularAutomata,
+                FloorAlgorithm::Bsp(BspGenerator::default()),
+                FloorAlgorithm::DrunkardsWalk(DrunkardsWalkGenerator::default()),
+                FloorAlgorithm::Maze(MazeGenerator::default()),
+                FloorAlgorithm::Wfc(WfcGenerator::default()),
+            ],
+            min_exit_distance,
+            ..GenerationConfig::default()
+        };
+
+        for seed in 0..3 {
+            let dungeon = Dungeon::try_new(
+                40.try_into().unwrap(),
+                40.try_into().unwrap(),
+                NonZeroU16::new(5).unwrap(),
+                DungeonType::Cave,
+                &config(30),
+                Seed::new(seed),
+                false,
+            )
+            .unwrap();
+
+            for floor in &dungeon.floors {
+                let distances = floor.distance_map([floor.entrance().unwrap()]);
+                assert!(distances.get(floor.exit().unwrap()).unwrap() >= 30);
+            }
+        }
+
+        assert!(matches!(
+            Floor::try_new(
+                FloorId::new(1),
+                DungeonType::Cave,
+                20.try_into().unwrap(),
+                20.try_into().unwrap(),
+                &config(1000),
+                Seed::new(0),
+                false,
+            ),
+            Err(GenerationError::ExitTooClose { min: 1000, .. })
+        ));
+    }
+}
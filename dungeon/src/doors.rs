@@ -0,0 +1,330 @@
+use std::{
+    collections::{BTreeSet, VecDeque},
+    error::Error,
+    fmt,
+};
+
+use crate::{DungeonTile, Floor, Point};
+
+/// Error returned when interacting with a door of a [`Floor`] fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoorError {
+    /// The point is outside of the floor.
+    OutOfBounds(Point),
+    /// The tile at the point isn't a [`DungeonTile::SecretDoor`].
+    NotADoor(Point),
+    /// The door is locked, and has to be [unlocked](Floor::unlock_door)
+    /// before it can be opened.
+    Locked(Point),
+}
+
+impl fmt::Display for DoorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DoorError::OutOfBounds(point) => {
+                f.write_fmt(format_args!("{:?} is outside of the floor", point))
+            }
+            DoorError::NotADoor(point) => {
+                f.write_fmt(format_args!("there is no door at {:?}", point))
+            }
+            DoorError::Locked(point) => {
+                f.write_fmt(format_args!("the door at {:?} is locked", point))
+            }
+        }
+    }
+}
+
+impl Error for DoorError {}
+
+impl Floor {
+    /// Opens the door at the point, making it passable. Opening a door that
+    /// is already open does nothing.
+    ///
+    /// # Errors
+    /// Errors if there is no door at the point, or if the door is locked.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use dungeon::{DoorError, DungeonType, Floor, FloorId, GenerationConfig, Seed};
+    /// use std::convert::TryInto;
+    ///
+    /// let mut floor = Floor::with_seed(
+    ///     FloorId::new(0),
+    ///     DungeonType::Cave,
+    ///     80.try_into().unwrap(),
+    ///     80.try_into().unwrap(),
+    ///     &GenerationConfig::default(),
+    ///     Seed::new(0),
+    ///     false,
+    /// );
+    ///
+    /// // every key on the floor unlocks one of it's locked doors
+    /// let keys = floor
+    ///     .iter_points_and_tiles()
+    ///     .filter(|(_, tile)| tile.is_key())
+    ///     .map(|(point, _)| point)
+    ///     .collect::<Vec<_>>();
+    ///
+    /// for key in keys {
+    ///     let door = floor.take_key(key).unwrap();
+    ///     assert_eq!(floor.open_door(door), Err(DoorError::Locked(door)));
+    ///
+    ///     floor.unlock_door(door).unwrap();
+    ///     floor.open_door(door).unwrap();
+    ///     assert!(floor.at(door).is_passable());
+    /// }
+    /// ```
+    pub fn open_door(&mut self, point: Point) -> Result<(), DoorError> {
+        match self.door_mut(point)? {
+            DungeonTile::SecretDoor {
+                requires_key: true,
+                is_open: false,
+            } => Err(DoorError::Locked(point)),
+            DungeonTile::SecretDoor { is_open, .. } => {
+                *is_open = true;
+                Ok(())
+            }
+            _ => Err(DoorError::NotADoor(point)),
+        }
+    }
+
+    /// Closes the door at the point, making it impassable again. Closed doors
+    /// don't lock again, so they can be reopened without a key. Closing a
+    /// door that is already closed does nothing.
+    ///
+    /// # Errors
+    /// Errors if there is no door at the point.
+    pub fn close_door(&mut self, point: Point) -> Result<(), DoorError> {
+        match self.door_mut(point)? {
+            DungeonTile::SecretDoor { is_open, .. } => {
+                *is_open = false;
+                Ok(())
+            }
+            _ => Err(DoorError::NotADoor(point)),
+        }
+    }
+
+    /// Unlocks the door at the point, so that it can be
+    /// [opened](Floor::open_door). Unlocking a door that isn't locked does
+    /// nothing.
+    ///
+    /// This doesn't check that the player has the key to the door; keep the
+    /// doors returned from [`Floor::take_key`] to know which doors can be
+    /// unlocked.
+    ///
+    /// # Errors
+    /// Errors if there is no door at the point.
+    pub fn unlock_door(&mut self, point: Point) -> Result<(), DoorError> {
+        match self.door_mut(point)? {
+            DungeonTile::SecretDoor { requires_key, .. } => {
+                *requires_key = false;
+                Ok(())
+            }
+            _ => Err(DoorError::NotADoor(point)),
+        }
+    }
+
+    /// Picks up the [key](DungeonTile::Key) at the point, replacing it with an
+    /// empty tile, and returns the point of the door it unlocks. Returns
+    /// [`None`] if there is no key at the point.
+    pub fn take_key(&mut self, point: Point) -> Option<Point> {
+        let index = self.index(point)?;
+
+        match self.data[index] {
+            DungeonTile::Key { door } => {
+                self.data[index] = DungeonTile::Empty;
+                Some(door)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the tile at the point, which should be a
+    /// door.
+    fn door_mut(&mut self, point: Point) -> Result<&mut DungeonTile, DoorError> {
+        let index = self.index(point).ok_or(DoorError::OutOfBounds(point))?;
+        Ok(&mut self.data[index])
+    }
+}
+
+/// Returns the first locked door (in row-major order) that can't be opened,
+/// because it's key can't be reached from the entrance without going through
+/// the door (or another door that can't be opened) first.
+///
+/// The player starts at the entrance, and can walk over everything that isn't
+/// [solid](DungeonTile::is_solid) except for locked doors. Every key that is
+/// walked over unlocks it's door, letting the player walk through it.
+pub(crate) fn first_locked_out_door(
+    tiles: &[DungeonTile],
+    width: usize,
+    entrance: usize,
+) -> Option<Point> {
+    let height = tiles.len() / width;
+
+    let mut reached = vec![false; tiles.len()];
+    reached[entrance] = true;
+    let mut queue = VecDeque::from(vec![entrance]);
+
+    // the locked doors that have been walked up to, and the doors that the
+    // keys picked up so far unlock
+    let mut waiting = BTreeSet::new();
+    let mut keys = BTreeSet::new();
+
+    loop {
+        while let Some(index) = queue.pop_front() {
+            if let DungeonTile::Key { door } = tiles[index] {
+                // keys to doors outside of the floor don't unlock anything
+                if (door.column.get().as_unbounded() as usize) < width {
+                    keys.insert(door.to_index(width));
+                }
+            }
+
+            for neighbour in Point::neighbour_indices(index, width, height) {
+                if reached[neighbour] || tiles[neighbour].is_solid() {
+                    continue;
+                }
+                reached[neighbour] = true;
+
+                if tiles[neighbour].is_locked() {
+                    waiting.insert(neighbour);
+                } else {
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        match waiting.intersection(&keys).next().copied() {
+            Some(door) => {
+                waiting.remove(&door);
+                queue.push_back(door);
+            }
+            None => {
+                return waiting
+                    .iter()
+                    .next()
+                    .and_then(|&door| Point::from_index(door, width).ok())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_doors {
+    use std::convert::TryInto;
+
+    use super::*;
+    use crate::{
+        test_utils::{cave_floor, floor_from_rows, point},
+        DungeonType, FloorGenerator, FloorId, FloorRequest, GenerationConfig, GenerationError,
+        PathOptions, Seed,
+    };
+
+    #[test]
+    fn test_doors_and_keys() {
+        let mut floor = floor_from_rows(&[
+            "##########",
+            "#<...#...#",
+            "#....L.>.#",
+            "#....#...#",
+            "##D#######",
+            "#........#",
+            "#........#",
+            "#........#",
+            "#........#",
+            "##########",
+        ]);
+
+        /// Always generates the same floor.
+        struct Fixed(Floor);
+
+        impl FloorGenerator for Fixed {
+            fn generate(&self, _: &FloorRequest<'_>) -> Result<Floor, GenerationError> {
+                Ok(self.0.clone())
+            }
+        }
+
+        let config = GenerationConfig::default();
+        let request = FloorRequest {
+            id: FloorId::new(0),
+            dungeon_type: DungeonType::Cave,
+            width: 10.try_into().unwrap(),
+            height: 10.try_into().unwrap(),
+            config: &config,
+            seed: Seed::new(0),
+            entrance: None,
+            gif_output: false,
+        };
+
+        let (locked, door) = (point(2, 5), point(4, 2));
+
+        // the key is behind the door it unlocks
+        *floor.at_mut(point(1, 7)) = DungeonTile::Key { door: locked };
+        assert!(matches!(
+            Floor::try_with_generator(&Fixed(floor.clone()), &request),
+            Err(GenerationError::LockedOut(found)) if found == locked
+        ));
+
+        // the key is behind an unlocked door
+        *floor.at_mut(point(1, 7)) = DungeonTile::Empty;
+        *floor.at_mut(point(7, 7)) = DungeonTile::Key { door: locked };
+        assert!(Floor::try_with_generator(&Fixed(floor.clone()), &request).is_ok());
+
+        assert!(!floor.at(door).is_passable());
+        assert_eq!(floor.open_door(door), Ok(()));
+        assert!(floor.at(door).is_passable());
+        assert_eq!(floor.close_door(door), Ok(()));
+        assert!(!floor.at(door).is_passable());
+
+        assert_eq!(floor.open_door(locked), Err(DoorError::Locked(locked)));
+        assert_eq!(floor.take_key(point(7, 6)), None);
+        assert_eq!(floor.take_key(point(7, 7)), Some(locked));
+        assert_eq!(floor.take_key(point(7, 7)), None);
+        assert_eq!(floor.unlock_door(locked), Ok(()));
+        assert_eq!(floor.open_door(locked), Ok(()));
+        assert!(floor.at(locked).is_passable());
+
+        assert_eq!(
+            floor.open_door(point(1, 1)),
+            Err(DoorError::NotADoor(point(1, 1)))
+        );
+        assert_eq!(
+            floor.close_door(point(3, 12)),
+            Err(DoorError::OutOfBounds(point(3, 12)))
+        );
+    }
+
+    #[test]
+    fn test_generated_keys_open_every_door() {
+        for seed in 0..5 {
+            let mut floor = cave_floor(80, &GenerationConfig::default(), seed);
+
+            let keys = floor
+                .iter_points_and_tiles()
+                .filter(|(_, tile)| tile.is_key())
+                .map(|(point, _)| point)
+                .collect::<Vec<_>>();
+            let doors = floor
+                .iter_points_and_tiles()
+                .filter(|(_, tile)| tile.is_locked())
+                .count();
+            assert_eq!(keys.len(), doors);
+
+            for key in keys {
+                let door = floor.take_key(key).unwrap();
+                floor.unlock_door(door).unwrap();
+                floor.open_door(door).unwrap();
+            }
+
+            // with every door open, the whole floor can be walked
+            let entrance = floor.entrance().unwrap();
+            let distances = floor.distance_map([entrance]);
+            assert!(floor
+                .iter_points_and_tiles()
+                .filter(|(_, tile)| tile.is_passable())
+                .all(|(point, _)| floor
+                    .path(entrance, point, &PathOptions::default())
+                    .is_some()
+                    && distances.get(point).is_some()));
+        }
+    }
+}
@@ -1,32 +1,46 @@
 use serde::{Deserialize, Serialize};
 
-use crate::floor_builder::to_block_character::ToAsciiCharacter;
+use crate::{floor_builder::to_block_character::ToAsciiCharacter, ChestId, DungeonType, Point};
 
 /// The various things a tile can be in a dungeon floor.
 ///
 /// Note that these are just base map features. Players and mobs will interact
-/// with these tiles, but are not tiles themselves.
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+/// with these tiles, but are not tiles themselves; they live in the
+/// [`Floor::entities`](crate::Floor::entities) layer instead.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub enum DungeonTile {
     /// Empty space. Traversable.
+    #[default]
     Empty,
     /// Solid wall. Not traversable.
     Wall,
     /// A secret door to a secret passageway. May or may not require a key to
     /// open.
+    ///
+    /// See [`Floor::open_door`](crate::Floor::open_door) and
+    /// [`Floor::unlock_door`](crate::Floor::unlock_door).
     SecretDoor {
-        /// Whether or not the door requires a key to open.
+        /// Whether or not the door requires a key to open. Every locked door
+        /// on a generated floor has exactly one [`DungeonTile::Key`], which
+        /// can be reached without going through the door.
         requires_key: bool,
         /// Whether or not the door is open. All doors start off closed.
         is_open: bool,
     },
     /// A secret passageway between two secret doors.
     SecretPassage,
-    /// A treasure chest that will at some point contain something.
-    // TODO: Add treasure (lol)
+    /// A treasure chest, filled with items from the
+    /// [`LootTable`](crate::LootTable) when the floor is generated.
     TreasureChest {
-        /// Placeholder for now to make refactoring easier as fields are added.
-        contents: (),
+        /// The id of the chest's contents. See
+        /// [`Floor::chest`](crate::Floor::chest).
+        contents: ChestId,
+    },
+    /// A key that unlocks a locked [`DungeonTile::SecretDoor`]. Traversable;
+    /// see [`Floor::take_key`](crate::Floor::take_key).
+    Key {
+        /// The point of the door the key unlocks.
+        door: Point,
     },
     /// Entrance to the floor. Both entrances and exits are to be one-way paths;
     /// once you leave a floor you cannot go back to it.
@@ -41,7 +55,7 @@ impl DungeonTile {
     /// Color map for use in exporting the floor to a gif.
     ///
     /// See [`DungeonTile::as_u8`].
-    pub const COLOR_MAP: [u8; 21] = [
+    pub const COLOR_MAP: [u8; 24] = [
         0xFF, 0xFF, 0xFF, // black
         0x00, 0x00, 0x00, // white
         0xFF, 0x00, 0x00, // red
@@ -49,8 +63,33 @@ impl DungeonTile {
         0x00, 0x00, 0xFF, // blue
         0xFF, 0x00, 0xFF, // purple
         0xAA, 0x40, 0x00, // yellow
+        0x00, 0xFF, 0xFF, // cyan
+    ];
+
+    /// Color map for use in exporting a [`DungeonType::Forest`] floor to a
+    /// gif. Walls are the trees of the forest.
+    ///
+    /// See [`DungeonTile::as_u8`].
+    pub const FOREST_COLOR_MAP: [u8; 24] = [
+        0x9A, 0xC8, 0x6B, // grass
+        0x1E, 0x4D, 0x2B, // trees
+        0x8B, 0x45, 0x13, // brown
+        0xC2, 0xB2, 0x80, // sand
+        0xFF, 0xD7, 0x00, // gold
+        0xFF, 0x00, 0xFF, // purple
+        0x00, 0x00, 0xFF, // blue
+        0x00, 0xFF, 0xFF, // cyan
     ];
 
+    /// Returns the color map for the provided [`DungeonType`].
+    #[must_use]
+    pub const fn color_map(dungeon_type: DungeonType) -> &'static [u8; 24] {
+        match dungeon_type {
+            DungeonType::Cave => &Self::COLOR_MAP,
+            DungeonType::Forest => &Self::FOREST_COLOR_MAP,
+        }
+    }
+
     /// Returns the u8 value of the tile for use in exporting to gif.
     ///
     /// This operation is lossy; any variant with attached information
@@ -66,6 +105,7 @@ impl DungeonTile {
             DungeonTile::TreasureChest { .. } => 4,
             DungeonTile::Entrance => 5,
             DungeonTile::Exit => 6,
+            DungeonTile::Key { .. } => 7,
         }
     }
 
@@ -99,6 +139,12 @@ impl DungeonTile {
         matches!(self, Self::TreasureChest { .. })
     }
 
+    /// Returns `true` if `self` is [`DungeonTile::Key`].
+    #[must_use]
+    pub const fn is_key(self) -> bool {
+        matches!(self, Self::Key { .. })
+    }
+
     /// Returns `true` if `self` is [`DungeonTile::Entrance`].
     #[must_use]
     pub const fn is_entrance(self) -> bool {
@@ -116,11 +162,37 @@ impl DungeonTile {
     pub const fn is_solid(self) -> bool {
         matches!(self, DungeonTile::Wall | DungeonTile::TreasureChest { .. })
     }
-}
 
-impl Default for DungeonTile {
-    fn default() -> Self {
-        Self::Empty
+    /// Returns `true` if `self` is a [`DungeonTile::SecretDoor`] that is
+    /// closed and requires a key to open.
+    #[must_use]
+    pub const fn is_locked(self) -> bool {
+        matches!(
+            self,
+            DungeonTile::SecretDoor {
+                requires_key: true,
+                is_open: false
+            }
+        )
+    }
+
+    /// Returns whether or not the tile can be walked onto right now. Unlike
+    /// [`DungeonTile::is_solid`], closed secret doors can't be walked
+    /// through until they are opened.
+    #[must_use]
+    pub const fn is_passable(self) -> bool {
+        !self.is_solid() && !matches!(self, DungeonTile::SecretDoor { is_open: false, .. })
+    }
+
+    /// Returns whether or not the tile hides the tiles behind it. Walls and
+    /// closed secret doors block sight; treasure chests are solid, but can be
+    /// seen over.
+    #[must_use]
+    pub const fn blocks_sight(self) -> bool {
+        matches!(
+            self,
+            DungeonTile::Wall | DungeonTile::SecretDoor { is_open: false, .. }
+        )
     }
 }
 
@@ -135,6 +207,7 @@ impl ToAsciiCharacter for DungeonTile {
             DungeonTile::TreasureChest { .. } => ['T', 'C'],
             DungeonTile::Entrance => ['E', 'N'],
             DungeonTile::Exit => ['E', 'X'],
+            DungeonTile::Key { .. } => ['K', 'Y'],
         }
     }
 }
@@ -0,0 +1,480 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    error::Error,
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Floor, Point};
+
+/// A unique, opaque ID assigned to each [`Entity`] on a floor when it is
+/// spawned. IDs are never reused on the same floor, even after the entity is
+/// removed; once every ID has been handed out, no more entities can be
+/// spawned on the floor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EntityId(u32);
+
+impl EntityId {
+    /// Creates a new [`EntityId`] with the given id.
+    #[must_use]
+    pub const fn new(id: u32) -> Self {
+        Self(id)
+    }
+
+    /// Returns the inner value.
+    #[must_use]
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+}
+
+/// What kind of thing an [`Entity`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum EntityKind {
+    /// The player.
+    Player,
+    /// A monster.
+    Monster,
+    /// An item lying on the ground.
+    Item,
+    /// Anything else that sits on the map, like furniture or a trap.
+    Prop,
+}
+
+/// Something that lives on a [`Floor`] without being a part of the map, like
+/// the player, a monster or an item; see [`Entities`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entity {
+    /// The id of the entity.
+    id: EntityId,
+    /// The point the entity is at. Kept private so that it can't get out of
+    /// sync with the spatial index of [`Entities`]; see
+    /// [`Floor::move_entity`].
+    point: Point,
+    /// What kind of thing the entity is.
+    pub kind: EntityKind,
+    /// Arbitrary data attached to the entity by the game, such as health or
+    /// the name of the monster, keyed by the name of the component.
+    #[serde(default)]
+    pub components: BTreeMap<String, serde_json::Value>,
+}
+
+impl Entity {
+    /// Returns the id of the entity.
+    #[must_use]
+    pub const fn id(&self) -> EntityId {
+        self.id
+    }
+
+    /// Returns the point the entity is at.
+    #[must_use]
+    pub const fn point(&self) -> Point {
+        self.point
+    }
+}
+
+/// The sparse layer of [entities](Entity) on a [`Floor`], kept separately
+/// from the tiles of the map.
+///
+/// Entities are spawned, moved and removed through the floor (see
+/// [`Floor::spawn_entity`]), which checks that they stay inside of it, and can
+/// be looked up by id or by where they are. Every entity is serialized along
+/// with the floor.
+///
+/// # Examples
+/// ```rust
+/// use dungeon::{
+///     DungeonType, EntityKind, Floor, FloorId, GenerationConfig, Point, Seed,
+/// };
+/// use std::convert::TryInto;
+///
+/// let mut floor = Floor::with_seed(
+///     FloorId::new(0),
+///     DungeonType::Cave,
+///     50.try_into().unwrap(),
+///     50.try_into().unwrap(),
+///     &GenerationConfig::default(),
+///     Seed::new(0),
+///     false,
+/// );
+///
+/// let entrance = floor.entrance().unwrap();
+/// let player = floor.spawn_entity(EntityKind::Player, entrance).unwrap();
+/// floor
+///     .entities
+///     .get_mut(player)
+///     .unwrap()
+///     .components
+///     .insert("health".to_string(), 10.into());
+///
+/// assert_eq!(floor.entities.at(entrance).count(), 1);
+///
+/// let next = entrance.saturating_add_column(1);
+/// floor.move_entity(player, next).unwrap();
+/// assert_eq!(floor.entities.at(entrance).count(), 0);
+/// assert_eq!(floor.entities.within(entrance, 1).count(), 1);
+///
+/// let player = floor.remove_entity(player).unwrap();
+/// assert_eq!(player.components["health"], 10);
+/// assert!(floor.entities.is_empty());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "SavedEntities", into = "SavedEntities")]
+pub struct Entities {
+    /// Every entity, by id.
+    entities: BTreeMap<EntityId, Entity>,
+    /// The ids of the entities at every point that has any.
+    by_point: BTreeMap<Point, BTreeSet<EntityId>>,
+    /// The id the next entity spawned gets.
+    next_id: u32,
+}
+
+impl Entities {
+    /// Returns the entity with the id, if it's on the floor.
+    #[must_use]
+    pub fn get(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.get(&id)
+    }
+
+    /// Returns a mutable reference to the entity with the id, if it's on the
+    /// floor.
+    #[must_use]
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.entities.get_mut(&id)
+    }
+
+    /// Returns an iterator over every entity, ordered by id.
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> + '_ {
+        self.entities.values()
+    }
+
+    /// Returns an iterator over the entities at the point, ordered by id.
+    pub fn at(&self, point: Point) -> impl Iterator<Item = &Entity> + '_ {
+        self.by_point
+            .get(&point)
+            .into_iter()
+            .flatten()
+            .map(move |id| &self.entities[id])
+    }
+
+    /// Returns an iterator over the entities within the radius of the point
+    /// (as the crow flies, including the point itself), ordered by their
+    /// point.
+    pub fn within(&self, center: Point, radius: u16) -> impl Iterator<Item = &Entity> + '_ {
+        let radius = i32::from(radius);
+        let (row, column) = (
+            center.row.get().as_unbounded(),
+            center.column.get().as_unbounded(),
+        );
+
+        self.by_point
+            .iter()
+            .filter(move |(point, _)| {
+                let row = point.row.get().as_unbounded() - row;
+                let column = point.column.get().as_unbounded() - column;
+                row * row + column * column <= radius * radius
+            })
+            .flat_map(|(_, ids)| ids)
+            .map(move |id| &self.entities[id])
+    }
+
+    /// Returns the amount of entities.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns `true` if there are no entities.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Adds a new entity at the point, returning it's id.
+    ///
+    /// # Errors
+    /// Errors if every id has already been handed out.
+    fn spawn(&mut self, kind: EntityKind, point: Point) -> Result<EntityId, EntityError> {
+        let id = EntityId::new(self.next_id);
+        self.next_id = self.next_id.checked_add(1).ok_or(EntityError::OutOfIds)?;
+
+        self.insert(Entity {
+            id,
+            point,
+            kind,
+            components: BTreeMap::new(),
+        });
+        Ok(id)
+    }
+
+    /// Adds the entity, indexing it by it's point.
+    fn insert(&mut self, entity: Entity) {
+        self.by_point
+            .entry(entity.point)
+            .or_default()
+            .insert(entity.id);
+        self.entities.insert(entity.id, entity);
+    }
+
+    /// Removes the entity with the id, if it's on the floor.
+    fn remove(&mut self, id: EntityId) -> Option<Entity> {
+        let entity = self.entities.remove(&id)?;
+
+        if let Some(ids) = self.by_point.get_mut(&entity.point) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.by_point.remove(&entity.point);
+            }
+        }
+
+        Some(entity)
+    }
+}
+
+/// How [`Entities`] are serialized; the spatial index is rebuilt when they
+/// are loaded, which fails if two entities have the same id or if an entity
+/// has an id that could never have been handed out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SavedEntities {
+    /// The id the next entity spawned gets.
+    next_id: u32,
+    /// Every entity, ordered by id.
+    entities: Vec<Entity>,
+}
+
+impl TryFrom<SavedEntities> for Entities {
+    type Error = EntityError;
+
+    fn try_from(saved: SavedEntities) -> Result<Self, Self::Error> {
+        let mut entities = Self {
+            next_id: saved.next_id,
+            ..Self::default()
+        };
+
+        for entity in saved.entities {
+            if entities.entities.contains_key(&entity.id) {
+                return Err(EntityError::DuplicateId(entity.id));
+            }
+
+            // never hand out an id that is already taken, even if the saved
+            // next id is off
+            let after = entity
+                .id
+                .get()
+                .checked_add(1)
+                .ok_or(EntityError::OutOfIds)?;
+            entities.next_id = entities.next_id.max(after);
+
+            entities.insert(entity);
+        }
+
+        Ok(entities)
+    }
+}
+
+impl From<Entities> for SavedEntities {
+    fn from(entities: Entities) -> Self {
+        Self {
+            next_id: entities.next_id,
+            entities: entities.entities.into_values().collect(),
+        }
+    }
+}
+
+/// Error returned when spawning or moving an [`Entity`] fails, or when
+/// loading [`Entities`] fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityError {
+    /// The point is outside of the floor.
+    OutOfBounds(Point),
+    /// There is no entity with the id on the floor.
+    NoSuchEntity(EntityId),
+    /// Every id has already been handed out on the floor.
+    OutOfIds,
+    /// More than one of the loaded entities had the id.
+    DuplicateId(EntityId),
+}
+
+impl fmt::Display for EntityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntityError::OutOfBounds(point) => {
+                f.write_fmt(format_args!("{:?} is outside of the floor", point))
+            }
+            EntityError::NoSuchEntity(id) => {
+                f.write_fmt(format_args!("there is no entity with id {}", id.get()))
+            }
+            EntityError::OutOfIds => f.write_str("every entity id has already been handed out"),
+            EntityError::DuplicateId(id) => {
+                f.write_fmt(format_args!("more than one entity has id {}", id.get()))
+            }
+        }
+    }
+}
+
+impl Error for EntityError {}
+
+impl Floor {
+    /// Spawns a new entity of the kind at the point, with no components, and
+    /// returns it's id.
+    ///
+    /// # Errors
+    /// Errors if the point is outside of the floor, or if every id has
+    /// already been handed out on the floor.
+    pub fn spawn_entity(
+        &mut self,
+        kind: EntityKind,
+        point: Point,
+    ) -> Result<EntityId, EntityError> {
+        self.index(point).ok_or(EntityError::OutOfBounds(point))?;
+        self.entities.spawn(kind, point)
+    }
+
+    /// Moves the entity with the id to the point. Entities can be moved
+    /// anywhere in the floor, including solid tiles; it's up to the game to
+    /// check where they can go.
+    ///
+    /// # Errors
+    /// Errors if the point is outside of the floor, or if there is no entity
+    /// with the id.
+    pub fn move_entity(&mut self, id: EntityId, to: Point) -> Result<(), EntityError> {
+        self.index(to).ok_or(EntityError::OutOfBounds(to))?;
+
+        let mut entity = self
+            .entities
+            .remove(id)
+            .ok_or(EntityError::NoSuchEntity(id))?;
+        entity.point = to;
+        self.entities.insert(entity);
+
+        Ok(())
+    }
+
+    /// Removes the entity with the id from the floor and returns it, or
+    /// [`None`] if there is no entity with the id.
+    pub fn remove_entity(&mut self, id: EntityId) -> Option<Entity> {
+        self.entities.remove(id)
+    }
+}
+
+#[cfg(test)]
+mod test_entities {
+    use super::*;
+    use crate::test_utils::{floor_from_rows, point};
+
+    #[test]
+    fn test_spawn_when_out_of_ids() {
+        let point = Point::from_index(11, 10).unwrap();
+        let mut entities = Entities {
+            next_id: u32::MAX - 1,
+            ..Entities::default()
+        };
+
+        assert_eq!(
+            entities.spawn(EntityKind::Monster, point),
+            Ok(EntityId::new(u32::MAX - 1))
+        );
+        assert_eq!(
+            entities.spawn(EntityKind::Monster, point),
+            Err(EntityError::OutOfIds)
+        );
+        assert_eq!(entities.len(), 1);
+    }
+
+    #[test]
+    fn test_load_rejects_bad_ids() {
+        let saved = |ids: &[u32]| {
+            let entities = ids
+                .iter()
+                .map(|id| {
+                    format!(
+                        r#"{{"id":{},"point":{{"column":1,"row":1}},"kind":"Monster"}}"#,
+                        id
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!(r#"{{"next_id":0,"entities":[{}]}}"#, entities.join(","))
+        };
+
+        let entities = serde_json::from_str::<Entities>(&saved(&[0, 4])).unwrap();
+        assert_eq!(entities.len(), 2);
+        assert_eq!(entities.next_id, 5);
+
+        assert!(serde_json::from_str::<Entities>(&saved(&[3, 3])).is_err());
+        assert!(serde_json::from_str::<Entities>(&saved(&[u32::MAX])).is_err());
+    }
+
+    #[test]
+    fn test_entities() {
+        let mut floor = floor_from_rows(&[
+            "##########",
+            "#<.......#",
+            "#........#",
+            "#........#",
+            "#........#",
+            "#........#",
+            "#........#",
+            "#........#",
+            "#.......>#",
+            "##########",
+        ]);
+
+        let player = floor.spawn_entity(EntityKind::Player, point(1, 1)).unwrap();
+        let monster = floor
+            .spawn_entity(EntityKind::Monster, point(3, 6))
+            .unwrap();
+        let potion = floor.spawn_entity(EntityKind::Item, point(1, 1)).unwrap();
+        floor
+            .entities
+            .get_mut(monster)
+            .unwrap()
+            .components
+            .insert("health".to_string(), serde_json::json!(12));
+
+        assert_eq!(
+            floor.spawn_entity(EntityKind::Prop, point(50, 1)),
+            Err(EntityError::OutOfBounds(point(50, 1)))
+        );
+
+        let ids = |entities: Vec<&Entity>| entities.iter().map(|e| e.id()).collect::<Vec<_>>();
+        assert_eq!(
+            ids(floor.entities.at(point(1, 1)).collect()),
+            vec![player, potion]
+        );
+        assert_eq!(
+            ids(floor.entities.within(point(2, 2), 2).collect()),
+            vec![player, potion]
+        );
+        assert_eq!(floor.entities.within(point(2, 2), 5).count(), 3);
+
+        floor.move_entity(monster, point(2, 2)).unwrap();
+        assert_eq!(floor.entities.get(monster).unwrap().point(), point(2, 2));
+        assert_eq!(floor.entities.at(point(3, 6)).count(), 0);
+        assert_eq!(ids(floor.entities.at(point(2, 2)).collect()), vec![monster]);
+        assert_eq!(
+            floor.move_entity(monster, point(10, 2)),
+            Err(EntityError::OutOfBounds(point(10, 2)))
+        );
+
+        assert_eq!(floor.remove_entity(potion).unwrap().kind, EntityKind::Item);
+        assert_eq!(floor.remove_entity(potion), None);
+        assert_eq!(
+            floor.move_entity(potion, point(1, 1)),
+            Err(EntityError::NoSuchEntity(potion))
+        );
+        assert_eq!(floor.entities.len(), 2);
+
+        // the entities are saved with the floor, and ids aren't reused
+        let json = serde_json::to_string(&floor).unwrap();
+        let mut loaded = serde_json::from_str::<Floor>(&json).unwrap();
+        assert_eq!(loaded, floor);
+        assert_eq!(
+            loaded.entities.get(monster).unwrap().components["health"],
+            12
+        );
+        let prop = loaded.spawn_entity(EntityKind::Prop, point(4, 4)).unwrap();
+        assert!(prop > potion);
+    }
+}
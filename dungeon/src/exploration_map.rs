@@ -0,0 +1,190 @@
+use bounded_int::BoundedInt;
+use serde::{Deserialize, Serialize};
+
+use crate::{fov, Floor, Point, MAX_FLOOR_SIZE, MIN_FLOOR_SIZE};
+
+/// How much of a tile the player knows about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ExplorationState {
+    /// The tile has never been seen.
+    #[default]
+    Unseen,
+    /// The tile has been seen before, but isn't visible right now.
+    Remembered,
+    /// The tile is currently visible.
+    Visible,
+}
+
+impl ExplorationState {
+    /// Returns `true` if the tile has ever been seen, i.e. if it is either
+    /// [remembered](ExplorationState::Remembered) or
+    /// [visible](ExplorationState::Visible).
+    #[must_use]
+    pub const fn is_explored(self) -> bool {
+        !matches!(self, ExplorationState::Unseen)
+    }
+}
+
+/// The player's memory of a [`Floor`]: which tiles have never been seen, which
+/// have been seen before, and which are visible right now. Also known as fog
+/// of war.
+///
+/// Create one for a floor with [`ExplorationMap::new`] and update it with the
+/// player's [field of view](fov::field_of_view) every time they move. The map
+/// can be serialized alongside the [`Dungeon`](crate::Dungeon) to save the
+/// player's progress.
+///
+/// # Examples
+/// ```rust
+/// use dungeon::{
+///     DungeonType, ExplorationMap, ExplorationState, Floor, FloorId, GenerationConfig, Seed,
+/// };
+/// use std::convert::TryInto;
+///
+/// let floor = Floor::with_seed(
+///     FloorId::new(0),
+///     DungeonType::Cave,
+///     50.try_into().unwrap(),
+///     50.try_into().unwrap(),
+///     &GenerationConfig::default(),
+///     Seed::new(0),
+///     false,
+/// );
+///
+/// let entrance = floor.entrance().unwrap();
+/// let mut exploration = ExplorationMap::new(&floor);
+/// assert_eq!(exploration.get(entrance), ExplorationState::Unseen);
+///
+/// exploration.update_from_fov(&floor, entrance, Some(8));
+/// assert_eq!(exploration.get(entrance), ExplorationState::Visible);
+///
+/// // nothing is visible anymore, but the entrance is remembered
+/// exploration.update(None);
+/// assert_eq!(exploration.get(entrance), ExplorationState::Remembered);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExplorationMap {
+    /// The width of the floor the map is for.
+    width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+    /// The height of the floor the map is for.
+    height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+    /// The state of every tile, row by row.
+    states: Vec<ExplorationState>,
+}
+
+impl ExplorationMap {
+    /// Creates a new map for the floor, where none of the tiles have been seen
+    /// yet.
+    #[must_use]
+    pub fn new(floor: &Floor) -> Self {
+        Self {
+            width: floor.width,
+            height: floor.height,
+            states: vec![ExplorationState::Unseen; floor.data.len()],
+        }
+    }
+
+    /// Marks the provided points as [visible](ExplorationState::Visible).
+    /// Every other point that was visible before is now
+    /// [remembered](ExplorationState::Remembered).
+    ///
+    /// Points outside of the map are ignored. Updating with no points (e.g.
+    /// when the player leaves the floor) hides the whole floor, while keeping
+    /// everything that was seen remembered.
+    pub fn update(&mut self, visible: impl IntoIterator<Item = Point>) {
+        for state in &mut self.states {
+            if *state == ExplorationState::Visible {
+                *state = ExplorationState::Remembered;
+            }
+        }
+
+        for point in visible {
+            if let Some(index) = point.index_in(self.width, self.height) {
+                self.states[index] = ExplorationState::Visible;
+            }
+        }
+    }
+
+    /// Updates the map with the [field of view](fov::field_of_view) from the
+    /// origin. See [`ExplorationMap::update`].
+    ///
+    /// The floor should be the same floor the map was created for.
+    pub fn update_from_fov(&mut self, floor: &Floor, origin: Point, radius: Option<u16>) {
+        self.update(fov::field_of_view(floor, origin, radius));
+    }
+
+    /// Returns the state of the tile at the point. Points outside of the map
+    /// are always [unseen](ExplorationState::Unseen).
+    #[must_use]
+    pub fn get(&self, point: Point) -> ExplorationState {
+        point
+            .index_in(self.width, self.height)
+            .map_or(ExplorationState::Unseen, |index| self.states[index])
+    }
+
+    /// Returns the states of every tile, row by row.
+    #[must_use]
+    pub fn states(&self) -> &[ExplorationState] {
+        &self.states
+    }
+
+    /// Returns an iterator over every point that is currently in the provided
+    /// state.
+    pub fn points(&self, state: ExplorationState) -> impl Iterator<Item = Point> + '_ {
+        let width = self.width.as_unbounded() as usize;
+
+        self.states
+            .iter()
+            .enumerate()
+            .filter(move |&(_, &other)| other == state)
+            .filter_map(move |(index, _)| Point::from_index(index, width).ok())
+    }
+}
+
+#[cfg(test)]
+mod test_exploration_map {
+    use super::*;
+    use crate::test_utils::{floor_from_rows, point};
+
+    #[test]
+    fn test_exploration_map() {
+        let floor = floor_from_rows(&[
+            "##########",
+            "#....#...#",
+            "#....#...#",
+            "#....#...#",
+            "#....#...#",
+            "#....#...#",
+            "#........#",
+            "#........#",
+            "#........#",
+            "##########",
+        ]);
+
+        let mut exploration = ExplorationMap::new(&floor);
+        assert!(exploration
+            .states()
+            .iter()
+            .all(|&state| state == ExplorationState::Unseen));
+
+        exploration.update_from_fov(&floor, point(1, 1), None);
+        assert_eq!(exploration.get(point(1, 4)), ExplorationState::Visible);
+        assert_eq!(exploration.get(point(1, 7)), ExplorationState::Unseen);
+        assert_eq!(exploration.get(point(20, 20)), ExplorationState::Unseen);
+
+        // walk around the wall
+        exploration.update_from_fov(&floor, point(7, 8), None);
+        assert_eq!(exploration.get(point(1, 7)), ExplorationState::Visible);
+        assert_eq!(exploration.get(point(1, 1)), ExplorationState::Remembered);
+        assert!(exploration.get(point(1, 1)).is_explored());
+        assert!(exploration
+            .points(ExplorationState::Visible)
+            .all(|visible| fov::line_of_sight(&floor, point(7, 8), visible)));
+
+        let json = serde_json::to_string(&exploration).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ExplorationMap>(&json).unwrap(),
+            exploration
+        );
+    }
+}
@@ -5,34 +5,69 @@ use std::{
 
 use bounded_int::BoundedInt;
 use noise::{Billow, MultiFractal, NoiseFn, Seedable};
-use rand::{thread_rng, Rng};
+use rand::{prelude::SliceRandom, Rng};
 
 use crate::{
     floor_builder::{
-        floor_builder_state::random_filled::RandomFilled, MAX_FLOOR_SIZE, MIN_FLOOR_SIZE,
-        RANDOM_FILL_WALL_PERCENT_CHANCE,
+        floor_builder_state::random_filled::RandomFilled, wave_function_collapse::Model,
+        MAX_FLOOR_SIZE, MIN_FLOOR_SIZE,
     },
     point_index::PointIndex,
-    Column, DungeonTile, FloorBuilder, Point, Row,
+    world::NoiseOffset,
+    BspGenerator, Column, DrunkardsWalkGenerator, DungeonTile, FloorBuilder, FloorMask,
+    GenerationError, MazeGenerator, NoiseConfig, Point, Row, WfcGenerator,
 };
 
-use super::FloorBuilderState;
+use super::{carved::Carved, FloorBuilderState};
 
 /// A blank floor builder, with all values in the floor map and the noise map
 /// set to their default.
 #[derive(Debug)]
-pub(in crate::floor_builder) struct Blank {}
+pub(in crate::floor_builder) struct Blank {
+    /// Where the floor sits in a larger noise field, if it's a chunk of a
+    /// [`World`](crate::World). See [`FloorBuilder::with_noise_offset`].
+    pub(in crate::floor_builder) noise_offset: Option<NoiseOffset>,
+}
 impl FloorBuilderState for Blank {
     const TYPE_NAME: &'static str = "Blank";
 }
 
 impl FloorBuilder<Blank> {
+    /// Samples the noise map from the provided part of a larger noise field
+    /// instead of a noise field of it's own, so that the noise lines up with
+    /// the floors next to it.
+    pub(in crate::floor_builder) fn with_noise_offset(mut self, offset: NoiseOffset) -> Self {
+        self.extra.noise_offset = Some(offset);
+        self
+    }
+
+    /// Masks out every tile outside of the shape of the mask, so that they
+    /// are always walls. See [`FloorMask`].
+    pub(in crate::floor_builder) fn with_mask(mut self, mask: &FloorMask) -> Self {
+        self.mask = mask.solid_tiles(self.width, self.height);
+        self
+    }
+
+    /// Fills the noise map using the provided [`NoiseConfig`], and randomly
+    /// fills the map with walls.
+    ///
+    /// `wall_percent_chance` is called with the noise value at each point, and
+    /// returns the percent chance of a wall being placed at that point.
+    ///
     /// TODO: Split this function into two parts, `random_fill` and
     /// `trace_entrance_exit` (or something along those lines)
-    pub(in crate::floor_builder) fn random_fill(mut self) -> FloorBuilder<RandomFilled> {
-        let mut rng = thread_rng();
-
-        let mut noise = create_billow(&mut rng);
+    pub(in crate::floor_builder) fn random_fill(
+        mut self,
+        noise_config: &NoiseConfig,
+        wall_percent_chance: impl Fn(u16) -> u8,
+    ) -> FloorBuilder<RandomFilled> {
+        let mut noise = create_billow(&mut self.rng, noise_config);
+        let offset = self.extra.noise_offset;
+        if let Some(offset) = offset {
+            noise = noise.set_seed(offset.seed);
+        }
+        let (row_offset, column_offset) =
+            offset.map_or((0.0, 0.0), |offset| (offset.row, offset.column));
 
         // build initial maps (walls and noise)
         for column in self
@@ -50,8 +85,15 @@ impl FloorBuilder<Blank> {
                     row: Row::new(row),
                 };
 
-                *self.noise_map.at_mut(point, self.width) =
-                    get_noise_value(&mut noise, column, row, self.height, self.width);
+                let noise_value = get_noise_value(
+                    &mut noise,
+                    column,
+                    row,
+                    self.height,
+                    self.width,
+                    (row_offset, column_offset),
+                );
+                *self.noise_map.at_mut(point, self.width) = noise_value;
 
                 if self.is_out_of_bounds(point) {
                     *self.map.at_mut(point, self.width) = DungeonTile::Wall;
@@ -60,7 +102,7 @@ impl FloorBuilder<Blank> {
 
                 // make a wall some percent of the time
                 *self.map.at_mut(point, self.width) =
-                    if rng.gen_range(0..=100) <= RANDOM_FILL_WALL_PERCENT_CHANCE {
+                    if self.rng.gen_range(0..=100) <= wall_percent_chance(noise_value) {
                         DungeonTile::Wall
                     } else {
                         DungeonTile::Empty
@@ -77,20 +119,383 @@ impl FloorBuilder<Blank> {
             width: self.width,
             map: self.map,
             noise_map: self.noise_map,
+            mask: self.mask,
+            vaults: self.vaults,
+            frames: self.frames,
+            id: self.id,
+            rng: self.rng,
+        }
+    }
+}
+
+impl FloorBuilder<Blank> {
+    /// Carves rooms out of solid walls using binary space partitioning. See
+    /// [`BspGenerator`].
+    pub(in crate::floor_builder) fn carve_bsp(
+        mut self,
+        generator: &BspGenerator,
+    ) -> FloorBuilder<Carved> {
+        self.fill_with_walls();
+
+        let area = Area {
+            row: 1,
+            column: 1,
+            height: self.height.as_unbounded() as usize - 2,
+            width: self.width.as_unbounded() as usize - 2,
+        };
+        self.carve_bsp_area(area, generator);
+
+        self.into_carved()
+    }
+
+    /// Splits the area in two if it's big enough, carving each half and
+    /// joining them with a corridor. Otherwise, carves a single room into the
+    /// area.
+    ///
+    /// Returns the centre of one of the rooms carved into the area, so that it
+    /// can be joined to it's sibling.
+    fn carve_bsp_area(&mut self, area: Area, generator: &BspGenerator) -> (usize, usize) {
+        let min_leaf_size = usize::from(generator.min_leaf_size);
+
+        let can_split_rows = area.height >= min_leaf_size * 2;
+        let can_split_columns = area.width >= min_leaf_size * 2;
+
+        let split_rows = match (can_split_rows, can_split_columns) {
+            (false, false) => return self.carve_room(area, usize::from(generator.min_room_size)),
+            (true, false) => true,
+            (false, true) => false,
+            // prefer splitting long areas across their length, so that the
+            // leaves stay roughly square
+            (true, true) if area.height * 4 > area.width * 5 => true,
+            (true, true) if area.width * 4 > area.height * 5 => false,
+            (true, true) => self.rng.gen(),
+        };
+
+        let (first, second) = if split_rows {
+            let split = self
+                .rng
+                .gen_range(min_leaf_size..=area.height - min_leaf_size);
+            (
+                Area {
+                    height: split,
+                    ..area
+                },
+                Area {
+                    row: area.row + split,
+                    height: area.height - split,
+                    ..area
+                },
+            )
+        } else {
+            let split = self
+                .rng
+                .gen_range(min_leaf_size..=area.width - min_leaf_size);
+            (
+                Area {
+                    width: split,
+                    ..area
+                },
+                Area {
+                    column: area.column + split,
+                    width: area.width - split,
+                    ..area
+                },
+            )
+        };
+
+        let first = self.carve_bsp_area(first, generator);
+        let second = self.carve_bsp_area(second, generator);
+        self.carve_corridor(first, second);
+
+        if self.rng.gen() {
+            first
+        } else {
+            second
+        }
+    }
+
+    /// Carves a randomly sized room into the area, leaving at least a 1 tile
+    /// wall between the room and the edges of the area. Returns the centre of
+    /// the room.
+    fn carve_room(&mut self, area: Area, min_room_size: usize) -> (usize, usize) {
+        let height = self
+            .rng
+            .gen_range(min_room_size.min(area.height - 2)..=area.height - 2);
+        let width = self
+            .rng
+            .gen_range(min_room_size.min(area.width - 2)..=area.width - 2);
+        let row = area.row + 1 + self.rng.gen_range(0..=area.height - 2 - height);
+        let column = area.column + 1 + self.rng.gen_range(0..=area.width - 2 - width);
+
+        for row in row..row + height {
+            for column in column..column + width {
+                self.carve_tile(row, column);
+            }
+        }
+        self.frame_from_current_state(10);
+
+        (row + height / 2, column + width / 2)
+    }
+
+    /// Carves an L-shaped corridor between the two points, randomly picking
+    /// whether to go along the row or the column first.
+    fn carve_corridor(&mut self, from: (usize, usize), to: (usize, usize)) {
+        let corner = if self.rng.gen() {
+            (from.0, to.1)
+        } else {
+            (to.0, from.1)
+        };
+
+        for (start, end) in [(from, corner), (corner, to)] {
+            for row in start.0.min(end.0)..=start.0.max(end.0) {
+                for column in start.1.min(end.1)..=start.1.max(end.1) {
+                    self.carve_tile(row, column);
+                }
+            }
+        }
+        self.frame_from_current_state(10);
+    }
+
+    /// Carves a floor out of solid walls by randomly walking around it. See
+    /// [`DrunkardsWalkGenerator`].
+    pub(in crate::floor_builder) fn carve_drunkards_walk(
+        mut self,
+        generator: &DrunkardsWalkGenerator,
+    ) -> FloorBuilder<Carved> {
+        self.fill_with_walls();
+
+        let height = self.height.as_unbounded() as usize;
+        let width = self.width.as_unbounded() as usize;
+
+        let target =
+            ((height - 2) * (width - 2) * usize::from(generator.floor_percent) / 100).max(2);
+
+        let start = (height / 2, width / 2);
+        self.carve_tile(start.0, start.1);
+        let mut carved = vec![start];
+
+        while carved.len() < target {
+            let (mut row, mut column) = *carved.choose(&mut self.rng).unwrap();
+
+            for _ in 0..generator.walk_length {
+                match self.rng.gen_range(0..4) {
+                    0 if row > 1 => row -= 1,
+                    1 if row < height - 2 => row += 1,
+                    2 if column > 1 => column -= 1,
+                    3 if column < width - 2 => column += 1,
+                    _ => continue,
+                }
+
+                if self.map[row * width + column].is_wall() {
+                    self.carve_tile(row, column);
+                    carved.push((row, column));
+
+                    if carved.len() >= target {
+                        break;
+                    }
+                }
+            }
+            self.frame_from_current_state(10);
+        }
+
+        self.into_carved()
+    }
+
+    /// Carves a maze out of solid walls using a recursive backtracker, then
+    /// opens up some of the dead ends. See [`MazeGenerator`].
+    ///
+    /// The cells of the maze are on the odd rows and columns, with the walls
+    /// between them on the even ones.
+    pub(in crate::floor_builder) fn carve_maze(
+        mut self,
+        generator: &MazeGenerator,
+    ) -> FloorBuilder<Carved> {
+        self.fill_with_walls();
+
+        let cell_rows = (self.height.as_unbounded() as usize - 1) / 2;
+        let cell_columns = (self.width.as_unbounded() as usize - 1) / 2;
+
+        // the neighbouring cells of the cell that are within the maze
+        let neighbours = |(row, column): (usize, usize)| {
+            [
+                (row > 0).then(|| (row - 1, column)),
+                (row + 1 < cell_rows).then(|| (row + 1, column)),
+                (column > 0).then(|| (row, column - 1)),
+                (column + 1 < cell_columns).then(|| (row, column + 1)),
+            ]
+        };
+
+        let mut visited = vec![false; cell_rows * cell_columns];
+        let start = (
+            self.rng.gen_range(0..cell_rows),
+            self.rng.gen_range(0..cell_columns),
+        );
+        visited[start.0 * cell_columns + start.1] = true;
+        self.carve_between_cells(start, start);
+        let mut stack = vec![start];
+
+        while let Some(&cell) = stack.last() {
+            let unvisited = neighbours(cell)
+                .iter()
+                .flatten()
+                .copied()
+                .filter(|&(row, column)| !visited[row * cell_columns + column])
+                .collect::<Vec<_>>();
+
+            if let Some(&next) = unvisited.choose(&mut self.rng) {
+                visited[next.0 * cell_columns + next.1] = true;
+                self.carve_between_cells(cell, next);
+                stack.push(next);
+            } else {
+                stack.pop();
+                self.frame_from_current_state(5);
+            }
+        }
+
+        // braid the maze by knocking a wall out of some of the dead ends
+        for row in 0..cell_rows {
+            for column in 0..cell_columns {
+                let closed = neighbours((row, column))
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .filter(|&next| self.is_wall_between_cells((row, column), next))
+                    .collect::<Vec<_>>();
+
+                let is_dead_end =
+                    closed.len() + 1 == neighbours((row, column)).iter().flatten().count();
+
+                if is_dead_end && self.rng.gen_range(0..100) < generator.braid_percent_chance {
+                    if let Some(&next) = closed.choose(&mut self.rng) {
+                        self.carve_between_cells((row, column), next);
+                    }
+                }
+            }
+        }
+        self.frame_from_current_state(100);
+
+        self.into_carved()
+    }
+
+    /// Fills the map with the walls of a floor built out of the patterns of
+    /// the sample, using wave function collapse. See [`WfcGenerator`].
+    ///
+    /// The edges of the map are always walls, regardless of the patterns.
+    ///
+    /// # Errors
+    /// Errors if every attempt runs into a contradiction.
+    pub(in crate::floor_builder) fn collapse_wfc(
+        mut self,
+        generator: &WfcGenerator,
+    ) -> Result<FloorBuilder<Carved>, GenerationError> {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.height.as_unbounded() as usize;
+
+        let model = Model::new(
+            &generator.sample,
+            generator.pattern_size.into(),
+            generator.periodic_sample,
+            generator.symmetry,
+        );
+
+        let attempts = generator.max_attempts.max(1);
+        let walls = (0..attempts)
+            .find_map(|_| model.run(width, height, &mut self.rng))
+            .ok_or(GenerationError::WfcContradiction(attempts))?;
+
+        for (index, wall) in walls.into_iter().enumerate() {
+            let (row, column) = (index / width, index % width);
+            let is_edge = row == 0 || column == 0 || row == height - 1 || column == width - 1;
+
+            self.map[index] = if wall || is_edge {
+                DungeonTile::Wall
+            } else {
+                DungeonTile::Empty
+            };
+        }
+        self.frame_from_current_state(100);
+
+        Ok(self.into_carved())
+    }
+
+    /// Carves out the two maze cells and the wall between them. The cells must
+    /// be next to each other (or the same cell).
+    fn carve_between_cells(&mut self, from: (usize, usize), to: (usize, usize)) {
+        // the cell (and wall) at index `i` is at tile `i * 2 + 1`, so the wall
+        // between two cells is at the sum of their indices plus one
+        self.carve_tile(from.0 * 2 + 1, from.1 * 2 + 1);
+        self.carve_tile(from.0 + to.0 + 1, from.1 + to.1 + 1);
+        self.carve_tile(to.0 * 2 + 1, to.1 * 2 + 1);
+    }
+
+    /// Returns `true` if there is a wall between the two neighbouring maze
+    /// cells.
+    fn is_wall_between_cells(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        let width = self.width.as_unbounded() as usize;
+        self.map[(from.0 + to.0 + 1) * width + from.1 + to.1 + 1].is_wall()
+    }
+
+    /// Fills the entire map with walls, to be carved out of.
+    fn fill_with_walls(&mut self) {
+        self.map.fill(DungeonTile::Wall);
+        self.frame_from_current_state(100);
+    }
+
+    /// Carves out the tile at the row and column.
+    fn carve_tile(&mut self, row: usize, column: usize) {
+        let width = self.width.as_unbounded() as usize;
+        self.map[row * width + column] = DungeonTile::Empty;
+    }
+
+    /// Moves the builder into the [`Carved`] state once it's map has been
+    /// carved, filling the tiles that have been masked out back in with
+    /// walls.
+    fn into_carved(mut self) -> FloorBuilder<Carved> {
+        for (tile, &masked) in self.map.iter_mut().zip(&self.mask) {
+            if masked {
+                *tile = DungeonTile::Wall;
+            }
+        }
+
+        FloorBuilder {
+            extra: Carved {},
+            height: self.height,
+            width: self.width,
+            map: self.map,
+            noise_map: self.noise_map,
+            mask: self.mask,
+            vaults: self.vaults,
             frames: self.frames,
             id: self.id,
+            rng: self.rng,
         }
     }
 }
 
+/// A rectangular area of the map, used when carving with binary space
+/// partitioning.
+#[derive(Debug, Clone, Copy)]
+struct Area {
+    /// The top row of the area.
+    row: usize,
+    /// The leftmost column of the area.
+    column: usize,
+    /// How many rows tall the area is.
+    height: usize,
+    /// How many columns wide the area is.
+    width: usize,
+}
+
 /// Gets the noise value for the provided billow at the row and column
-/// specified.
+/// specified. The noise is sampled with the floor spanning `1` unit in each
+/// direction, shifted by the offset (in rows and columns of floors).
 fn get_noise_value(
     noise: &mut Billow,
     column: BoundedInt<0, MAX_FLOOR_SIZE>,
     row: BoundedInt<0, MAX_FLOOR_SIZE>,
     height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
     width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+    (row_offset, column_offset): (f64, f64),
 ) -> u16 {
     /// Makes it easier for me to think about powers of two
     mod u4 {
@@ -101,8 +506,8 @@ fn get_noise_value(
     #[allow(clippy::cast_possible_truncation)]
     let n = noise
         .get([
-            (column.as_unbounded() as f64 / width.as_unbounded() as f64),
-            (row.as_unbounded() as f64 / height.as_unbounded() as f64),
+            column_offset + (column.as_unbounded() as f64 / width.as_unbounded() as f64),
+            row_offset + (row.as_unbounded() as f64 / height.as_unbounded() as f64),
         ])
         // change the range from [-1, 1] to [-8, 8]
         .mul((u4::MAX / 2) as f64)
@@ -124,16 +529,14 @@ fn get_noise_value(
     }
 }
 
-/// Creates a [`Billow`] using some magic numbers that have been fine tuned to
-/// work well.
-///
-/// Don't touch 'em
-fn create_billow(rng: &mut impl rand::Rng) -> Billow {
+/// Creates a [`Billow`] from the provided [`NoiseConfig`], seeded from the
+/// provided rng.
+fn create_billow(rng: &mut impl rand::Rng, config: &NoiseConfig) -> Billow {
     Billow::new()
-        .set_octaves(1)
-        .set_frequency(5.0)
-        .set_lacunarity(0.001)
-        .set_persistence(0.001)
+        .set_octaves(config.octaves)
+        .set_frequency(config.frequency)
+        .set_lacunarity(config.lacunarity)
+        .set_persistence(config.persistence)
         .set_seed(rng.gen())
 }
 
@@ -153,6 +556,7 @@ mod test_noise {
     use std::path::Path;
 
     use itertools::Itertools;
+    use rand::thread_rng;
 
     use super::*;
 
@@ -164,7 +568,7 @@ mod test_noise {
 
         let mut rng = thread_rng();
 
-        let mut noise = create_billow(&mut rng);
+        let mut noise = create_billow(&mut rng, &NoiseConfig::default());
 
         for column in BoundedInt::<0, MAX_FLOOR_SIZE>::new(WIDTH)
             .unwrap()
@@ -185,6 +589,7 @@ mod test_noise {
                     row,
                     HEIGHT.try_into().unwrap(),
                     WIDTH.try_into().unwrap(),
+                    (0.0, 0.0),
                 );
                 *noise_map.at_mut(point, BoundedInt::<0, MAX_FLOOR_SIZE>::new(WIDTH).unwrap()) = n;
             }
@@ -200,9 +605,9 @@ mod test_noise {
         //     })
         //     .collect_vec();
 
-        let _ = image::save_buffer(
-            &Path::new(&"noise.png"),
-            &*noise_map
+        image::save_buffer(
+            Path::new(&"noise.png"),
+            &noise_map
                 .into_iter()
                 .flat_map(|integer| [integer as u8, (integer >> 8) as u8])
                 .collect_vec(),
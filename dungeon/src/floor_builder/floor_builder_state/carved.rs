@@ -0,0 +1,190 @@
+use std::{cmp::Reverse, collections::VecDeque};
+
+use rand::prelude::SliceRandom;
+
+use crate::{DungeonTile, FloorBuilder, GenerationError, Point};
+
+use super::{filled::Filled, FloorBuilderState};
+
+/// A builder that has had it's floor carved out of solid walls, by one of the
+/// room, walk or maze algorithms.
+#[derive(Debug)]
+pub(in crate::floor_builder) struct Carved {}
+impl FloorBuilderState for Carved {
+    const TYPE_NAME: &'static str = "Carved";
+}
+
+impl FloorBuilder<Carved> {
+    /// Places the entrance and the exit as far apart as possible (by walking
+    /// distance) within the carved out floor.
+    ///
+    /// A random open tile is picked, the exit is placed at the tile furthest
+    /// away from it, and the entrance is placed at the tile furthest away from
+    /// the exit. Tiles that are furthest away are very rarely in the way of
+    /// any other tiles, so the entrance and exit almost never cut off part of
+    /// the floor (and when they do, the secret passages join it back up).
+    ///
+    /// If an entrance is provided, it is placed there instead (carving a
+    /// tunnel to the largest open area if it isn't already in it), and the
+    /// exit is placed at the tile furthest away from it.
+    ///
+    /// # Errors
+    /// Errors if there are less than 2 open tiles reachable from each other.
+    pub(in crate::floor_builder) fn place_entrance_and_exit(
+        mut self,
+        entrance: Option<Point>,
+    ) -> Result<FloorBuilder<Filled>, GenerationError> {
+        let width = self.width.as_unbounded() as usize;
+
+        let (entrance, exit) = if let Some(entrance) = entrance {
+            let entrance = entrance.to_index(width);
+            self.carve_to_largest_open_area(entrance);
+
+            (entrance, self.furthest_open_tile(entrance))
+        } else {
+            let open = (0..self.map.len())
+                .filter(|&index| self.map[index].is_empty())
+                .collect::<Vec<_>>();
+
+            let mut start = *open
+                .choose(&mut self.rng)
+                .ok_or(GenerationError::TooFewOpenTiles(0))?;
+
+            // a mask can cut single tiles off from the rest of the floor, so
+            // start from the largest open area instead if one was picked
+            if self.furthest_open_tile(start) == start {
+                if let (areas, Some(largest)) = self.open_areas() {
+                    start = areas
+                        .iter()
+                        .position(|&area| area == Some(largest))
+                        .expect("the largest area has tiles");
+                }
+            }
+
+            let exit = self.furthest_open_tile(start);
+            (self.furthest_open_tile(exit), exit)
+        };
+
+        if entrance == exit {
+            return Err(GenerationError::TooFewOpenTiles(1));
+        }
+
+        self.map[entrance] = DungeonTile::Entrance;
+        self.map[exit] = DungeonTile::Exit;
+        self.frame_from_current_state(100);
+
+        Ok(FloorBuilder {
+            extra: Filled {
+                chests: vec![],
+                spawn_points: vec![],
+            },
+            height: self.height,
+            width: self.width,
+            map: self.map,
+            noise_map: self.noise_map,
+            mask: self.mask,
+            vaults: self.vaults,
+            frames: self.frames,
+            id: self.id,
+            rng: self.rng,
+        })
+    }
+
+    /// Carves a tunnel from the tile at the provided index to the nearest tile
+    /// of the largest open area, unless the tile is already part of it. Only
+    /// the inside of the map is carved; the edges and the masked out tiles
+    /// are always left as walls.
+    fn carve_to_largest_open_area(&mut self, from: usize) {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.height.as_unbounded() as usize;
+
+        let (areas, largest) = self.open_areas();
+        let largest = match largest {
+            Some(largest) if areas[from] != Some(largest) => largest,
+            // already part of the largest area
+            Some(_) => return,
+            // there are no open tiles at all, so open up just the entrance
+            None => {
+                self.map[from] = DungeonTile::Empty;
+                return;
+            }
+        };
+
+        let mut previous = vec![None; self.map.len()];
+        previous[from] = Some(from);
+        let mut queue = VecDeque::from(vec![from]);
+
+        while let Some(index) = queue.pop_front() {
+            if areas[index] == Some(largest) {
+                // walk back along the path, carving it out
+                let mut current = index;
+                while current != from {
+                    current = previous[current].unwrap();
+                    self.map[current] = DungeonTile::Empty;
+                }
+                self.frame_from_current_state(10);
+                return;
+            }
+
+            let (row, column) = (index / width, index % width);
+            let neighbours = [
+                (row > 1).then(|| index - width),
+                (row + 2 < height).then(|| index + width),
+                (column > 1).then(|| index - 1),
+                (column + 2 < width).then(|| index + 1),
+            ];
+
+            for neighbour in neighbours.iter().flatten().copied() {
+                if previous[neighbour].is_none() && !self.mask[neighbour] {
+                    previous[neighbour] = Some(index);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+
+    /// Labels every open area (group of empty tiles connected up, down, left
+    /// and right) with a number, returning the labels of every tile along with
+    /// the label of the largest area, if there are any.
+    fn open_areas(&self) -> (Vec<Option<usize>>, Option<usize>) {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.height.as_unbounded() as usize;
+
+        let (areas, tiles) = Point::connected_groups(width, height, 0..self.map.len(), |index| {
+            self.map[index].is_empty()
+        });
+        // the first of the largest areas, to keep it deterministic
+        let largest = tiles
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, tiles)| Reverse(tiles.len()))
+            .map(|(area, _)| area);
+
+        (areas, largest)
+    }
+
+    /// Returns the index of the open tile that is the furthest walking distance
+    /// away from the tile at the provided index.
+    fn furthest_open_tile(&self, from: usize) -> usize {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.height.as_unbounded() as usize;
+
+        let mut visited = vec![false; self.map.len()];
+        visited[from] = true;
+        let mut queue = VecDeque::from(vec![from]);
+        let mut furthest = from;
+
+        while let Some(index) = queue.pop_front() {
+            furthest = index;
+
+            for neighbour in Point::neighbour_indices(index, width, height) {
+                if !visited[neighbour] && self.map[neighbour].is_empty() {
+                    visited[neighbour] = true;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        furthest
+    }
+}
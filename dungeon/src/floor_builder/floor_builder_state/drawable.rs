@@ -2,7 +2,7 @@ use crate::{
     connection_path::{ConnectionPath, ConnectionPathLength},
     floor_builder::filled::Filled,
     point_index::PointIndex,
-    DungeonTile, FloorBuilder, Point,
+    DungeonTile, FloorBuilder, GenerationError, Point,
 };
 
 use super::FloorBuilderState;
@@ -29,6 +29,10 @@ impl FloorBuilder<Drawable> {
     /// [`DungeonTile`] that will be placed at the provided point.
     ///
     /// Note that the first and second arguments are not mutually exclisive.
+    ///
+    /// # Errors
+    /// Errors if any of the paths contain their own start or end point in
+    /// the points between them.
     pub(in crate::floor_builder) fn draw(
         mut self,
         // TODO: Make a more explicit type for the first two arguments (`enum PositionInPath`
@@ -40,24 +44,28 @@ impl FloorBuilder<Drawable> {
             bool,
             Point,
         ) -> DungeonTile,
-    ) -> FloorBuilder<Filled> {
+    ) -> Result<FloorBuilder<Filled>, GenerationError> {
         for path in self.extra.to_draw.clone() {
             match &path.path {
                 ConnectionPathLength::Length1 { point } => {
-                    *self.map.at_mut(*point, self.width) = draw_with(true, true, *point)
+                    self.draw_tile(*point, draw_with(true, true, *point))
                 }
                 ConnectionPathLength::Length2 { start, end } => {
-                    *self.map.at_mut(*start, self.width) = draw_with(true, false, *start);
-                    *self.map.at_mut(*end, self.width) = draw_with(false, true, *end);
+                    self.draw_tile(*start, draw_with(true, false, *start));
+                    self.draw_tile(*end, draw_with(false, true, *end));
                 }
                 ConnectionPathLength::Length3Plus { points, start, end } => {
-                    assert!(!points.contains(start));
-                    assert!(!points.contains(end));
-                    *self.map.at_mut(*start, self.width) = draw_with(true, false, *start);
-                    *self.map.at_mut(*end, self.width) = draw_with(false, true, *end);
+                    if points.contains(start) || points.contains(end) {
+                        return Err(GenerationError::InvalidConnectionPath {
+                            start: *start,
+                            end: *end,
+                        });
+                    }
+                    self.draw_tile(*start, draw_with(true, false, *start));
+                    self.draw_tile(*end, draw_with(false, true, *end));
 
                     for point in points {
-                        *self.map.at_mut(*point, self.width) = draw_with(false, false, *point);
+                        self.draw_tile(*point, draw_with(false, false, *point));
 
                         self.frame_from_current_state(1);
                     }
@@ -65,14 +73,33 @@ impl FloorBuilder<Drawable> {
             };
         }
 
-        FloorBuilder {
+        Ok(FloorBuilder {
             width: self.width,
             height: self.height,
             map: self.map,
             noise_map: self.noise_map,
-            extra: Filled {},
+            mask: self.mask,
+            vaults: self.vaults,
+            extra: Filled {
+                chests: vec![],
+                spawn_points: vec![],
+            },
             frames: self.frames,
             id: self.id,
+            rng: self.rng,
+        })
+    }
+
+    /// Places the tile at the point, unless the point is the entrance or the
+    /// exit of the floor, or inside of a vault. Paths can go *through* the
+    /// entrance, the exit and the vaults, but must never replace them.
+    fn draw_tile(&mut self, point: Point, tile: DungeonTile) {
+        if self.in_vault(point) {
+            return;
+        }
+        let current = self.map.at_mut(point, self.width);
+        if !current.is_entrance() && !current.is_exit() {
+            *current = tile;
         }
     }
 }
@@ -1,6 +1,12 @@
-use std::convert::TryInto;
+use std::{collections::BTreeSet, convert::TryInto};
 
-use crate::{DungeonTile, Floor, FloorBuilder};
+use itertools::Itertools;
+use rand::prelude::SliceRandom;
+
+use crate::{
+    point_index::PointIndex, DistanceMap, DungeonTile, DungeonType, Entities, Floor, FloorBuilder,
+    GenerationError, Item, MonsterConfig, Point,
+};
 
 use super::{FloorBuilderState, Smoothable};
 
@@ -10,7 +16,13 @@ use super::{FloorBuilderState, Smoothable};
 /// Note that even though this can be thought as the 'final state' of the
 /// builder, there is still likely steps remaining until the builder is done.
 #[derive(Debug)]
-pub(in crate::floor_builder) struct Filled {}
+pub(in crate::floor_builder) struct Filled {
+    /// The contents of the treasure chests on the map, indexed by their
+    /// [`ChestId`](crate::ChestId).
+    pub(in crate::floor_builder) chests: Vec<Vec<Item>>,
+    /// The spawn points for monsters on the map.
+    pub(in crate::floor_builder) spawn_points: Vec<Point>,
+}
 
 impl FloorBuilderState for Filled {
     const TYPE_NAME: &'static str = "Filled";
@@ -28,9 +40,80 @@ impl Smoothable for Filled {}
 // }
 
 impl FloorBuilder<Filled> {
+    /// Places the spawn points for monsters, spread out over every cave by
+    /// the [`MonsterConfig::density`].
+    ///
+    /// Spawn points are only placed on empty tiles in the open (with no solid
+    /// tiles around them) outside of the vaults, at least
+    /// [`MonsterConfig::min_entrance_distance`] steps away from the entrance,
+    /// and never right next to each other.
+    pub(in crate::floor_builder) fn place_spawn_points(mut self, config: &MonsterConfig) -> Self {
+        let width = self.width.as_unbounded() as usize;
+
+        let entrance = self
+            .map
+            .iter()
+            .position(|tile| tile.is_entrance())
+            .and_then(|entrance| Point::from_index(entrance, width).ok());
+        let from_entrance =
+            DistanceMap::through_locked_doors(&self.map, self.width, self.height, entrance);
+
+        let mut spawn_points = BTreeSet::new();
+        let mut placed = vec![];
+
+        for cave in self.caves() {
+            let amount = (cave.len() as f64 * config.density).round() as usize;
+
+            let mut candidates = cave
+                .into_iter()
+                .filter(|&point| {
+                    self.map.at(point, self.width).is_empty()
+                        && !self.in_vault(point)
+                        && from_entrance.get(point).is_some_and(|distance| {
+                            distance >= i32::from(config.min_entrance_distance)
+                        })
+                        && self.get_legal_neighbors_with_diagonals(point).count() == 8
+                        && self
+                            .get_legal_neighbors_with_diagonals(point)
+                            .all(|neighbour| !self.map.at(neighbour, self.width).is_solid())
+                })
+                .collect_vec();
+            candidates.shuffle(&mut self.rng);
+
+            let mut amount = (0..amount).peekable();
+            for point in candidates {
+                if amount.peek().is_none() {
+                    break;
+                }
+
+                if self
+                    .get_legal_neighbors_with_diagonals(point)
+                    .any(|neighbour| spawn_points.contains(&neighbour))
+                {
+                    continue;
+                }
+
+                spawn_points.insert(point);
+                placed.push(point);
+                amount.next();
+            }
+        }
+
+        self.extra.spawn_points = placed;
+        self
+    }
+
     /// Finishes the builder, returning the completed [`Floor`] and writing the
-    /// gif out to `out/frame_{id}.gif`.
-    pub(in crate::floor_builder) fn finish(self) -> Floor {
+    /// gif out to `out/frame_{id}.gif`, using the palette for the provided
+    /// [`DungeonType`].
+    ///
+    /// # Errors
+    /// Errors if gif output is enabled and the frames fail to encode. Failing
+    /// to write the encoded gif to disk is *not* an error, and is only logged.
+    pub(in crate::floor_builder) fn finish(
+        self,
+        dungeon_type: DungeonType,
+    ) -> Result<Floor, GenerationError> {
         if let Some(frames) = self.frames {
             use gif::{Encoder, Repeat};
 
@@ -40,29 +123,75 @@ impl FloorBuilder<Filled> {
                     &mut image,
                     self.width.as_unbounded().try_into().unwrap(),
                     self.height.as_unbounded().try_into().unwrap(),
-                    &DungeonTile::COLOR_MAP,
-                )
-                .unwrap();
-                encoder.set_repeat(Repeat::Finite(0)).unwrap();
+                    DungeonTile::color_map(dungeon_type),
+                )?;
+                encoder.set_repeat(Repeat::Finite(0))?;
                 for frame in frames {
-                    encoder.write_frame(&frame).unwrap();
+                    encoder.write_frame(&frame)?;
                 }
             }
 
             let out_path = format!("out/floor_{}.gif", self.id);
             match std::fs::write(&out_path, image) {
-                Ok(_) => {
-                    eprintln!("Successfully wrote to {}", out_path)
-                }
-                Err(err) => {
-                    eprintln!("Error writing to {}: {}", out_path, err)
-                }
+                Ok(_) => log::info!("Successfully wrote to {}", out_path),
+                Err(err) => log::warn!("Error writing to {}: {}", out_path, err),
             }
         }
-        Floor {
+        Ok(Floor {
             height: self.height,
             width: self.width,
             data: self.map,
+            chests: self.extra.chests,
+            spawn_points: self.extra.spawn_points,
+            entities: Entities::default(),
+            vaults: self.vaults,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_filled {
+    use super::*;
+    use crate::{test_utils::cave_floor, GenerationConfig, GenerationConfigError};
+
+    #[test]
+    fn test_monster_spawn_points() {
+        let mut config = GenerationConfig::default();
+        config.monsters.density = 0.02;
+        config.monsters.min_entrance_distance = 15;
+
+        let floor = cave_floor(80, &config, 0);
+
+        let from_entrance = floor.distance_map(floor.entrance());
+
+        assert!(!floor.spawn_points.is_empty());
+        for &point in &floor.spawn_points {
+            assert!(floor.at(point).is_empty());
+            assert!(from_entrance.get(point).unwrap() >= 15);
+
+            // no two spawn points are right next to each other
+            assert!(!floor.spawn_points.iter().any(|&other| {
+                other != point
+                    && (other.row.get().as_unbounded() - point.row.get().as_unbounded()).abs() <= 1
+                    && (other.column.get().as_unbounded() - point.column.get().as_unbounded()).abs()
+                        <= 1
+            }));
         }
+
+        // the spawn points are saved with the floor
+        let json = serde_json::to_string(&floor).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Floor>(&json).unwrap().spawn_points,
+            floor.spawn_points
+        );
+
+        config.monsters.density = 0.0;
+        assert!(cave_floor(80, &config, 0).spawn_points.is_empty());
+
+        config.monsters.density = 1.5;
+        assert_eq!(
+            config.validate(),
+            Err(GenerationConfigError::MonsterDensity(1.5))
+        );
     }
 }
@@ -1,11 +1,14 @@
 use petgraph::algo::min_spanning_tree;
-use std::{collections::HashMap, iter};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    iter,
+};
 
-use petgraph::{algo::kosaraju_scc, data::FromElements, graphmap::UnGraphMap};
+use petgraph::{algo::kosaraju_scc, data::FromElements, graphmap::UnGraphMap, visit::Bfs};
 
 use crate::{
     border::{Border, BorderId},
-    distance, Connection, FloorBuilder, Point,
+    BuildConnectionIterations, Connection, FloorBuilder, Point,
 };
 
 use super::{has_connections::HasConnections, FloorBuilderState};
@@ -21,20 +24,6 @@ impl FloorBuilderState for HasBorders {
     const TYPE_NAME: &'static str = "HasBorders";
 }
 
-/// How many iterations there should be when generating the connections between
-/// the caves.
-#[derive(Debug, Clone, Copy)]
-pub enum BuildConnectionIterations {
-    /// Until there is only 1 scc left (the caves are fully connected).
-    FullyConnect,
-    /// A finite amount of times, or until the caves are fully connected.
-    Finite(u8),
-    /// Until there are at most the specified amount of sccs; guaranteed
-    /// to be `<=` the specified amount.
-    #[allow(dead_code)]
-    Until(u8),
-}
-
 impl FloorBuilder<HasBorders> {
     /// Builds bridges between the disjointed caves and the closest cave border
     /// point *not* in the border of the first cave.
@@ -48,9 +37,12 @@ impl FloorBuilder<HasBorders> {
                 height: self.height,
                 map: self.map,
                 noise_map: self.noise_map,
+                mask: self.mask,
+                vaults: self.vaults,
                 extra: HasConnections::default(),
                 frames: self.frames,
                 id: self.id,
+                rng: self.rng,
             };
         }
 
@@ -60,9 +52,9 @@ impl FloorBuilder<HasBorders> {
             .iter()
             .cloned()
             .flat_map(|Border { id, points }| points.into_iter().zip(iter::repeat(id)))
-            .collect::<HashMap<Point, BorderId>>();
+            .collect::<BTreeMap<Point, BorderId>>();
 
-        let mut connections_with_points = HashMap::<(Point, BorderId), (Point, BorderId)>::new();
+        let mut connections_with_points = BTreeSet::<((Point, BorderId), (Point, BorderId))>::new();
         // a graph is required to check for the strongly connected components and the
         // minimum spanning tree (because i don't want to implement that myself lol)
         let mut connected_borders_graph = UnGraphMap::<BorderId, ()>::new();
@@ -77,20 +69,19 @@ impl FloorBuilder<HasBorders> {
         //     BuildConnectionIterations::Until(_) => todo!(),
         // }
 
-        // loop through all the borders
+        // loop through all the borders (as many times as needed)
         // build one connection per loop
-        for (acc, current_border) in self.extra.borders.iter().enumerate() {
-            let already_connected_ids = connected_borders_graph
-                .neighbors(current_border.id)
-                .collect::<Vec<_>>();
+        for (acc, current_border) in self.extra.borders.iter().cycle().enumerate() {
+            // every border reachable from the current border, including itself
+            let already_connected_ids = {
+                let mut bfs = Bfs::new(&connected_borders_graph, current_border.id);
+                iter::from_fn(|| bfs.next(&connected_borders_graph)).collect::<BTreeSet<_>>()
+            };
 
             let maybe_new_connection: Option<Connection> = all_border_points
                 .iter()
-                // filter out border points that are either:
-                // - in the current border, or
-                .filter(|(_, &id)| id != current_border.id)
-                //
-                // - in a border the current border is already connected to
+                // filter out border points that are in the current border, or in a border the
+                // current border is already connected to (directly or not)
                 .filter(|(_, &id)| !already_connected_ids.contains(&id))
                 .flat_map(|(&point, &id)| {
                     // create a `Connection` between every point in this border and the borders it
@@ -100,7 +91,7 @@ impl FloorBuilder<HasBorders> {
                         .points
                         .iter()
                         .map(move |&current_border_point| Connection {
-                            distance: distance(point, current_border_point),
+                            distance: point.distance(current_border_point),
                             from: (current_border_point, current_border.id),
                             to: (point, id),
                         })
@@ -115,21 +106,23 @@ impl FloorBuilder<HasBorders> {
                 });
 
             if let Some(Connection { from, to, .. }) = maybe_new_connection {
-                connections_with_points.insert(from, to);
+                connections_with_points.insert((from, to));
                 connected_borders_graph.add_edge(from.1, to.1, ());
             }
 
             // strongly connected components
             let sccs = kosaraju_scc(&connected_borders_graph);
 
-            let should_return = match dbg!(iterations) {
-                // if there is only one scc, we're done here
-                BuildConnectionIterations::FullyConnect => dbg!(sccs.len()) == 1,
-                // if we've iterated enough times, return
-                BuildConnectionIterations::Finite(amount) => acc == amount as usize,
-                // if the amount of sccs is less than or equal to the amount requested, return
-                BuildConnectionIterations::Until(until) => sccs.len() <= until.into(),
-            };
+            // once there is only one scc, there is nothing left to connect
+            let should_return = sccs.len() == 1
+                || match iterations {
+                    // only done once there is one scc, which is checked above
+                    BuildConnectionIterations::FullyConnect => false,
+                    // if we've iterated enough times, return
+                    BuildConnectionIterations::Finite(amount) => acc == amount as usize,
+                    // if the amount of sccs is less than or equal to the amount requested, return
+                    BuildConnectionIterations::Until(until) => sccs.len() <= until.into(),
+                };
 
             if should_return {
                 let msf = UnGraphMap::from_elements(min_spanning_tree(
@@ -140,9 +133,11 @@ impl FloorBuilder<HasBorders> {
                     height: self.height,
                     map: self.map,
                     noise_map: self.noise_map,
+                    mask: self.mask,
+                    vaults: self.vaults,
                     extra: HasConnections {
-                        // remove extra connections from the connections_with_points hashmap (make
-                        // it into the MSF)
+                        // remove extra connections from the connections_with_points set (make it
+                        // into the MSF)
                         connections: connections_with_points
                             .into_iter()
                             .filter(|&((_, k), (_, v))| msf.contains_edge(k, v))
@@ -151,6 +146,7 @@ impl FloorBuilder<HasBorders> {
                     },
                     frames: self.frames,
                     id: self.id,
+                    rng: self.rng,
                 };
             };
         }
@@ -159,12 +155,15 @@ impl FloorBuilder<HasBorders> {
             height: self.height,
             map: self.map,
             noise_map: self.noise_map,
+            mask: self.mask,
+            vaults: self.vaults,
             extra: HasConnections {
                 connections: connections_with_points,
                 borders: self.extra.borders.into_iter().map(|b| (b.id, b)).collect(),
             },
             frames: self.frames,
             id: self.id,
+            rng: self.rng,
         }
     }
 }
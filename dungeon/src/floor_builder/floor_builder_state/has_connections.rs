@@ -1,7 +1,7 @@
 use crate::{floor_builder::dijkstra, point_index::PointIndex};
 use itertools::Itertools;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use crate::{
     border::{Border, BorderId},
@@ -15,9 +15,11 @@ use super::{drawable::Drawable, FloorBuilderState};
 // TODO: This could use a better name
 #[derive(Debug, Default)]
 pub(in crate::floor_builder) struct HasConnections {
-    /// The connections between the borders, mapping one [`Point`] and a
-    /// [`BorderId`] to another.
-    pub(in crate::floor_builder) connections: HashMap<(Point, BorderId), (Point, BorderId)>,
+    /// The connections between the borders, each from one [`Point`] and a
+    /// [`BorderId`] to another. A point may be the start of more than one
+    /// connection. Ordered so that the paths are always traced (and drawn) in
+    /// the same order.
+    pub(in crate::floor_builder) connections: BTreeSet<((Point, BorderId), (Point, BorderId))>,
 
     /// The borders of the floor, indexable by their [`BorderId`].
     pub(in crate::floor_builder) borders: HashMap<BorderId, Border>,
@@ -47,7 +49,7 @@ impl FloorBuilder<HasConnections> {
             .extra
             .connections
             .iter()
-            .filter_map(|(&(from, from_id), &(to, to_id))| {
+            .filter_map(|&((from, from_id), (to, to_id))| {
                 dijkstra(
                     &from,
                     |&point| {
@@ -105,7 +107,7 @@ impl FloorBuilder<HasConnections> {
                                         vec![]
                                     })
                                     .filter(move |v| *v != from && *v != to)
-                                    .collect::<HashSet<_>>(),
+                                    .collect::<BTreeSet<_>>(),
                                 start: from,
                                 end: to,
                             },
@@ -120,9 +122,12 @@ impl FloorBuilder<HasConnections> {
             height: self.height,
             map: self.map,
             noise_map: self.noise_map,
+            mask: self.mask,
+            vaults: self.vaults,
             extra: Drawable { to_draw },
             frames: self.frames,
             id: self.id,
+            rng: self.rng,
         }
     }
 }
@@ -1,9 +1,16 @@
-use std::convert::TryInto;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, VecDeque},
+    convert::TryInto,
+};
 
 use itertools::Itertools;
-use rand::{prelude::SliceRandom, thread_rng, Rng};
+use rand::{prelude::SliceRandom, Rng};
 
-use crate::{point_index::PointIndex, Column, DungeonTile, FloorBuilder, Point, Row};
+use crate::{
+    point_index::PointIndex, ChestId, Column, DistanceMap, DungeonTile, FloorBuilder,
+    GenerationError, Point, Row, TreasureConfig, TreasurePlacement,
+};
 
 use super::{filled::Filled, FloorBuilderState};
 
@@ -16,10 +23,403 @@ impl FloorBuilderState for HasSecretPassages {
 }
 
 impl FloorBuilder<HasSecretPassages> {
-    /// Places the treasure chests on the map.
-    pub(in crate::floor_builder) fn place_treasure_chests(mut self) -> FloorBuilder<Filled> {
-        let mut rng = thread_rng();
-        let mut empty_points_sorted_by_noise = self
+    /// Fills in every tile that can't be reached from the entrance with walls.
+    ///
+    /// # Errors
+    /// Errors if the exit can't be reached from the entrance.
+    pub(super) fn fill_unreachable_caves(mut self) -> Result<Self, GenerationError> {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.height.as_unbounded() as usize;
+
+        let entrance = self
+            .map
+            .iter()
+            .position(|tile| tile.is_entrance())
+            .expect("the entrance is placed before the secret passages");
+
+        let mut reached = vec![false; self.map.len()];
+        reached[entrance] = true;
+        let mut queue = VecDeque::from(vec![entrance]);
+
+        while let Some(index) = queue.pop_front() {
+            for neighbour in Point::neighbour_indices(index, width, height) {
+                if !reached[neighbour] && !self.map[neighbour].is_wall() {
+                    reached[neighbour] = true;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        for (index, tile) in self.map.iter_mut().enumerate() {
+            if reached[index] || tile.is_wall() {
+                continue;
+            }
+
+            if tile.is_exit() {
+                return Err(GenerationError::UnreachableExit {
+                    entrance: Point::from_index(entrance, width)?,
+                    exit: Point::from_index(index, width)?,
+                });
+            }
+
+            *tile = DungeonTile::Wall;
+        }
+        self.frame_from_current_state(100);
+
+        Ok(self)
+    }
+
+    /// Places a [key](DungeonTile::Key) for every locked secret door, on a
+    /// random empty tile outside of the vaults that can be reached from the
+    /// entrance without going through the door.
+    ///
+    /// The doors are found by walking out from the entrance, stopping at
+    /// locked doors; the key to the first door found (in row-major order) is
+    /// placed somewhere in the area walked so far, and then the walk carries
+    /// on through that door. This way every key can always be reached, no
+    /// matter in which order the doors are opened.
+    ///
+    /// If there is no empty tile left to place a key on (e.g. when the
+    /// entrance is in a cave barely bigger than itself), the door is unlocked
+    /// instead.
+    ///
+    /// # Errors
+    /// Errors if one of the doors isn't inside of the map.
+    pub(in crate::floor_builder) fn place_keys(mut self) -> Result<Self, GenerationError> {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.height.as_unbounded() as usize;
+
+        let entrance = self
+            .map
+            .iter()
+            .position(|tile| tile.is_entrance())
+            .expect("the entrance is placed before the secret passages");
+
+        let mut reached = vec![false; self.map.len()];
+        reached[entrance] = true;
+        let mut queue = VecDeque::from(vec![entrance]);
+        // the empty tiles walked over so far, which the keys can be placed on
+        let mut room = vec![];
+        let mut locked_doors = BTreeSet::new();
+
+        loop {
+            while let Some(index) = queue.pop_front() {
+                if self.map[index].is_empty() && !self.in_vault(Point::from_index(index, width)?) {
+                    room.push(index);
+                }
+
+                for neighbour in Point::neighbour_indices(index, width, height) {
+                    if reached[neighbour] || self.map[neighbour].is_solid() {
+                        continue;
+                    }
+                    reached[neighbour] = true;
+
+                    if self.map[neighbour].is_locked() {
+                        locked_doors.insert(neighbour);
+                    } else {
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+
+            let door = match locked_doors.iter().next().copied() {
+                Some(door) => door,
+                None => break,
+            };
+            locked_doors.remove(&door);
+
+            if room.is_empty() {
+                self.map[door] = DungeonTile::SecretDoor {
+                    requires_key: false,
+                    is_open: false,
+                };
+            } else {
+                let key = room.swap_remove(self.rng.gen_range(0..room.len()));
+                self.map[key] = DungeonTile::Key {
+                    door: Point::from_index(door, width)?,
+                };
+                self.frame_from_current_state(10);
+            }
+
+            queue.push_back(door);
+        }
+
+        Ok(self)
+    }
+
+    /// Moves the exit to the tile furthest away from the entrance (by walking
+    /// distance, and outside of the vaults) if it's closer than the minimum
+    /// distance. Locked doors can't be walked through, so an exit behind one
+    /// is moved as well, unless there is no minimum distance (`0`), in which
+    /// case the exit is always left where it is.
+    ///
+    /// # Errors
+    /// Errors if even the furthest tile is closer than the minimum distance.
+    pub(in crate::floor_builder) fn ensure_exit_distance(
+        mut self,
+        min_distance: u16,
+    ) -> Result<Self, GenerationError> {
+        if min_distance == 0 {
+            return Ok(self);
+        }
+
+        let width = self.width.as_unbounded() as usize;
+
+        let entrance = self
+            .map
+            .iter()
+            .position(|tile| tile.is_entrance())
+            .expect("the entrance is placed before the secret passages");
+        let exit = self
+            .map
+            .iter()
+            .position(|tile| tile.is_exit())
+            .expect("the exit is placed before the secret passages");
+
+        let distances = DistanceMap::new(
+            &self.map,
+            self.width,
+            self.height,
+            [Point::from_index(entrance, width)?],
+        );
+
+        if distances.values()[exit].is_some_and(|distance| distance >= min_distance.into()) {
+            return Ok(self);
+        }
+
+        // only empty tiles outside of the vaults can be replaced with the
+        // exit; the first of the furthest tiles is picked, to keep it
+        // deterministic
+        let (furthest, distance) = distances
+            .values()
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| {
+                index == exit
+                    || (self.map[index].is_empty()
+                        && Point::from_index(index, width).is_ok_and(|point| !self.in_vault(point)))
+            })
+            .filter_map(|(index, distance)| distance.map(|distance| (index, distance)))
+            .fold((exit, 0), |furthest, (index, distance)| {
+                if distance > furthest.1 {
+                    (index, distance)
+                } else {
+                    furthest
+                }
+            });
+
+        if distance < min_distance.into() {
+            return Err(GenerationError::ExitTooClose {
+                distance,
+                min: min_distance,
+            });
+        }
+
+        self.map[exit] = DungeonTile::Empty;
+        self.map[furthest] = DungeonTile::Exit;
+        self.frame_from_current_state(100);
+
+        Ok(self)
+    }
+
+    /// Returns the least amount of secret doors that have to be walked through
+    /// to get from the entrance to every tile, row by row. Tiles that can't be
+    /// reached at all are [`None`].
+    fn secret_doors_passed(&self) -> Vec<Option<u16>> {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.height.as_unbounded() as usize;
+
+        let entrance = self
+            .map
+            .iter()
+            .position(|tile| tile.is_entrance())
+            .expect("the entrance is placed before the secret passages");
+
+        let mut passed = vec![None; self.map.len()];
+        passed[entrance] = Some(0);
+        let mut queue = VecDeque::from(vec![(entrance, 0)]);
+
+        // walking onto a secret door costs 1 and every other step costs 0, so
+        // the doors are pushed to the back of the queue and every other tile
+        // to the front
+        while let Some((index, doors)) = queue.pop_front() {
+            if passed[index].is_some_and(|passed| passed < doors) {
+                continue;
+            }
+
+            for neighbour in Point::neighbour_indices(index, width, height) {
+                if self.map[neighbour].is_wall() {
+                    continue;
+                }
+
+                let is_door = self.map[neighbour].is_secret_door();
+                let doors = doors + u16::from(is_door);
+                if passed[neighbour].is_none_or(|passed| doors < passed) {
+                    passed[neighbour] = Some(doors);
+                    if is_door {
+                        queue.push_back((neighbour, doors));
+                    } else {
+                        queue.push_front((neighbour, doors));
+                    }
+                }
+            }
+        }
+
+        passed
+    }
+
+    /// Returns which tiles are cut tiles, row by row: walkable tiles that, if
+    /// they became solid, would split the walkable tiles around them into
+    /// parts that can't reach each other anymore.
+    ///
+    /// These are the articulation points of the graph of the tiles that
+    /// aren't [solid](DungeonTile::is_solid), found with Tarjan's algorithm.
+    /// Locked doors are left out of the graph, since they can't be walked
+    /// through until their key has been picked up; otherwise a tile could be
+    /// blocked off whose only other way around leads through the door its
+    /// key is behind. The tiles next to locked doors are cut tiles too, so
+    /// that the doors can always be walked up to.
+    fn cut_tiles(&self) -> Vec<bool> {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.height.as_unbounded() as usize;
+        let is_blocked = |index: usize| self.map[index].is_solid() || self.map[index].is_locked();
+
+        // the order every tile was first visited in (0 for unvisited tiles),
+        // and the earliest visited tile that can be reached from it without
+        // going back through the tile it was visited from
+        let mut visited = vec![0; self.map.len()];
+        let mut low = vec![0; self.map.len()];
+        let mut cut = vec![false; self.map.len()];
+        let mut order = 1;
+
+        for root in 0..self.map.len() {
+            if visited[root] != 0 || is_blocked(root) {
+                continue;
+            }
+            visited[root] = order;
+            low[root] = order;
+            order += 1;
+
+            let mut root_children = 0;
+            // the tiles being visited, with the tile they were visited from
+            // and the next of their neighbours to look at
+            let mut stack = vec![(root, None, 0)];
+
+            while let Some(&(index, parent, next)) = stack.last() {
+                if let Some(neighbour) = Point::neighbour_indices(index, width, height).nth(next) {
+                    stack.last_mut().expect("the stack isn't empty").2 += 1;
+
+                    if is_blocked(neighbour) || Some(neighbour) == parent {
+                        continue;
+                    }
+
+                    if visited[neighbour] == 0 {
+                        visited[neighbour] = order;
+                        low[neighbour] = order;
+                        order += 1;
+                        if index == root {
+                            root_children += 1;
+                        }
+                        stack.push((neighbour, Some(index), 0));
+                    } else {
+                        low[index] = low[index].min(visited[neighbour]);
+                    }
+                } else {
+                    stack.pop();
+                    if let Some(parent) = parent {
+                        low[parent] = low[parent].min(low[index]);
+                        if parent != root && low[index] >= visited[parent] {
+                            cut[parent] = true;
+                        }
+                    }
+                }
+            }
+
+            cut[root] = root_children > 1;
+        }
+
+        for door in (0..self.map.len()).filter(|&index| self.map[index].is_locked()) {
+            for neighbour in Point::neighbour_indices(door, width, height) {
+                cut[neighbour] = true;
+            }
+        }
+
+        cut
+    }
+
+    /// Returns how secluded every tile is for a treasure chest, row by row;
+    /// see [`TreasurePlacement::Secluded`]. Higher is more secluded.
+    ///
+    /// The score is the walking distance from the path between the entrance
+    /// and the exit, multiplied by one more than the amount of solid tiles
+    /// up, down, left and right of the tile; a dead end is worth four times
+    /// as much as a tile in the open.
+    fn seclusion(&self) -> Vec<i32> {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.height.as_unbounded() as usize;
+
+        let entrance = self
+            .map
+            .iter()
+            .position(|tile| tile.is_entrance())
+            .expect("the entrance is placed before the secret passages");
+        let exit = self
+            .map
+            .iter()
+            .position(|tile| tile.is_exit())
+            .expect("the exit is placed before the secret passages");
+
+        let to_entrance = DistanceMap::through_locked_doors(
+            &self.map,
+            self.width,
+            self.height,
+            Point::from_index(entrance, width).ok(),
+        );
+
+        let mut path = vec![];
+        let mut position = Point::from_index(exit, width).ok();
+        while let Some(point) = position {
+            path.push(point);
+            position = to_entrance.downhill(point);
+        }
+
+        let to_path = DistanceMap::through_locked_doors(&self.map, self.width, self.height, path);
+
+        to_path
+            .values()
+            .iter()
+            .enumerate()
+            .map(|(index, distance)| {
+                let walls = if self.map[index].is_wall() {
+                    0
+                } else {
+                    Point::neighbour_indices(index, width, height)
+                        .filter(|&neighbour| self.map[neighbour].is_solid())
+                        .count() as i32
+                };
+
+                distance.unwrap_or_default() * (1 + walls)
+            })
+            .collect()
+    }
+
+    /// Places the treasure chests on the map. The amount of chests is picked
+    /// from the range specified in the [`TreasureConfig`], the tiles are
+    /// picked by the [`TreasureConfig::placement`] strategy, and every chest
+    /// is filled from the [`TreasureConfig::loot`] table, with rarer items the
+    /// deeper the floor is and the more secret doors the chest is behind.
+    ///
+    /// Chests are only placed on empty tiles outside of the vaults that aren't
+    /// [cut tiles](Self::cut_tiles), so they never block the way to the rest
+    /// of the floor, and every chest can be reached from the entrance. If
+    /// there aren't enough such tiles, fewer chests are placed.
+    pub(in crate::floor_builder) fn place_treasure_chests(
+        mut self,
+        config: &TreasureConfig,
+    ) -> FloorBuilder<Filled> {
+        let width = self.width.as_unbounded() as usize;
+
+        let mut candidates = self
             .width
             .expand_lower()
             .range_from(0.try_into().unwrap())
@@ -32,36 +432,67 @@ impl FloorBuilder<HasSecretPassages> {
                         row: Row::new(row),
                     })
             })
-            .filter(|&point| matches!(self.map.at(point, self.width), crate::DungeonTile::Empty))
-            // .filter(|&point| {
-            //     self.get_legal_neighbors_with_diagonals(point)
-            //         .all(|point| self.map.at(point, self.width) == &DungeonTile::Empty)
-            // })
-            // .sorted_by(|&a, &b| {
-            //     self.noise_map
-            //         .at(a, self.width)
-            //         .cmp(self.noise_map.at(b, self.width))
-            // })
+            .filter(|&point| {
+                matches!(self.map.at(point, self.width), crate::DungeonTile::Empty)
+                    && !self.in_vault(point)
+            })
             .collect_vec();
 
-        let mut amount = (0..rng.gen_range(5..=10)).peekable();
-        empty_points_sorted_by_noise.shuffle(&mut rng);
+        let hidden = self.secret_doors_passed();
+        let mut chests = vec![];
 
-        for point in empty_points_sorted_by_noise {
-            if amount.peek().is_some() {
-                if self
-                    .get_legal_neighbors_with_diagonals(point)
-                    .all(|point| self.map.at(point, self.width) == &DungeonTile::Empty)
-                {
-                    self.frame_from_current_state(10);
-                    *self.map.at_mut(point, self.width) =
-                        DungeonTile::TreasureChest { contents: () };
-                    amount.next();
-                }
-                // dbg!(&self.noise_map.at(point, self.width));
-            } else {
+        let mut amount = (0..self.rng.gen_range(config.min_chests..=config.max_chests)).peekable();
+        candidates.shuffle(&mut self.rng);
+
+        // the sorts are stable, so ties are still broken by the shuffle
+        match config.placement {
+            TreasurePlacement::Random => {}
+            TreasurePlacement::Noise => {
+                candidates.sort_by_key(|&point| *self.noise_map.at(point, self.width));
+            }
+            TreasurePlacement::Secluded => {
+                let seclusion = self.seclusion();
+                candidates.sort_by_key(|&point| Reverse(seclusion[point.to_index(width)]));
+            }
+        }
+
+        let mut cut = self.cut_tiles();
+
+        for point in candidates {
+            if amount.peek().is_none() {
                 break;
             }
+
+            let is_placeable = match config.placement {
+                TreasurePlacement::Random | TreasurePlacement::Noise => self
+                    .get_legal_neighbors_with_diagonals(point)
+                    .all(|point| self.map.at(point, self.width) == &DungeonTile::Empty),
+                // secluded tiles are usually next to walls, so only other
+                // chests are kept away
+                TreasurePlacement::Secluded => self
+                    .get_legal_neighbors_with_diagonals(point)
+                    .all(|point| !self.map.at(point, self.width).is_treasure_chest()),
+            };
+
+            if !is_placeable || cut[point.to_index(width)] {
+                continue;
+            }
+
+            self.frame_from_current_state(10);
+            *self.map.at_mut(point, self.width) = DungeonTile::TreasureChest {
+                contents: ChestId::new(chests.len() as u16),
+            };
+            let hidden = hidden[point.to_index(width)];
+            chests.push(
+                config
+                    .loot
+                    .roll(&mut self.rng, self.id, hidden.unwrap_or_default()),
+            );
+            amount.next();
+
+            // the chest may have turned some of the tiles around it into cut
+            // tiles
+            cut = self.cut_tiles();
         }
 
         FloorBuilder {
@@ -69,9 +500,195 @@ impl FloorBuilder<HasSecretPassages> {
             height: self.height,
             map: self.map,
             noise_map: self.noise_map,
-            extra: Filled {},
+            mask: self.mask,
+            vaults: self.vaults,
+            extra: Filled {
+                chests,
+                spawn_points: vec![],
+            },
             frames: self.frames,
             id: self.id,
+            rng: self.rng,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_has_secret_connections {
+    use super::*;
+    use crate::{test_utils::cave_floor, GenerationConfig};
+
+    #[test]
+    fn test_place_keys_unlocks_door_without_room_for_key() {
+        let builder = FloorBuilder::from_ascii(
+            &[
+                "##########",
+                "#<L......#",
+                "###......#",
+                "#........#",
+                "#........#",
+                "#........#",
+                "#........#",
+                "#........#",
+                "#.......>#",
+                "##########",
+            ],
+            HasSecretPassages,
+        )
+        .place_keys()
+        .unwrap();
+
+        let width = builder.width.as_unbounded() as usize;
+        assert_eq!(
+            builder.map[width + 2],
+            DungeonTile::SecretDoor {
+                requires_key: false,
+                is_open: false,
+            }
+        );
+        assert!(!builder
+            .map
+            .iter()
+            .any(|tile| tile.is_locked() || tile.is_key()));
+
+        let distances = DistanceMap::new(
+            &builder.map,
+            builder.width,
+            builder.height,
+            [Point::from_index(width + 1, width).unwrap()],
+        );
+        let exit = builder.map.iter().position(|tile| tile.is_exit()).unwrap();
+        assert!(distances.values()[exit].is_some());
+    }
+
+    #[test]
+    fn test_chests_never_block_the_way_to_a_locked_door() {
+        // the right half can be reached from the left half either through the
+        // gap in the middle or by going around through the locked door; only
+        // the gap can actually be walked through
+        let rows = [
+            "##########",
+            "#<..#...>#",
+            "#...#....#",
+            "#........#",
+            "#...#....#",
+            "#...##L###",
+            "#...#....#",
+            "#........#",
+            "#...#....#",
+            "##########",
+        ];
+        let width = rows[0].len();
+        let gap = 3 * width + 4;
+        let door = 5 * width + 6;
+
+        let builder = FloorBuilder::from_ascii(&rows, HasSecretPassages);
+
+        let cut = builder.cut_tiles();
+        assert!(cut[gap]);
+        assert!(cut[door - width]);
+        assert!(cut[door + width]);
+
+        let builder = builder.place_treasure_chests(&TreasureConfig {
+            min_chests: 100,
+            max_chests: 100,
+            placement: TreasurePlacement::Secluded,
+            ..TreasureConfig::default()
+        });
+
+        assert!(builder.map[gap].is_empty());
+        assert!(builder.map[door - width].is_empty());
+        assert!(builder.map[door + width].is_empty());
+    }
+
+    #[test]
+    fn test_exit_distance_is_not_walked_through_locked_doors() {
+        let rows = [
+            "##########",
+            "#<.......#",
+            "#........#",
+            "#........#",
+            "####L#####",
+            "#........#",
+            "#.......>#",
+            "#........#",
+            "#........#",
+            "##########",
+        ];
+        let width = rows[0].len();
+        let behind_door = 6 * width + 8;
+
+        let builder = FloorBuilder::from_ascii(&rows, HasSecretPassages)
+            .ensure_exit_distance(5)
+            .unwrap();
+
+        let exit = builder.map.iter().position(|tile| tile.is_exit()).unwrap();
+        assert!(builder.map[behind_door].is_empty());
+        assert!(exit < 4 * width);
+
+        // without a minimum distance, the exit stays behind the door
+        let builder = FloorBuilder::from_ascii(&rows, HasSecretPassages)
+            .ensure_exit_distance(0)
+            .unwrap();
+        assert!(builder.map[behind_door].is_exit());
+    }
+
+    #[test]
+    fn test_treasure_placement() {
+        // the average amount of solid tiles around the chests
+        let mut enclosure = vec![];
+
+        for placement in [
+            TreasurePlacement::Random,
+            TreasurePlacement::Noise,
+            TreasurePlacement::Secluded,
+        ] {
+            let mut config = GenerationConfig::default();
+            config.treasure.placement = placement;
+
+            let mut walls = 0;
+            let mut chests = 0;
+
+            for seed in 0..5 {
+                let floor = cave_floor(60, &config, seed);
+
+                // the chests don't cut off any part of the floor
+                let reachable = floor.distance_map(floor.entrance());
+                for (point, tile) in floor.iter_points_and_tiles() {
+                    if !tile.is_solid() {
+                        assert!(reachable.get(point).is_some(), "{:?} is cut off", point);
+                    }
+                }
+
+                for (point, tile) in floor.iter_points_and_tiles() {
+                    if !tile.is_treasure_chest() {
+                        continue;
+                    }
+
+                    let neighbours = [
+                        point.saturating_sub_row(1),
+                        point.saturating_add_row(1),
+                        point.saturating_sub_column(1),
+                        point.saturating_add_column(1),
+                    ];
+                    assert!(neighbours
+                        .iter()
+                        .any(|&neighbour| reachable.get(neighbour).is_some()));
+
+                    walls += neighbours
+                        .iter()
+                        .filter(|&&neighbour| floor.at(neighbour).is_solid())
+                        .count();
+                    chests += 1;
+                }
+            }
+
+            assert!(chests > 0);
+            enclosure.push(walls as f64 / chests as f64);
         }
+
+        // secluded chests are tucked away in dead ends and alcoves
+        assert!(enclosure[2] > enclosure[0]);
+        assert!(enclosure[2] > 1.0);
     }
 }
@@ -22,6 +22,11 @@ pub trait Smoothable: FloorBuilderState {}
 /// [`FloorBuilder`]: crate::floor_builder::FloorBuilder
 pub(in crate::floor_builder) mod blank;
 
+/// A [`FloorBuilder`] that has had it's floor carved out of solid walls.
+///
+/// [`FloorBuilder`]: crate::floor_builder::FloorBuilder
+pub(in crate::floor_builder) mod carved;
+
 /// A [`FloorBuilder`] that has state to write to it's internal map.
 ///
 /// [`FloorBuilder`]: crate::floor_builder::FloorBuilder
@@ -1,11 +1,10 @@
 use crate::{
-    floor_builder::{MAX_FLOOR_SIZE, MIN_FLOOR_SIZE},
-    DungeonTile, Floor, FloorBuilder, FloorId,
+    world::NoiseOffset, BspGenerator, DrunkardsWalkGenerator, DungeonTile, DungeonType, Floor,
+    FloorBuilder, FloorRequest, ForestConfig, GenerationConfig, GenerationError, MazeGenerator,
+    Point, WfcGenerator,
 };
 
-use bounded_int::BoundedInt;
-
-use super::{blank::Blank, has_borders::BuildConnectionIterations, FloorBuilderState};
+use super::{blank::Blank, carved::Carved, FloorBuilderState};
 
 /// The initial state of the floor builder. Entry point to the state machine.
 #[derive(Debug)]
@@ -15,34 +14,265 @@ impl FloorBuilderState for New {
 }
 
 impl FloorBuilder<New> {
-    /// Creates a new floor builder with the provided values.
-    pub(in crate) fn create(
-        id: FloorId,
-        width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
-        height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
-        gif_output: bool,
-    ) -> Floor {
-        FloorBuilder::<Blank>::blank(id, width, height, gif_output)
-            .random_fill()
+    /// Creates a new floor builder for the request and runs it to completion,
+    /// using the cellular automata recipe for the requested [`DungeonType`].
+    ///
+    /// # Errors
+    /// Errors if any stage of the generation fails. See [`GenerationError`].
+    pub(crate) fn create(request: &FloorRequest<'_>) -> Result<Floor, GenerationError> {
+        Self::create_from(Self::blank_for(request), request)
+    }
+
+    /// Creates a chunk of a [`World`](crate::World) for the request, the same
+    /// way as [`FloorBuilder::create`], but with the noise sampled from the
+    /// chunk's part of the world's noise field. The
+    /// [mask](GenerationConfig::mask) isn't applied to chunks.
+    ///
+    /// # Errors
+    /// Errors if any stage of the generation fails. See [`GenerationError`].
+    pub(crate) fn create_chunk(
+        request: &FloorRequest<'_>,
+        offset: NoiseOffset,
+    ) -> Result<Floor, GenerationError> {
+        Self::create_from(
+            Self::unmasked_blank_for(request).with_noise_offset(offset),
+            request,
+        )
+    }
+
+    /// Runs the cellular automata recipe for the requested [`DungeonType`] on
+    /// the blank floor builder.
+    fn create_from(
+        blank: FloorBuilder<Blank>,
+        request: &FloorRequest<'_>,
+    ) -> Result<Floor, GenerationError> {
+        match request.dungeon_type {
+            DungeonType::Cave => Self::create_cave(blank, request.config, request.entrance),
+            DungeonType::Forest => Self::create_forest(blank, request.config, request.entrance),
+        }
+    }
+
+    /// Creates a floor of rooms and corridors using binary space partitioning.
+    ///
+    /// # Errors
+    /// Errors if any stage of the generation fails. See [`GenerationError`].
+    pub(crate) fn create_bsp(
+        request: &FloorRequest<'_>,
+        generator: &BspGenerator,
+    ) -> Result<Floor, GenerationError> {
+        let carved = Self::blank_for(request).carve_bsp(generator).inspect();
+
+        Self::finish_carved(carved, request)
+    }
+
+    /// Creates a floor by carving it out with a drunkard's walk.
+    ///
+    /// # Errors
+    /// Errors if any stage of the generation fails. See [`GenerationError`].
+    pub(crate) fn create_drunkards_walk(
+        request: &FloorRequest<'_>,
+        generator: &DrunkardsWalkGenerator,
+    ) -> Result<Floor, GenerationError> {
+        let carved = Self::blank_for(request)
+            .carve_drunkards_walk(generator)
+            .inspect();
+
+        Self::finish_carved(carved, request)
+    }
+
+    /// Creates a maze floor using a recursive backtracker.
+    ///
+    /// # Errors
+    /// Errors if any stage of the generation fails. See [`GenerationError`].
+    pub(crate) fn create_maze(
+        request: &FloorRequest<'_>,
+        generator: &MazeGenerator,
+    ) -> Result<Floor, GenerationError> {
+        let carved = Self::blank_for(request).carve_maze(generator).inspect();
+
+        Self::finish_carved(carved, request)
+    }
+
+    /// Creates a floor that looks like the sample of the generator, using wave
+    /// function collapse. Unlike the other carved floors, the generated floor
+    /// can be split into many separate areas, so they're joined up with
+    /// tunnels the same way the caves are before the secret passages are
+    /// added.
+    ///
+    /// # Errors
+    /// Errors if any stage of the generation fails. See [`GenerationError`].
+    pub(crate) fn create_wfc(
+        request: &FloorRequest<'_>,
+        generator: &WfcGenerator,
+    ) -> Result<Floor, GenerationError> {
+        Self::blank_for(request)
+            .collapse_wfc(generator)?
+            .inspect()
+            .place_entrance_and_exit(request.entrance)?
+            .inspect()
+            .smoothen(0, |_| false)
+            .inspect()
+            .get_cave_borders()
+            .inspect()
+            .build_connections(request.config.connection_iterations)
+            .inspect()
+            .trace_connection_paths(request.config.wide_connections, false)
+            .inspect()
+            .draw(|_, _, _| DungeonTile::Empty)?
+            .inspect()
+            .smoothen(0, |_| false)
+            .inspect()
+            .check_for_secret_passages()?
+            .inspect()
+            .ensure_exit_distance(request.config.min_exit_distance)?
+            .inspect()
+            .place_treasure_chests(&request.config.treasure)
+            .inspect()
+            .place_spawn_points(&request.config.monsters)
+            .inspect()
+            .finish(request.dungeon_type)
+    }
+
+    /// Creates a blank floor builder for the request, masked with the
+    /// request's [mask](GenerationConfig::mask).
+    fn blank_for(request: &FloorRequest<'_>) -> FloorBuilder<Blank> {
+        Self::unmasked_blank_for(request).with_mask(&request.config.mask)
+    }
+
+    /// Creates a blank floor builder for the request, without masking out any
+    /// of it's tiles.
+    fn unmasked_blank_for(request: &FloorRequest<'_>) -> FloorBuilder<Blank> {
+        FloorBuilder::<Blank>::blank(
+            request.id,
+            request.width,
+            request.height,
+            request.seed,
+            request.gif_output,
+        )
+    }
+
+    /// The shared end of the recipes for carved floors: places the entrance
+    /// and the exit, joins up anything that was left unconnected with secret
+    /// passages, and fills the floor with treasure.
+    fn finish_carved(
+        carved: FloorBuilder<Carved>,
+        request: &FloorRequest<'_>,
+    ) -> Result<Floor, GenerationError> {
+        carved
+            .place_entrance_and_exit(request.entrance)?
+            .inspect()
+            .smoothen(0, |_| false)
+            .inspect()
+            .check_for_secret_passages()?
+            .inspect()
+            .ensure_exit_distance(request.config.min_exit_distance)?
+            .inspect()
+            .place_treasure_chests(&request.config.treasure)
+            .inspect()
+            .place_spawn_points(&request.config.monsters)
+            .inspect()
+            .finish(request.dungeon_type)
+    }
+
+    /// Cave recipe: cellular automata caves, connected by wide tunnels.
+    ///
+    /// See <http://roguebasin.roguelikedevelopment.org/index.php?title=Cellular_Automata_Method_for_Generating_Random_Cave-Like_Levels>
+    fn create_cave(
+        blank: FloorBuilder<Blank>,
+        config: &GenerationConfig,
+        entrance: Option<Point>,
+    ) -> Result<Floor, GenerationError> {
+        let GenerationConfig {
+            initial_smoothing,
+            final_smoothing,
+            ..
+        } = *config;
+
+        blank
+            .random_fill(&config.noise, |_| config.wall_percent_chance)
+            .inspect()
+            .trace_original_path(entrance)?
+            .inspect()
+            .smoothen(initial_smoothing.iterations, |r| {
+                r < initial_smoothing.new_wall_iterations
+            })
+            .inspect()
+            .place_vaults(&config.vaults)
+            .inspect()
+            .get_cave_borders()
+            .inspect()
+            .build_connections(config.connection_iterations)
+            .inspect()
+            .trace_connection_paths(config.wide_connections, config.connections_use_noise_map)
+            .inspect()
+            .draw(|_, _, _| DungeonTile::Empty)?
+            .inspect()
+            .smoothen(final_smoothing.iterations, |r| {
+                r < final_smoothing.new_wall_iterations
+            })
+            .inspect()
+            .check_for_secret_passages()?
+            .inspect()
+            .ensure_exit_distance(config.min_exit_distance)?
+            .inspect()
+            .place_treasure_chests(&config.treasure)
             .inspect()
-            .trace_original_path()
+            .place_spawn_points(&config.monsters)
             .inspect()
-            .smoothen(3, |r| r < 4)
+            .finish(DungeonType::Cave)
+    }
+
+    /// Forest recipe: the noise map splits the floor into open clearings and
+    /// dense thickets of trees (walls), which are then joined by narrow trails
+    /// that wind along the noise map.
+    ///
+    /// The trails are drawn *after* the last smoothing pass, since smoothing
+    /// would close them up.
+    fn create_forest(
+        blank: FloorBuilder<Blank>,
+        config: &GenerationConfig,
+        entrance: Option<Point>,
+    ) -> Result<Floor, GenerationError> {
+        let ForestConfig {
+            clearing_tree_percent_chance,
+            thicket_tree_percent_chance,
+            smoothing,
+            wide_trails,
+        } = config.forest;
+
+        blank
+            .random_fill(&config.noise, |noise| {
+                if noise > u16::MAX / 2 {
+                    thicket_tree_percent_chance
+                } else {
+                    clearing_tree_percent_chance
+                }
+            })
+            .inspect()
+            .trace_original_path(entrance)?
+            .inspect()
+            .smoothen(smoothing.iterations, |r| r < smoothing.new_wall_iterations)
+            .inspect()
+            .place_vaults(&config.vaults)
             .inspect()
             .get_cave_borders()
             .inspect()
-            .build_connections(BuildConnectionIterations::Finite(20))
+            .build_connections(config.connection_iterations)
+            .inspect()
+            .trace_connection_paths(wide_trails, true)
+            .inspect()
+            .draw(|_, _, _| DungeonTile::Empty)?
             .inspect()
-            .trace_connection_paths(true, true)
+            .smoothen(0, |_| false)
             .inspect()
-            .draw(|_, _, _| DungeonTile::Empty)
+            .check_for_secret_passages()?
             .inspect()
-            .smoothen(7, |_| false)
+            .ensure_exit_distance(config.min_exit_distance)?
             .inspect()
-            .check_for_secret_passages()
+            .place_treasure_chests(&config.treasure)
             .inspect()
-            .place_treasure_chests()
+            .place_spawn_points(&config.monsters)
             .inspect()
-            .finish()
+            .finish(DungeonType::Forest)
     }
 }
@@ -1,11 +1,11 @@
 use std::convert::TryInto;
 
+use bounded_int::BoundedInt;
 use pathfinding::prelude::dijkstra;
-use rand::{thread_rng, Rng};
+use rand::{prelude::SliceRandom, Rng};
 
 use crate::{
-    distance, point_index::PointIndex,
-    Column, DungeonTile, FloorBuilder, Point, Row,
+    point_index::PointIndex, Column, DungeonTile, FloorBuilder, GenerationError, Point, Row,
 };
 
 use super::{filled::Filled, FloorBuilderState};
@@ -19,49 +19,51 @@ impl FloorBuilderState for RandomFilled {
 
 impl FloorBuilder<RandomFilled> {
     /// Traces the original path through the map from the entrance to the exit.
-    pub(in crate::floor_builder) fn trace_original_path(mut self) -> FloorBuilder<Filled> {
-        let mut rng = thread_rng();
-        let start = Point {
-            row: Row::new(
-                rng.gen_range(1..(self.height.as_unbounded() - 1))
-                    .try_into()
-                    .unwrap(),
-            ),
-            column: Column::new(
-                rng.gen_range(1..(self.width.as_unbounded() - 1))
-                    .try_into()
-                    .unwrap(),
-            ),
+    ///
+    /// The entrance is placed at the provided point, or at a random point if
+    /// there isn't one. Neither of them are placed on a tile that has been
+    /// masked out.
+    ///
+    /// # Errors
+    /// Errors if the mask leaves less than 2 tiles open, if no path can be
+    /// found between the entrance and the exit, or if either of them is
+    /// overwritten while tracing the path.
+    pub(in crate::floor_builder) fn trace_original_path(
+        mut self,
+        entrance: Option<Point>,
+    ) -> Result<FloorBuilder<Filled>, GenerationError> {
+        let start = match entrance {
+            Some(entrance) => entrance,
+            None => self.random_open_point()?,
         };
 
-        let end = loop {
-            let larger_dimension = if self.width > self.height {
-                self.width
-            } else {
-                self.height
-            };
-            let maybe_end = Point {
-                row: Row::new(
-                    rng.gen_range(1..(self.height.as_unbounded() - 1))
-                        .try_into()
-                        .unwrap(),  
-                ),
-                column: Column::new(
-                    rng.gen_range(1..(self.width.as_unbounded() - 1))
-                        .try_into()
-                        .unwrap(),
-                ),
-            };
-            let dist = distance(maybe_end, start);
+        let larger_dimension = if self.width > self.height {
+            self.width
+        } else {
+            self.height
+        };
+        let mut end = None;
+        for _ in 0..EXIT_ATTEMPTS {
+            let maybe_end = self.random_point()?;
+            let dist = maybe_end.distance(start);
 
-            #[allow(clippy::redundant_else)] // I prefer the explicitness here
-            if dist > (larger_dimension.as_unbounded() as f64 / 2.0)
+            if !self.is_out_of_bounds(maybe_end)
+                && dist > (larger_dimension.as_unbounded() as f64 / 2.0)
                 && dist < (larger_dimension.as_unbounded() as f64)
             {
-                break maybe_end;
-            } else {
-                continue;
+                end = Some(maybe_end);
+                break;
             }
+        }
+        // a mask can leave no tiles far enough away from the entrance, in
+        // which case the exit is placed as far away as it can be
+        let end = match end {
+            Some(end) => end,
+            None => self
+                .open_points()
+                .filter(|&point| point != start)
+                .max_by(|a, b| a.distance(start).total_cmp(&b.distance(start)))
+                .ok_or(GenerationError::TooFewOpenTiles(1))?,
         };
 
         let (found_path, _) = dijkstra(
@@ -72,7 +74,10 @@ impl FloorBuilder<RandomFilled> {
             },
             |&point| !self.is_out_of_bounds(point) && point == end,
         )
-        .expect("no path found");
+        .ok_or(GenerationError::UnreachableExit {
+            entrance: start,
+            exit: end,
+        })?;
 
         *self.map.at_mut(start, self.width) = DungeonTile::Entrance;
         *self.map.at_mut(end, self.width) = DungeonTile::Exit;
@@ -91,17 +96,86 @@ impl FloorBuilder<RandomFilled> {
             }
             self.frame_from_current_state(1);
         }
-        assert_eq!(self.map.at(start, self.width), &DungeonTile::Entrance);
-        assert_eq!(self.map.at(end, self.width), &DungeonTile::Exit);
+        for (point, expected) in [(start, DungeonTile::Entrance), (end, DungeonTile::Exit)] {
+            let found = *self.map.at(point, self.width);
+            if found != expected {
+                return Err(GenerationError::TileOverwritten {
+                    point,
+                    expected,
+                    found,
+                });
+            }
+        }
         self.frame_from_current_state(100);
-        FloorBuilder {
-            extra: Filled {},
+        Ok(FloorBuilder {
+            extra: Filled {
+                chests: vec![],
+                spawn_points: vec![],
+            },
             height: self.height,
             width: self.width,
             map: self.map,
             noise_map: self.noise_map,
+            mask: self.mask,
+            vaults: self.vaults,
             frames: self.frames,
             id: self.id,
+            rng: self.rng,
+        })
+    }
+
+    /// Picks a random point within the inside of the map, which may have been
+    /// masked out.
+    fn random_point(&mut self) -> Result<Point, GenerationError> {
+        Ok(Point {
+            row: Row::new(
+                self.rng
+                    .gen_range(1..(self.height.as_unbounded() - 1))
+                    .try_into()?,
+            ),
+            column: Column::new(
+                self.rng
+                    .gen_range(1..(self.width.as_unbounded() - 1))
+                    .try_into()?,
+            ),
+        })
+    }
+
+    /// Picks a random point that isn't out of bounds. Random points are tried
+    /// first, since most of the map usually isn't masked out; if none of
+    /// them are inside of the mask, one of the open points is picked instead.
+    ///
+    /// # Errors
+    /// Errors if every point is out of bounds.
+    fn random_open_point(&mut self) -> Result<Point, GenerationError> {
+        for _ in 0..EXIT_ATTEMPTS {
+            let point = self.random_point()?;
+            if !self.is_out_of_bounds(point) {
+                return Ok(point);
+            }
         }
+
+        let open = self.open_points().collect::<Vec<_>>();
+        open.choose(&mut self.rng)
+            .copied()
+            .ok_or(GenerationError::TooFewOpenTiles(0))
+    }
+
+    /// Returns every point of the map that isn't out of bounds, column by
+    /// column.
+    fn open_points(&self) -> impl Iterator<Item = Point> + '_ {
+        let columns = 1..self.width.as_unbounded() - 1;
+        columns
+            .flat_map(move |column| {
+                (1..self.height.as_unbounded() - 1).map(move |row| Point {
+                    row: Row::new(BoundedInt::new_clamped(row)),
+                    column: Column::new(BoundedInt::new_clamped(column)),
+                })
+            })
+            .filter(move |&point| !self.is_out_of_bounds(point))
     }
 }
+
+/// How many random points are tried when looking for the entrance or the exit
+/// before falling back to looking through every open point.
+const EXIT_ATTEMPTS: usize = 1000;
@@ -1,19 +1,21 @@
-use std::{
-    collections::{HashSet, VecDeque},
-    convert::TryInto,
-};
+use std::collections::BTreeSet;
 
-use rand::prelude::SliceRandom;
+use rand::{prelude::SliceRandom, Rng};
 
 use crate::{
     border::{Border, BorderId},
-    floor_builder::floor_builder_state::has_borders::{BuildConnectionIterations, HasBorders},
+    floor_builder::{floor_builder_state::has_borders::HasBorders, is_cave},
     point_index::PointIndex,
-    Column, DungeonTile, FloorBuilder, Point, Row,
+    BuildConnectionIterations, DistanceMap, DungeonTile, FloorBuilder, GenerationError,
+    PlacedVault, Point, Vault, VaultConfig, VaultLayout, VaultTile,
 };
 
 use super::{has_secret_connections::HasSecretPassages, FloorBuilderState};
 
+/// How many tiles of space are left around every vault, between it and the
+/// edges of the map, masked out tiles and the other vaults.
+const VAULT_CLEARANCE: usize = 2;
+
 /// A [`FloorBuilder`] that has run the cellular automata on it's map; all the
 /// rough edges of the caves have been smoothed out.
 #[derive(Debug)]
@@ -24,65 +26,171 @@ impl FloorBuilderState for Smoothed {
 }
 
 impl FloorBuilder<Smoothed> {
-    /// Finds the borders around all of the caves in the [`FloorBuilder`],
-    /// leaving them in the [`HasBorders`] state.
-    pub(in crate::floor_builder) fn get_cave_borders(self) -> FloorBuilder<HasBorders> {
-        let mut already_visited = vec![
-            false;
-            (self.width.as_unbounded() * self.height.as_unbounded())
-                .try_into()
-                .unwrap()
-        ];
-
-        let mut borders = vec![];
-
-        // loop through the entire map
-        for column in self.width.expand_lower().range_from(0.try_into().unwrap()) {
-            'rows: for row in self.height.expand_lower().range_from(0.try_into().unwrap()) {
-                let point = Point {
-                    column: Column::new(column),
-                    row: Row::new(row),
-                };
-                // if the point has already been visited (by either the main loop or the cave
-                // searching) then continue looping through the map
-                if *already_visited.at(point, self.width) {
-                    continue 'rows;
-                }
-                // otherwise, mark the point as visited
-                *already_visited.at_mut(point, self.width) = true;
-
-                // if there's an empty space at the point, BFS to find the border of the cave
-                // (no diagonals)
-                if self.map.at(point, self.width).is_empty() {
-                    let mut border = HashSet::new();
-
-                    let mut queue = self.get_legal_neighbors(point).collect::<VecDeque<_>>();
-
-                    loop {
-                        if let Some(point) = queue.pop_front() {
-                            // if point is empty, mark it as visited and then add all of it's
-                            // legal neighbors to the queue
-                            if self.map.at(point, self.width).is_empty() {
-                                if *already_visited.at(point, self.width) {
-                                    continue;
-                                }
-                                *already_visited.at_mut(point, self.width) = true;
-                                self.get_legal_neighbors(point)
-                                    .for_each(|p| queue.push_back(p));
-                            } else {
-                                border.insert(point);
-                            }
-                        } else {
-                            if !border.is_empty() {
-                                // add the found cave to the collection of all caves
-                                borders.push(border);
-                            }
-                            continue 'rows;
+    /// Stamps vaults picked from [`VaultConfig::prefabs`] into the map, each
+    /// at a random position (see [`FloorBuilder::vault_positions`]). The walls
+    /// of the vaults are masked out, so that the tunnels joining up the caves
+    /// can only get into the vaults through their doors.
+    ///
+    /// Nothing is placed (and the rng is left untouched) if there are no
+    /// prefabs to pick from.
+    pub(in crate::floor_builder) fn place_vaults(mut self, config: &VaultConfig) -> Self {
+        if config.prefabs.is_empty() {
+            return self;
+        }
+
+        let amount = self.rng.gen_range(config.min_vaults..=config.max_vaults);
+        for _ in 0..amount {
+            let vault = config.prefabs.choose(&mut self.rng).unwrap();
+            let positions = self.vault_positions(&vault.layout);
+
+            if let Some(&(row, column)) = positions.choose(&mut self.rng) {
+                self.stamp_vault(vault, row, column);
+                self.frame_from_current_state(50);
+            }
+        }
+
+        self
+    }
+
+    /// Returns the row and column of every position the top left corner of
+    /// the layout can be placed at.
+    ///
+    /// The vault has to have [`VAULT_CLEARANCE`] tiles of unmasked space
+    /// around it on every side, so that the tunnels joining up the caves can
+    /// always find a way around it. It's walls can only go over walls (so
+    /// that placing it never cuts a cave in two), and at least one of it's
+    /// doors has to open onto a tile that can be reached from the entrance.
+    fn vault_positions(&self, layout: &VaultLayout) -> Vec<(usize, usize)> {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.height.as_unbounded() as usize;
+        let entrance = self
+            .map
+            .iter()
+            .position(|tile| tile.is_entrance())
+            .and_then(|entrance| Point::from_index(entrance, width).ok());
+        let from_entrance = DistanceMap::new(&self.map, self.width, self.height, entrance);
+        let stamped = layout.tiles_where(|_| true);
+        let doors = layout.tiles_where(|tile| tile == VaultTile::Door);
+
+        // the first and last rows and columns the top left corner can be at,
+        // leaving room for the clearance and the edges of the map
+        let first = VAULT_CLEARANCE + 1;
+        let last_row = (height + first).checked_sub(layout.height() + 2 * first);
+        let last_column = (width + first).checked_sub(layout.width() + 2 * first);
+        let (last_row, last_column) = match (last_row, last_column) {
+            (Some(last_row), Some(last_column)) if last_row >= first && last_column >= first => {
+                (last_row, last_column)
+            }
+            _ => return vec![],
+        };
+
+        (first..=last_row)
+            .flat_map(|row| (first..=last_column).map(move |column| (row, column)))
+            .filter(|&(row, column)| {
+                // keep the clearances of the vaults from overlapping, so that
+                // there's always a way between them
+                !self.vaults.iter().any(|vault| {
+                    let top = vault.top_left.row.get().as_unbounded() as usize;
+                    let left = vault.top_left.column.get().as_unbounded() as usize;
+                    row < top + vault.layout.height() + 2 * VAULT_CLEARANCE
+                        && top < row + layout.height() + 2 * VAULT_CLEARANCE
+                        && column < left + vault.layout.width() + 2 * VAULT_CLEARANCE
+                        && left < column + layout.width() + 2 * VAULT_CLEARANCE
+                })
+            })
+            .filter(|&(row, column)| {
+                (row - VAULT_CLEARANCE..row + layout.height() + VAULT_CLEARANCE).all(|row| {
+                    (column - VAULT_CLEARANCE..column + layout.width() + VAULT_CLEARANCE)
+                        .all(|column| !self.mask[row * width + column])
+                })
+            })
+            .filter(|&(row, column)| {
+                // never stamp over the entrance, the exit or a key, and only
+                // stamp walls over walls, so that a vault never cuts off part
+                // of a cave
+                stamped.iter().all(|&(tile_row, tile_column)| {
+                    match self.map[(row + tile_row) * width + column + tile_column] {
+                        DungeonTile::Wall => true,
+                        DungeonTile::Empty => {
+                            layout.tile(tile_row, tile_column) != Some(VaultTile::Wall)
                         }
+                        _ => false,
                     }
+                })
+            })
+            .filter(|&(row, column)| {
+                // the clearance keeps the tiles outside of the doors inside of
+                // the map
+                doors.iter().any(|&(door_row, door_column)| {
+                    layout.outside_neighbours(door_row, door_column).any(
+                        |(row_offset, column_offset)| {
+                            let neighbour_row = (row + door_row) as isize + row_offset;
+                            let neighbour_column = (column + door_column) as isize + column_offset;
+                            from_entrance.values()
+                                [neighbour_row as usize * width + neighbour_column as usize]
+                                .is_some()
+                        },
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Stamps the vault into the map with the top left corner of it's layout
+    /// at the row and column, masking out it's walls.
+    fn stamp_vault(&mut self, vault: &Vault, row: usize, column: usize) {
+        let width = self.width.as_unbounded() as usize;
+        let point =
+            |row: usize, column: usize| Point::from_index(row * width + column, width).unwrap();
+
+        let mut placed = PlacedVault {
+            name: vault.name.clone(),
+            top_left: point(row, column),
+            layout: vault.layout.clone(),
+            doors: vec![],
+            anchors: vec![],
+        };
+
+        for (tile_row, tile_column) in vault.layout.tiles_where(|_| true) {
+            let (row, column) = (row + tile_row, column + tile_column);
+            let index = row * width + column;
+
+            self.map[index] = match vault.layout.tile(tile_row, tile_column) {
+                Some(VaultTile::Wall) => {
+                    self.mask[index] = true;
+                    DungeonTile::Wall
                 }
-            }
+                Some(VaultTile::Door) => {
+                    placed.doors.push(point(row, column));
+                    DungeonTile::Empty
+                }
+                Some(VaultTile::Anchor) => {
+                    placed.anchors.push(point(row, column));
+                    DungeonTile::Empty
+                }
+                Some(VaultTile::Floor) | None => DungeonTile::Empty,
+            };
         }
+
+        self.vaults.push(placed);
+    }
+
+    /// Finds the borders around all of the caves in the [`FloorBuilder`],
+    /// leaving them in the [`HasBorders`] state.
+    pub(in crate::floor_builder) fn get_cave_borders(mut self) -> FloorBuilder<HasBorders> {
+        // the border of a cave is every tile around it that isn't part of it
+        let borders = self
+            .caves()
+            .into_iter()
+            .map(|cave| {
+                cave.iter()
+                    .flat_map(|&point| self.get_legal_neighbors(point))
+                    .filter(|&point| !is_cave(*self.map.at(point, self.width)))
+                    .collect::<BTreeSet<_>>()
+            })
+            .filter(|border| !border.is_empty())
+            .collect::<Vec<_>>();
+
         let mut vec_of_borders = borders
             .iter()
             .enumerate()
@@ -91,7 +199,7 @@ impl FloorBuilder<Smoothed> {
                 points: hashset.clone(),
             })
             .collect::<Vec<_>>();
-        vec_of_borders.shuffle(&mut rand::thread_rng());
+        vec_of_borders.shuffle(&mut self.rng);
 
         FloorBuilder {
             extra: HasBorders {
@@ -101,20 +209,38 @@ impl FloorBuilder<Smoothed> {
             width: self.width,
             map: self.map,
             noise_map: self.noise_map,
+            mask: self.mask,
+            vaults: self.vaults,
             frames: self.frames,
             id: self.id,
+            rng: self.rng,
         }
     }
 
     /// Adds secret passages to the map between all of the remaining disjointed
-    /// caves.
+    /// caves. Any caves that can't be reached with a secret passage (when
+    /// every path to them would have to go through another cave) are filled
+    /// in, so that the entire floor is reachable from the entrance.
+    ///
+    /// The doors of the secret passages are locked, and a key for each of
+    /// them is placed somewhere it can be reached from the entrance without
+    /// going through the door.
+    ///
+    /// # Errors
+    /// Errors if the secret passages can't be drawn (see
+    /// [`FloorBuilder<Drawable>::draw`]), or if the exit couldn't be reached.
+    ///
+    /// [`FloorBuilder<Drawable>::draw`]: crate::floor_builder::FloorBuilder::draw
     pub(in crate::floor_builder) fn check_for_secret_passages(
         self,
-    ) -> FloorBuilder<HasSecretPassages> {
-        println!("starting secret passages search");
+    ) -> Result<FloorBuilder<HasSecretPassages>, GenerationError> {
         let mut self_with_borders = self.get_cave_borders();
 
-        dbg!(self_with_borders.extra.borders.len());
+        log::debug!(
+            "floor {:?}: searching {} caves for secret passages",
+            self_with_borders.id,
+            self_with_borders.extra.borders.len()
+        );
         // if there is more than 1 cave (border), find secret passages
         if self_with_borders.extra.borders.len() > 1 {
             self_with_borders = self_with_borders
@@ -131,7 +257,7 @@ impl FloorBuilder<Smoothed> {
                     } else {
                         DungeonTile::SecretPassage
                     }
-                })
+                })?
                 .smoothen(0, |_| false)
                 .inspect()
                 .get_cave_borders()
@@ -144,9 +270,14 @@ impl FloorBuilder<Smoothed> {
             width: new_self.width,
             map: new_self.map,
             noise_map: new_self.noise_map,
+            mask: new_self.mask,
+            vaults: new_self.vaults,
             extra: HasSecretPassages {},
             frames: new_self.frames,
             id: new_self.id,
+            rng: new_self.rng,
         }
+        .fill_unreachable_caves()?
+        .place_keys()
     }
 }
@@ -3,19 +3,34 @@ use crate::{
     dungeon_tile::DungeonTile,
     floor_builder::{floor_builder_state::*, to_block_character::ToAsciiCharacter},
     point_index::PointIndex,
-    Column, FloorId, Point, Row,
+    Column, FloorId, PlacedVault, Point, Row, Seed,
 };
 use bounded_int::BoundedInt;
 use gif::Frame;
 use itertools::Itertools;
 use pathfinding::prelude::dijkstra;
+use rand_chacha::ChaCha8Rng;
 
-use std::{borrow::Cow, convert::TryInto, fmt::Debug, vec};
+use std::{borrow::Cow, collections::BTreeSet, convert::TryInto, fmt::Debug, vec};
 
-use self::floor_builder_state::{blank::Blank, smoothed::Smoothed};
+use self::{
+    floor_builder_state::{blank::Blank, smoothed::Smoothed},
+    smoothing::SmoothingGrid,
+};
 
 mod floor_builder_state;
 
+/// The overlapping model of wave function collapse, used by the
+/// [`WfcGenerator`](crate::WfcGenerator).
+///
+/// See the type-level documentation for more information.
+mod wave_function_collapse;
+
+/// Counting the walls around the tiles of a map while it is being smoothed.
+///
+/// See the type-level documentation for more information.
+mod smoothing;
+
 /// Represents a type that can be 'pretty-printed' using ascii characters.
 ///
 /// See the type-level documentation for more information.
@@ -27,10 +42,6 @@ pub const MIN_FLOOR_SIZE: i32 = 10;
 /// The maximum dimensions a [`Floor`](crate::Floor) can have.
 pub const MAX_FLOOR_SIZE: i32 = 200;
 
-/// The percent chance of a wall being placed during the initial noise
-/// generation.
-const RANDOM_FILL_WALL_PERCENT_CHANCE: u8 = 52;
-
 /// Builder struct for a [`Floor`](crate::Floor).
 ///
 /// See <http://roguebasin.roguelikedevelopment.org/index.php?title=Cellular_Automata_Method_for_Generating_Random_Cave-Like_Levels>
@@ -45,6 +56,17 @@ pub struct FloorBuilder<S: FloorBuilderState> {
     /// The map containing the noise for the floor builder, used in various
     /// places during the build process.
     pub(crate) noise_map: Vec<u16>,
+    /// Whether or not each tile is masked out, either by the floor's
+    /// [`FloorMask`] or by being one of the walls of a vault. Masked tiles are
+    /// always walls, and are treated as
+    /// [out of bounds](FloorBuilder::is_out_of_bounds) throughout the build
+    /// process.
+    ///
+    /// [`FloorMask`]: crate::FloorMask
+    mask: Vec<bool>,
+    /// The vaults stamped into the map so far. The tiles inside of them are
+    /// never smoothed or drawn over.
+    vaults: Vec<PlacedVault>,
     /// The current state of the floor builder. May or may not contain extra
     /// data to be used at that stage of generation.
     extra: S,
@@ -53,6 +75,10 @@ pub struct FloorBuilder<S: FloorBuilderState> {
     frames: Option<Vec<gif::Frame<'static>>>,
     /// A unique, opaque ID assigned to the floor builder upon creation.
     id: FloorId,
+    /// The rng used for every random decision made while building the floor.
+    /// Seeded from the [`Seed`] passed in on creation, so that the same seed
+    /// always builds the same floor.
+    rng: ChaCha8Rng,
 }
 
 // #[cfg(test)]
@@ -83,20 +109,59 @@ pub struct FloorBuilder<S: FloorBuilderState> {
 
 impl<S: Smoothable> FloorBuilder<S> {
     /// Smooths out the map using cellular automata.
+    ///
+    /// The tiles are smoothed in place, column by column, so every tile sees
+    /// the tiles that were already smoothed before it in the same pass. The
+    /// rules are the same as [`FloorBuilder::place_wall_logic`], but the walls
+    /// around every tile are counted with a [`SmoothingGrid`] instead of
+    /// being looked up one by one. The tiles inside of the vaults are left as
+    /// they are.
     fn smoothen(
         mut self,
         repeat: usize,
-        create_new_walls: fn(usize) -> bool,
+        create_new_walls: impl Fn(usize) -> bool,
     ) -> FloorBuilder<Smoothed> {
+        use DungeonTile::{Empty, Wall};
+
+        let width = self.width.as_unbounded() as usize;
+        let height = self.height.as_unbounded() as usize;
+        let mut grid = SmoothingGrid::new(&self.map, width);
+        let in_vault = (0..self.map.len())
+            .map(|index| self.in_vault(Point::from_index(index, width).unwrap()))
+            .collect::<Vec<_>>();
+
         for r in 0..repeat {
-            for column in self.width.expand_lower().range_from(0.try_into().unwrap()) {
-                for row in self.height.expand_lower().range_from(0.try_into().unwrap()) {
-                    let point = Point {
-                        column: Column::new(column),
-                        row: Row::new(row),
+            let create_new_walls = create_new_walls(r);
+
+            for column in 0..width {
+                for row in 0..height {
+                    let index = row * width + column;
+                    let tile = self.map[index];
+
+                    if !matches!(tile, Empty | Wall) || in_vault[index] {
+                        continue;
+                    }
+
+                    if row == 0
+                        || column == 0
+                        || row + 1 >= height
+                        || column + 1 >= width
+                        || self.mask[index]
+                    {
+                        self.map[index] = Wall;
+                        continue;
+                    }
+
+                    let walls_1_away = grid.walls_around(row, column, 1);
+                    let is_wall = if tile.is_solid() {
+                        walls_1_away >= 4
+                            || (create_new_walls && grid.walls_around(row, column, 2) < 2)
+                    } else {
+                        walls_1_away >= 5
                     };
-                    *self.map.at_mut(point, self.width) =
-                        self.place_wall_logic(point, create_new_walls(r));
+
+                    self.map[index] = if is_wall { Wall } else { Empty };
+                    grid.set_wall(row, column, is_wall);
                 }
             }
 
@@ -107,19 +172,22 @@ impl<S: Smoothable> FloorBuilder<S> {
             height: self.height,
             map: self.map,
             noise_map: self.noise_map,
+            mask: self.mask,
+            vaults: self.vaults,
             extra: Smoothed {},
             frames: self.frames,
             id: self.id,
+            rng: self.rng,
         }
     }
 }
 
 impl<S: FloorBuilderState> FloorBuilder<S> {
-    /// Dumps the current state of the builder to stdout.
-    //
-    /// for use in debugging only
+    /// Logs the current state of the builder at the trace level, for use in
+    /// debugging only. Floors are generated in parallel, so this goes through
+    /// [`log`] rather than straight to stderr.
     fn inspect(self) -> Self {
-        dbg!(S::TYPE_NAME);
+        log::trace!("floor {:?}: {}", self.id, S::TYPE_NAME);
         self
     }
 
@@ -148,6 +216,7 @@ impl<S: FloorBuilderState> FloorBuilder<S> {
         id: FloorId,
         width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
         height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        seed: Seed,
         gif_output: bool,
     ) -> FloorBuilder<Blank> {
         FloorBuilder {
@@ -165,14 +234,51 @@ impl<S: FloorBuilderState> FloorBuilder<S> {
                     .try_into()
                     .unwrap()
             ],
-            extra: Blank {},
+            mask: vec![
+                false;
+                (width.as_unbounded() * height.as_unbounded())
+                    .try_into()
+                    .unwrap()
+            ],
+            vaults: vec![],
+            extra: Blank { noise_offset: None },
             frames: if gif_output { Some(vec![]) } else { None },
             // frames: ,
             id,
+            rng: seed.floor_rng(id),
+        }
+    }
+
+    /// Creates a [`FloorBuilder`] in any state from an ascii map, one string
+    /// per row, using the same characters as the floors built by
+    /// `test_utils::floor_from_rows`.
+    ///
+    /// for use in tests only
+    #[cfg(test)]
+    pub(in crate::floor_builder) fn from_ascii(rows: &[&str], extra: S) -> Self {
+        let floor = crate::test_utils::floor_from_rows(rows);
+        let blank = Self::blank(FloorId(0), floor.width, floor.height, Seed::new(0), false);
+
+        FloorBuilder {
+            width: blank.width,
+            height: blank.height,
+            map: floor.data,
+            noise_map: blank.noise_map,
+            mask: blank.mask,
+            vaults: blank.vaults,
+            extra,
+            frames: blank.frames,
+            id: blank.id,
+            rng: blank.rng,
         }
     }
 
-    /// will only return wall or empty
+    /// Returns what the tile at the point becomes after being smoothed; only
+    /// walls and empty tiles are changed, and only into walls or empty tiles.
+    ///
+    /// This is the straightforward version of the rules used by
+    /// [`FloorBuilder::smoothen`], which it is checked against.
+    #[cfg(test)]
     fn place_wall_logic(&self, point: Point, create_new_walls: bool) -> DungeonTile {
         use DungeonTile::{Empty, Wall};
 
@@ -231,6 +337,28 @@ impl<S: FloorBuilderState> FloorBuilder<S> {
         counter
     }
 
+    /// Finds all of the caves in the map: the groups of [cave tiles](is_cave)
+    /// that are connected up, down, left and right, leaving out the tiles that
+    /// are [out of bounds](Self::is_out_of_bounds). The caves are in the
+    /// order their first point is found in, going through the map column by
+    /// column.
+    fn caves(&self) -> Vec<BTreeSet<Point>> {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.height.as_unbounded() as usize;
+        let point = |index| Point::from_index(index, width).expect("the index is in the map");
+
+        let column_by_column =
+            (0..width).flat_map(|column| (0..height).map(move |row| row * width + column));
+        let (_, caves) = Point::connected_groups(width, height, column_by_column, |index| {
+            is_cave(self.map[index]) && !self.is_out_of_bounds(point(index))
+        });
+
+        caves
+            .into_iter()
+            .map(|cave| cave.into_iter().map(point).collect())
+            .collect()
+    }
+
     /// Considers out-of-bounds a wall
     pub fn is_wall(&self, point: Point) -> bool {
         if self.is_out_of_bounds(point) {
@@ -243,7 +371,8 @@ impl<S: FloorBuilderState> FloorBuilder<S> {
         false
     }
 
-    /// Considers the 1-wide border around the edge of the map to be out of
+    /// Considers the 1-wide border around the edge of the map, and every
+    /// tile that has been [masked out](FloorBuilder::mask), to be out of
     /// bounds.
     fn is_out_of_bounds(&self, point: Point) -> bool {
         // REVIEW: points can't be 0
@@ -251,6 +380,13 @@ impl<S: FloorBuilderState> FloorBuilder<S> {
             || point.row.get() == 0.try_into().unwrap()
             || point.column.get() >= (self.width.as_unbounded() - 1).try_into().unwrap()
             || point.row.get() >= (self.height.as_unbounded() - 1).try_into().unwrap()
+            || *self.mask.at(point, self.width)
+    }
+
+    /// Returns `true` if the point is one of the tiles of the vaults stamped into
+    /// the map.
+    fn in_vault(&self, point: Point) -> bool {
+        self.vaults.iter().any(|vault| vault.contains(point))
     }
 
     /// Gets the 8 neighbours around the specified point that aren't out of
@@ -266,13 +402,13 @@ impl<S: FloorBuilderState> FloorBuilder<S> {
         let v = vec![
             point.saturating_sub_row(1)
                  .saturating_sub_column(1), point.saturating_sub_row(1), point.saturating_sub_row(1)
-                                                                              .saturating_add_row(1),
+                                                                              .saturating_add_column(1),
 
             point.saturating_sub_column(1), point,                       point.saturating_add_column(1),
 
             point.saturating_add_row(1)
                  .saturating_sub_column(1), point.saturating_add_row(1), point.saturating_add_row(1)
-                                                                              .saturating_add_row(1),
+                                                                              .saturating_add_column(1),
         ];
 
         v.into_iter()
@@ -335,6 +471,19 @@ impl<S: FloorBuilderState> FloorBuilder<S> {
     }
 }
 
+/// Returns `true` if the tile is part of a cave.
+///
+/// The entrance and the exit are part of the cave they're in, so that they are
+/// still connected up with the rest of the floor if smoothing walls them in.
+/// Keys are part of the cave they're placed in too, so that they don't split
+/// it up.
+const fn is_cave(tile: DungeonTile) -> bool {
+    matches!(
+        tile,
+        DungeonTile::Empty | DungeonTile::Entrance | DungeonTile::Exit | DungeonTile::Key { .. }
+    )
+}
+
 #[cfg(test)]
 mod test_super {
     use crate::floor_builder::to_block_character::_print_vec_2d;
@@ -347,6 +496,7 @@ mod test_super {
             FloorId(0),
             10.try_into().unwrap(),
             10.try_into().unwrap(),
+            Seed::new(0),
             false,
         );
 
@@ -360,6 +510,7 @@ mod test_super {
             FloorId(0),
             50.try_into().unwrap(),
             100.try_into().unwrap(),
+            Seed::new(0),
             false,
         );
         let formatted = random_filled_floor._pretty(&[], &[]);
@@ -372,7 +523,8 @@ mod test_super {
         let width = 10.try_into().unwrap();
         let height = 15.try_into().unwrap();
 
-        let blank_floor = FloorBuilder::<Blank>::blank(FloorId(0), width, height, false);
+        let blank_floor =
+            FloorBuilder::<Blank>::blank(FloorId(0), width, height, Seed::new(0), false);
 
         let mut new_vec = vec![false; (width.as_unbounded() * height.as_unbounded()) as usize];
 
@@ -393,7 +545,8 @@ mod test_super {
         let width = 10.try_into().unwrap();
         let height = 15.try_into().unwrap();
 
-        let blank_floor = FloorBuilder::<Blank>::blank(FloorId(0), width, height, false);
+        let blank_floor =
+            FloorBuilder::<Blank>::blank(FloorId(0), width, height, Seed::new(0), false);
 
         let mut new_vec = vec![false; (width.as_unbounded() * height.as_unbounded()) as usize];
 
@@ -409,4 +562,45 @@ mod test_super {
 
         println!("{}", _print_vec_2d(&new_vec, width));
     }
+
+    #[test]
+    fn test_smoothing_matches_the_naive_rules() {
+        let sizes = [(10, 10), (37, 53), (64, 65), (200, 120)];
+
+        for (seed, &(width, height)) in (0..8).zip(sizes.iter().cycle()) {
+            let filled = || {
+                FloorBuilder::<Blank>::blank(
+                    FloorId(0),
+                    width.try_into().unwrap(),
+                    height.try_into().unwrap(),
+                    Seed::new(seed),
+                    false,
+                )
+                .random_fill(&crate::NoiseConfig::default(), |_| 45)
+                .trace_original_path(None)
+                .unwrap()
+            };
+            let create_new_walls = |r| r < 3;
+
+            let mut naive = filled();
+            for r in 0..6 {
+                for column in naive.width.expand_lower().range_from(0.try_into().unwrap()) {
+                    for row in naive
+                        .height
+                        .expand_lower()
+                        .range_from(0.try_into().unwrap())
+                    {
+                        let point = Point {
+                            column: Column::new(column),
+                            row: Row::new(row),
+                        };
+                        *naive.map.at_mut(point, naive.width) =
+                            naive.place_wall_logic(point, create_new_walls(r));
+                    }
+                }
+            }
+
+            assert_eq!(filled().smoothen(6, create_new_walls).map, naive.map);
+        }
+    }
 }
@@ -0,0 +1,144 @@
+use crate::dungeon_tile::DungeonTile;
+
+/// The walls of a map being smoothed, along with how many walls there are
+/// around every tile, kept up to date as the tiles change.
+///
+/// The walls are packed into a bitmap covering the map plus one extra row and
+/// column past the bottom and right edges, since the smoothing rules count
+/// the tiles just past those edges as walls (but not the ones past the top
+/// and left edges). Every tile on the edge of the map counts as a wall too,
+/// no matter what is actually there.
+///
+/// The counts start out as box sums over a summed-area table of the bitmap.
+/// Smoothing changes the map in place, so that every tile sees the tiles that
+/// were smoothed before it in the same pass; rather than rebuilding the sums
+/// after every change, the counts around a tile are adjusted whenever it
+/// turns into or out of a wall.
+#[derive(Debug)]
+pub(super) struct SmoothingGrid {
+    /// The width of the grid, one more than the width of the map.
+    width: usize,
+    /// The height of the grid, one more than the height of the map.
+    height: usize,
+    /// Whether or not every tile of the grid is a wall, row by row, 64 tiles
+    /// to a word.
+    walls: Vec<u64>,
+    /// The amount of walls within 1 tile of every tile, including itself.
+    near: Vec<u8>,
+    /// The amount of walls within 2 tiles of every tile, including itself.
+    far: Vec<u8>,
+}
+
+impl SmoothingGrid {
+    /// Creates the grid for the map, which is `width` tiles wide.
+    pub(super) fn new(map: &[DungeonTile], width: usize) -> Self {
+        let map_height = map.len() / width;
+        let (grid_width, grid_height) = (width + 1, map_height + 1);
+
+        let mut grid = Self {
+            width: grid_width,
+            height: grid_height,
+            walls: vec![0; (grid_width * grid_height).div_ceil(64)],
+            near: vec![],
+            far: vec![],
+        };
+
+        for row in 0..grid_height {
+            for column in 0..grid_width {
+                let is_wall = row == 0
+                    || column == 0
+                    || row + 1 >= map_height
+                    || column + 1 >= width
+                    || map[row * width + column].is_solid();
+                if is_wall {
+                    let index = row * grid_width + column;
+                    grid.walls[index / 64] |= 1 << (index % 64);
+                }
+            }
+        }
+
+        // sums[row][column] is the amount of walls above and to the left of
+        // the tile, exclusive
+        let sums_width = grid_width + 1;
+        let mut sums = vec![0_u32; sums_width * (grid_height + 1)];
+        for row in 0..grid_height {
+            for column in 0..grid_width {
+                sums[(row + 1) * sums_width + column + 1] = u32::from(grid.is_wall(row, column))
+                    + sums[row * sums_width + column + 1]
+                    + sums[(row + 1) * sums_width + column]
+                    - sums[row * sums_width + column];
+            }
+        }
+
+        let box_sums = |distance: usize| {
+            let mut counts = vec![0; grid_width * grid_height];
+            for row in 0..grid_height {
+                for column in 0..grid_width {
+                    let (top, left) = (
+                        row.saturating_sub(distance),
+                        column.saturating_sub(distance),
+                    );
+                    let bottom = (row + distance + 1).min(grid_height);
+                    let right = (column + distance + 1).min(grid_width);
+
+                    let count = sums[bottom * sums_width + right] + sums[top * sums_width + left]
+                        - sums[top * sums_width + right]
+                        - sums[bottom * sums_width + left];
+                    // at most 25 tiles are counted
+                    counts[row * grid_width + column] = count as u8;
+                }
+            }
+            counts
+        };
+
+        grid.near = box_sums(1);
+        grid.far = box_sums(2);
+        grid
+    }
+
+    /// Returns whether or not the tile is a wall.
+    fn is_wall(&self, row: usize, column: usize) -> bool {
+        let index = row * self.width + column;
+        self.walls[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// Returns the amount of walls within `distance` tiles of the tile, not
+    /// including the tile itself. `distance` must be either 1 or 2.
+    pub(super) fn walls_around(&self, row: usize, column: usize, distance: usize) -> usize {
+        let counts = match distance {
+            1 => &self.near,
+            2 => &self.far,
+            _ => unreachable!("only the walls within 1 or 2 tiles are counted"),
+        };
+
+        usize::from(counts[row * self.width + column]) - usize::from(self.is_wall(row, column))
+    }
+
+    /// Turns the tile into or out of a wall, updating the counts of every tile
+    /// around it. Must only be used for tiles that aren't on the edge of the
+    /// map, since those are always walls.
+    pub(super) fn set_wall(&mut self, row: usize, column: usize, is_wall: bool) {
+        if self.is_wall(row, column) == is_wall {
+            return;
+        }
+
+        let index = row * self.width + column;
+        self.walls[index / 64] ^= 1 << (index % 64);
+
+        for (counts, distance) in IntoIterator::into_iter([(&mut self.near, 1), (&mut self.far, 2)])
+        {
+            let rows = row.saturating_sub(distance)..(row + distance + 1).min(self.height);
+            for around_row in rows {
+                let columns =
+                    column.saturating_sub(distance)..(column + distance + 1).min(self.width);
+                for count in &mut counts[around_row * self.width..][columns] {
+                    if is_wall {
+                        *count += 1;
+                    } else {
+                        *count -= 1;
+                    }
+                }
+            }
+        }
+    }
+}
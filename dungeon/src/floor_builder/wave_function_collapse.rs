@@ -0,0 +1,399 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BinaryHeap},
+};
+
+use rand::Rng;
+
+use crate::WfcSample;
+
+/// The offsets (in columns and rows) to the neighbouring cell in each
+/// direction: left, down, right and up.
+const DIRECTIONS: [(isize, isize); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+/// Returns the index of the direction opposite to the one at the index.
+const fn opposite(direction: usize) -> usize {
+    (direction + 2) % 4
+}
+
+/// The overlapping model of wave function collapse, learned from a
+/// [`WfcSample`].
+///
+/// Every `n * n` pattern of walls in the sample is extracted (along with it's
+/// rotations and reflections, if enabled), and the output is built so that
+/// every `n * n` area of it is one of those patterns, with patterns appearing
+/// about as often as they do in the sample.
+///
+/// See <https://github.com/mxgmn/WaveFunctionCollapse>
+#[derive(Debug)]
+pub(in crate::floor_builder) struct Model {
+    /// The size of the patterns.
+    n: usize,
+    /// The patterns found in the sample, as row-major `n * n` grids of walls.
+    patterns: Vec<Vec<bool>>,
+    /// How many times each pattern was found in the sample.
+    weights: Vec<f64>,
+    /// For each direction and pattern, the patterns that can be placed next to
+    /// it in that direction.
+    propagator: [Vec<Vec<usize>>; 4],
+}
+
+impl Model {
+    /// Learns the patterns of size `n` from the sample.
+    pub(in crate::floor_builder) fn new(
+        sample: &WfcSample,
+        n: usize,
+        periodic: bool,
+        symmetry: bool,
+    ) -> Self {
+        let (width, height) = (sample.width(), sample.height());
+
+        let (max_column, max_row) = if periodic {
+            (width, height)
+        } else {
+            (width - n + 1, height - n + 1)
+        };
+
+        // ordered, so that the patterns are always in the same order
+        let mut counts = BTreeMap::<Vec<bool>, usize>::new();
+
+        for row in 0..max_row {
+            for column in 0..max_column {
+                let pattern = (0..n * n)
+                    .map(|i| sample.is_wall((column + i % n) % width, (row + i / n) % height))
+                    .collect::<Vec<_>>();
+
+                let variants = if symmetry {
+                    let rotated = rotate(&pattern, n);
+                    let rotated_twice = rotate(&rotated, n);
+                    let rotated_thrice = rotate(&rotated_twice, n);
+                    vec![
+                        reflect(&pattern, n),
+                        reflect(&rotated, n),
+                        reflect(&rotated_twice, n),
+                        reflect(&rotated_thrice, n),
+                        rotated,
+                        rotated_twice,
+                        rotated_thrice,
+                        pattern,
+                    ]
+                } else {
+                    vec![pattern]
+                };
+
+                for variant in variants {
+                    *counts.entry(variant).or_default() += 1;
+                }
+            }
+        }
+
+        let (patterns, weights): (Vec<_>, Vec<_>) = counts
+            .into_iter()
+            .map(|(pattern, count)| (pattern, count as f64))
+            .unzip();
+
+        let propagator = [0, 1, 2, 3].map(|direction| {
+            let (dx, dy) = DIRECTIONS[direction];
+            patterns
+                .iter()
+                .map(|first| {
+                    (0..patterns.len())
+                        .filter(|&second| agrees(first, &patterns[second], dx, dy, n))
+                        .collect()
+                })
+                .collect()
+        });
+
+        Self {
+            n,
+            patterns,
+            weights,
+            propagator,
+        }
+    }
+
+    /// Runs the model once, returning the walls of a `width * height` output,
+    /// or [`None`] if the model ran into a contradiction.
+    pub(in crate::floor_builder) fn run(
+        &self,
+        width: usize,
+        height: usize,
+        rng: &mut impl Rng,
+    ) -> Option<Vec<bool>> {
+        let mut wave = Wave::new(self, width - self.n + 1, height - self.n + 1, rng);
+
+        while let Some(cell) = wave.lowest_entropy_cell()? {
+            wave.observe(cell, rng);
+            wave.propagate()?;
+        }
+
+        Some(
+            (0..width * height)
+                .map(|index| {
+                    let (column, row) = (index % width, index / width);
+                    // the cells along the right and bottom edges also cover
+                    // the last `n - 1` columns and rows of the output
+                    let cell_column = column.min(wave.width - 1);
+                    let cell_row = row.min(wave.height - 1);
+                    let pattern = wave.collapsed(cell_row * wave.width + cell_column);
+
+                    self.patterns[pattern][(row - cell_row) * self.n + (column - cell_column)]
+                })
+                .collect(),
+        )
+    }
+}
+
+/// The state of a single run of the [`Model`]: which patterns are still
+/// possible in each cell of the output.
+#[derive(Debug)]
+struct Wave<'a> {
+    /// The model being run.
+    model: &'a Model,
+    /// How many cells wide the wave is.
+    width: usize,
+    /// How many cells tall the wave is.
+    height: usize,
+    /// Whether or not each pattern is still possible, for each cell.
+    possible: Vec<bool>,
+    /// For each cell, pattern and direction, how many patterns in the
+    /// neighbouring cell in that direction are still compatible with the
+    /// pattern. Once this reaches `0`, the pattern is no longer possible.
+    compatible: Vec<[u16; 4]>,
+    /// How many patterns are still possible in each cell.
+    counts: Vec<usize>,
+    /// The sum of the weights of the possible patterns in each cell.
+    sums_of_weights: Vec<f64>,
+    /// The sum of `weight * ln(weight)` of the possible patterns in each cell.
+    sums_of_weight_log_weights: Vec<f64>,
+    /// A small amount of noise for each cell, to randomly break ties between
+    /// cells with the same entropy.
+    noise: Vec<f64>,
+    /// The cells to observe next, lowest entropy first. May contain outdated
+    /// entries, which are skipped.
+    heap: BinaryHeap<Entropy>,
+    /// The patterns that have been banned, but not yet propagated.
+    stack: Vec<(usize, usize)>,
+}
+
+impl<'a> Wave<'a> {
+    /// Creates a wave where every pattern is possible in every cell.
+    fn new(model: &'a Model, width: usize, height: usize, rng: &mut impl Rng) -> Self {
+        let cells = width * height;
+        let pattern_count = model.patterns.len();
+
+        let initial_compatible = (0..pattern_count)
+            .map(|pattern| {
+                [0, 1, 2, 3]
+                    .map(|direction| model.propagator[opposite(direction)][pattern].len() as u16)
+            })
+            .collect::<Vec<_>>();
+
+        let sum_of_weights = model.weights.iter().sum::<f64>();
+        let sum_of_weight_log_weights = model.weights.iter().map(|w| w * w.ln()).sum::<f64>();
+
+        let mut wave = Self {
+            model,
+            width,
+            height,
+            possible: vec![true; cells * pattern_count],
+            compatible: initial_compatible.repeat(cells),
+            counts: vec![pattern_count; cells],
+            sums_of_weights: vec![sum_of_weights; cells],
+            sums_of_weight_log_weights: vec![sum_of_weight_log_weights; cells],
+            noise: (0..cells).map(|_| rng.gen::<f64>() * 1e-6).collect(),
+            heap: BinaryHeap::with_capacity(cells),
+            stack: vec![],
+        };
+
+        for cell in 0..cells {
+            wave.push_entropy(cell);
+        }
+
+        wave
+    }
+
+    /// Returns the entropy of the cell, plus it's noise.
+    fn entropy(&self, cell: usize) -> f64 {
+        let sum = self.sums_of_weights[cell];
+        sum.ln() - self.sums_of_weight_log_weights[cell] / sum + self.noise[cell]
+    }
+
+    /// Adds the current entropy of the cell to the heap.
+    fn push_entropy(&mut self, cell: usize) {
+        self.heap.push(Entropy {
+            entropy: self.entropy(cell),
+            cell,
+        });
+    }
+
+    /// Returns the cell with the lowest entropy that hasn't been collapsed yet,
+    /// `Some(None)` if every cell has been collapsed, or [`None`] if there is
+    /// a contradiction.
+    fn lowest_entropy_cell(&mut self) -> Option<Option<usize>> {
+        while let Some(Entropy { entropy, cell }) = self.heap.pop() {
+            match self.counts[cell] {
+                0 => return None,
+                1 => continue,
+                // skip entries that are out of date
+                _ if entropy != self.entropy(cell) => continue,
+                _ => return Some(Some(cell)),
+            }
+        }
+
+        Some(None)
+    }
+
+    /// Collapses the cell into one of it's possible patterns, picked randomly
+    /// according to their weights.
+    fn observe(&mut self, cell: usize, rng: &mut impl Rng) {
+        let pattern_count = self.model.patterns.len();
+        let possible = |pattern: &usize| self.possible[cell * pattern_count + pattern];
+
+        let mut remaining = rng.gen::<f64>() * self.sums_of_weights[cell];
+        let chosen = (0..pattern_count)
+            .filter(possible)
+            .find(|&pattern| {
+                remaining -= self.model.weights[pattern];
+                remaining <= 0.0
+            })
+            // floating point error can leave a tiny bit remaining at the end
+            .or_else(|| (0..pattern_count).rfind(possible))
+            .unwrap();
+
+        for pattern in 0..pattern_count {
+            if pattern != chosen && self.possible[cell * pattern_count + pattern] {
+                self.ban(cell, pattern);
+            }
+        }
+    }
+
+    /// Marks the pattern as no longer possible in the cell.
+    fn ban(&mut self, cell: usize, pattern: usize) {
+        let index = cell * self.model.patterns.len() + pattern;
+
+        self.possible[index] = false;
+        self.compatible[index] = [0; 4];
+        self.stack.push((cell, pattern));
+
+        let weight = self.model.weights[pattern];
+        self.counts[cell] -= 1;
+        self.sums_of_weights[cell] -= weight;
+        self.sums_of_weight_log_weights[cell] -= weight * weight.ln();
+
+        if self.counts[cell] > 0 {
+            self.push_entropy(cell);
+        }
+    }
+
+    /// Removes the patterns that are no longer compatible with their
+    /// neighbours, until nothing else changes. Returns [`None`] if a cell is
+    /// left with no possible patterns.
+    fn propagate(&mut self) -> Option<()> {
+        let pattern_count = self.model.patterns.len();
+
+        while let Some((cell, pattern)) = self.stack.pop() {
+            let (column, row) = (cell % self.width, cell / self.width);
+
+            for (direction, (dx, dy)) in DIRECTIONS.iter().enumerate() {
+                let neighbour_column = column as isize + dx;
+                let neighbour_row = row as isize + dy;
+                if neighbour_column < 0
+                    || neighbour_row < 0
+                    || neighbour_column >= self.width as isize
+                    || neighbour_row >= self.height as isize
+                {
+                    continue;
+                }
+                let neighbour = neighbour_row as usize * self.width + neighbour_column as usize;
+
+                for &other in &self.model.propagator[direction][pattern] {
+                    let index = neighbour * pattern_count + other;
+                    if !self.possible[index] {
+                        continue;
+                    }
+
+                    self.compatible[index][direction] -= 1;
+                    if self.compatible[index][direction] == 0 {
+                        self.ban(neighbour, other);
+
+                        if self.counts[neighbour] == 0 {
+                            return None;
+                        }
+                    }
+                }
+            }
+        }
+
+        Some(())
+    }
+
+    /// Returns the pattern the cell collapsed into.
+    fn collapsed(&self, cell: usize) -> usize {
+        let pattern_count = self.model.patterns.len();
+        (0..pattern_count)
+            .find(|pattern| self.possible[cell * pattern_count + pattern])
+            .unwrap()
+    }
+}
+
+/// An entry in the heap of cells to observe, ordered so that the lowest
+/// entropy is at the top of the heap.
+#[derive(Debug, Clone, Copy)]
+struct Entropy {
+    /// The entropy of the cell at the time the entry was added.
+    entropy: f64,
+    /// The cell.
+    cell: usize,
+}
+
+impl PartialEq for Entropy {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entropy {}
+
+impl PartialOrd for Entropy {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entropy {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .entropy
+            .total_cmp(&self.entropy)
+            .then_with(|| other.cell.cmp(&self.cell))
+    }
+}
+
+/// Returns `true` if the second pattern can be placed `(dx, dy)` away from the
+/// first, i.e. the area where they overlap is the same in both.
+fn agrees(first: &[bool], second: &[bool], dx: isize, dy: isize, n: usize) -> bool {
+    let n = n as isize;
+
+    let (x_min, x_max) = if dx < 0 { (0, dx + n) } else { (dx, n) };
+    let (y_min, y_max) = if dy < 0 { (0, dy + n) } else { (dy, n) };
+
+    (y_min..y_max).all(|y| {
+        (x_min..x_max)
+            .all(|x| first[(x + n * y) as usize] == second[(x - dx + n * (y - dy)) as usize])
+    })
+}
+
+/// Rotates the `n * n` pattern 90 degrees.
+fn rotate(pattern: &[bool], n: usize) -> Vec<bool> {
+    (0..n * n)
+        .map(|i| pattern[n - 1 - i / n + (i % n) * n])
+        .collect()
+}
+
+/// Reflects the `n * n` pattern horizontally.
+fn reflect(pattern: &[bool], n: usize) -> Vec<bool> {
+    (0..n * n)
+        .map(|i| pattern[n - 1 - i % n + (i / n) * n])
+        .collect()
+}
@@ -0,0 +1,795 @@
+use std::{convert::TryFrom, error::Error, fmt};
+
+use bounded_int::BoundedInt;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    floor_builder::{to_block_character::ToAsciiCharacter, MAX_FLOOR_SIZE, MIN_FLOOR_SIZE},
+    ChestId, Column, DungeonTile, DungeonType, Floor, FloorBuilder, FloorId, GenerationConfig,
+    GenerationError, Point, Row, Seed,
+};
+
+/// An algorithm that generates a [`Floor`].
+///
+/// Every generator must produce a floor with exactly one
+/// [`DungeonTile::Entrance`] and one [`DungeonTile::Exit`], where every tile
+/// that isn't a [`DungeonTile::Wall`] can be reached from the entrance without
+/// walking over any [solid](crate::DungeonTile::is_solid) tiles, and where the
+/// [key](crate::DungeonTile::Key) to every locked door can be reached without
+/// opening the door. If the request has an [entrance](FloorRequest::entrance),
+/// the floor's entrance must be placed there. These invariants are checked by
+/// [`Floor::try_with_generator`], so custom generators can be plugged in
+/// without being trusted.
+///
+/// The built-in generators are [`CellularAutomataGenerator`],
+/// [`BspGenerator`], [`DrunkardsWalkGenerator`], [`MazeGenerator`] and
+/// [`WfcGenerator`]. Which one is used for each floor of a
+/// [`Dungeon`](crate::Dungeon) is selected with
+/// [`GenerationConfig::algorithms`].
+///
+/// [`DungeonTile::Entrance`]: crate::DungeonTile::Entrance
+/// [`DungeonTile::Exit`]: crate::DungeonTile::Exit
+/// [`DungeonTile::Wall`]: crate::DungeonTile::Wall
+///
+/// # Examples
+/// ```rust
+/// use dungeon::{
+///     CellularAutomataGenerator, DungeonType, Floor, FloorGenerator, FloorId, FloorRequest,
+///     GenerationConfig, GenerationError, MazeGenerator, Seed,
+/// };
+/// use std::convert::TryInto;
+///
+/// /// Mazes on the even floors, caves on the odd ones.
+/// struct Alternating;
+///
+/// impl FloorGenerator for Alternating {
+///     fn generate(&self, request: &FloorRequest<'_>) -> Result<Floor, GenerationError> {
+///         if request.id.get() % 2 == 0 {
+///             MazeGenerator::default().generate(request)
+///         } else {
+///             CellularAutomataGenerator.generate(request)
+///         }
+///     }
+/// }
+///
+/// let config = GenerationConfig::default();
+/// let floor = Floor::try_with_generator(
+///     &Alternating,
+///     &FloorRequest {
+///         id: FloorId::new(0),
+///         dungeon_type: DungeonType::Cave,
+///         width: 40.try_into().unwrap(),
+///         height: 30.try_into().unwrap(),
+///         config: &config,
+///         seed: Seed::new(0),
+///         entrance: None,
+///         gif_output: false,
+///     },
+/// );
+///
+/// assert!(floor.is_ok());
+/// ```
+pub trait FloorGenerator {
+    /// Generates a floor for the provided request.
+    ///
+    /// # Errors
+    /// Errors if the floor can't be generated. See [`GenerationError`].
+    fn generate(&self, request: &FloorRequest<'_>) -> Result<Floor, GenerationError>;
+}
+
+/// Everything a [`FloorGenerator`] needs to know to generate a [`Floor`].
+#[derive(Debug, Clone, Copy)]
+pub struct FloorRequest<'a> {
+    /// The id of the floor.
+    pub id: FloorId,
+    /// The type of the dungeon the floor is in.
+    pub dungeon_type: DungeonType,
+    /// The width of the floor.
+    pub width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+    /// The height of the floor.
+    pub height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+    /// The parameters to generate the floor with.
+    pub config: &'a GenerationConfig,
+    /// The seed to generate the floor from.
+    pub seed: Seed,
+    /// Where the entrance must be placed, if anywhere. Used to line the
+    /// entrance up with the exit of the previous floor; see
+    /// [`GenerationConfig::link_floors`]. Must not be on the edge of the floor.
+    pub entrance: Option<Point>,
+    /// Whether or not the generation should be written out to a gif. Generators
+    /// are free to ignore this.
+    pub gif_output: bool,
+}
+
+impl FloorRequest<'_> {
+    /// Creates the rng for the requested floor. Generators should make all of
+    /// their random decisions with this, so that the floor is reproducible
+    /// from it's [`Seed`].
+    #[must_use]
+    pub fn rng(&self) -> ChaCha8Rng {
+        self.seed.floor_rng(self.id)
+    }
+}
+
+/// The built-in generation algorithms, selectable per floor with
+/// [`GenerationConfig::algorithms`].
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum FloorAlgorithm {
+    /// See [`CellularAutomataGenerator`].
+    #[default]
+    CellularAutomata,
+    /// See [`BspGenerator`].
+    Bsp(BspGenerator),
+    /// See [`DrunkardsWalkGenerator`].
+    DrunkardsWalk(DrunkardsWalkGenerator),
+    /// See [`MazeGenerator`].
+    Maze(MazeGenerator),
+    /// See [`WfcGenerator`].
+    Wfc(WfcGenerator),
+}
+
+impl FloorGenerator for FloorAlgorithm {
+    fn generate(&self, request: &FloorRequest<'_>) -> Result<Floor, GenerationError> {
+        match self {
+            FloorAlgorithm::CellularAutomata => CellularAutomataGenerator.generate(request),
+            FloorAlgorithm::Bsp(generator) => generator.generate(request),
+            FloorAlgorithm::DrunkardsWalk(generator) => generator.generate(request),
+            FloorAlgorithm::Maze(generator) => generator.generate(request),
+            FloorAlgorithm::Wfc(generator) => generator.generate(request),
+        }
+    }
+}
+
+/// The original generator: caves formed by cellular automata, joined by
+/// tunnels and secret passages.
+///
+/// This is the only generator with a different recipe per [`DungeonType`];
+/// see [`DungeonType::Cave`] and [`DungeonType::Forest`].
+///
+/// See <http://roguebasin.roguelikedevelopment.org/index.php?title=Cellular_Automata_Method_for_Generating_Random_Cave-Like_Levels>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CellularAutomataGenerator;
+
+impl FloorGenerator for CellularAutomataGenerator {
+    fn generate(&self, request: &FloorRequest<'_>) -> Result<Floor, GenerationError> {
+        FloorBuilder::create(request)
+    }
+}
+
+/// Binary space partitioning: the floor is recursively split into smaller
+/// and smaller areas, a rectangular room is placed in each of the smallest
+/// areas, and sibling areas are joined by corridors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BspGenerator {
+    /// Areas smaller than this (in either dimension) won't be split any
+    /// further.
+    pub min_leaf_size: u8,
+    /// The smallest a room can be in either dimension. Must be at least 2
+    /// smaller than [`BspGenerator::min_leaf_size`], so that every room fits
+    /// in it's area with a wall around it.
+    pub min_room_size: u8,
+}
+
+impl Default for BspGenerator {
+    fn default() -> Self {
+        Self {
+            min_leaf_size: 10,
+            min_room_size: 4,
+        }
+    }
+}
+
+impl FloorGenerator for BspGenerator {
+    fn generate(&self, request: &FloorRequest<'_>) -> Result<Floor, GenerationError> {
+        FloorBuilder::create_bsp(request, self)
+    }
+}
+
+/// A random walk: a 'drunkard' stumbles around the floor, carving out
+/// everything they walk over, until enough of the floor has been carved.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DrunkardsWalkGenerator {
+    /// The percent (`1..=100`) of the floor to carve out.
+    pub floor_percent: u8,
+    /// How many steps the drunkard takes before starting again from a random
+    /// point they've already carved. Shorter walks create blobbier floors,
+    /// longer walks create longer, windier tunnels.
+    pub walk_length: u16,
+}
+
+impl Default for DrunkardsWalkGenerator {
+    fn default() -> Self {
+        Self {
+            floor_percent: 40,
+            walk_length: 200,
+        }
+    }
+}
+
+impl FloorGenerator for DrunkardsWalkGenerator {
+    fn generate(&self, request: &FloorRequest<'_>) -> Result<Floor, GenerationError> {
+        FloorBuilder::create_drunkards_walk(request, self)
+    }
+}
+
+/// A perfect maze carved with a recursive backtracker, optionally with some
+/// of the dead ends knocked through to create loops.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MazeGenerator {
+    /// The percent chance (`0..=100`) of each dead end being opened up into a
+    /// neighbouring corridor. `0` creates a perfect maze, with exactly one
+    /// path between any two points.
+    pub braid_percent_chance: u8,
+}
+
+impl Default for MazeGenerator {
+    fn default() -> Self {
+        Self {
+            braid_percent_chance: 10,
+        }
+    }
+}
+
+impl FloorGenerator for MazeGenerator {
+    fn generate(&self, request: &FloorRequest<'_>) -> Result<Floor, GenerationError> {
+        FloorBuilder::create_maze(request, self)
+    }
+}
+
+/// Wave function collapse, using the overlapping model: every small pattern
+/// of walls in a hand-made [`WfcSample`] is learned, and a new floor is built
+/// out of those patterns so that it looks like the sample, at any size.
+///
+/// Only the walls of the sample are learned; the entrance, the exit, the
+/// treasure and any secret passages are placed afterwards. Areas of the
+/// generated floor that end up cut off from each other are joined with
+/// tunnels and secret passages, the same way the caves are.
+///
+/// See <https://github.com/mxgmn/WaveFunctionCollapse>
+///
+/// # Examples
+/// ```rust
+/// use dungeon::{FloorAlgorithm, GenerationConfig, WfcGenerator, WfcSample};
+///
+/// let sample = WfcSample::from_ascii(
+///     "██████████████\n\
+///      ██    ██    ██\n\
+///      ██          ██\n\
+///      ██    ██    ██\n\
+///      ██████████████",
+/// )
+/// .unwrap();
+///
+/// let config = GenerationConfig {
+///     algorithms: vec![FloorAlgorithm::Wfc(WfcGenerator {
+///         sample,
+///         ..WfcGenerator::default()
+///     })],
+///     ..GenerationConfig::default()
+/// };
+///
+/// assert!(config.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WfcGenerator {
+    /// The sample to learn the patterns from.
+    pub sample: WfcSample,
+    /// The width and height (`2..=4`) of the patterns learned from the sample.
+    /// Larger patterns copy bigger features of the sample, but need a larger
+    /// sample to avoid copying it outright. Must fit within the sample.
+    pub pattern_size: u8,
+    /// Whether or not the sample wraps around at it's edges, so that patterns
+    /// are also learned across them.
+    pub periodic_sample: bool,
+    /// Whether or not the rotations and reflections of each pattern are
+    /// learned as well.
+    pub symmetry: bool,
+    /// How many times to start over after running into a contradiction (an
+    /// area where none of the patterns fit) before giving up. `0` is treated
+    /// as `1`.
+    pub max_attempts: u8,
+}
+
+impl Default for WfcGenerator {
+    fn default() -> Self {
+        Self {
+            sample: WfcSample::default(),
+            pattern_size: 3,
+            periodic_sample: false,
+            symmetry: true,
+            max_attempts: 10,
+        }
+    }
+}
+
+impl FloorGenerator for WfcGenerator {
+    fn generate(&self, request: &FloorRequest<'_>) -> Result<Floor, GenerationError> {
+        FloorBuilder::create_wfc(request, self)
+    }
+}
+
+/// The rows of the default [`WfcSample`]: rooms of a few sizes, joined by
+/// doorways, with some pillars.
+const DEFAULT_SAMPLE: [&str; 12] = [
+    "████████████████████████████████",
+    "██            ██              ██",
+    "██            ██              ██",
+    "██    ████          ██████    ██",
+    "██    ████          ██████    ██",
+    "██            ██              ██",
+    "██████    ████████████  ████████",
+    "██            ██              ██",
+    "██    ██                ██    ██",
+    "██            ██              ██",
+    "██            ██      ██      ██",
+    "████████████████████████████████",
+];
+
+/// A small, hand-made floor for the [`WfcGenerator`] to learn from. Only the
+/// walls of the sample matter; every other tile is treated as open floor.
+///
+/// Samples are written as ascii grids, using the same 2-character glyphs a
+/// [`Floor`] is printed with (`"██"` for walls, `"  "` for empty tiles, and
+/// `"EN"`, `"EX"`, `"TC"`, `"SD"` and `"<>"` for the other tiles). This is
+/// also how samples are serialized.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct WfcSample {
+    /// How many tiles wide the sample is.
+    width: usize,
+    /// Whether or not each tile of the sample is a wall, row by row.
+    walls: Vec<bool>,
+}
+
+impl WfcSample {
+    /// Parses a sample from an ascii grid. Every row must have the same
+    /// amount of tiles; leading and trailing empty lines are ignored.
+    ///
+    /// # Errors
+    /// Errors if the grid is empty, the rows aren't all the same length, or
+    /// a glyph isn't one of the [`DungeonTile`] glyphs.
+    pub fn from_ascii(ascii: &str) -> Result<Self, WfcSampleError> {
+        let lines = ascii.lines().collect::<Vec<_>>();
+        let first = lines.iter().position(|line| !line.is_empty());
+        let last = lines.iter().rposition(|line| !line.is_empty());
+        let lines = match (first, last) {
+            (Some(first), Some(last)) => &lines[first..=last],
+            _ => return Err(WfcSampleError::Empty),
+        };
+
+        let mut width = None;
+        let mut walls = vec![];
+
+        for (row, line) in lines.iter().enumerate() {
+            let chars = line.chars().collect::<Vec<_>>();
+
+            for (column, glyph) in chars.chunks(2).enumerate() {
+                walls.push(
+                    parse_glyph(glyph).ok_or_else(|| WfcSampleError::InvalidGlyph {
+                        row,
+                        column,
+                        glyph: glyph.iter().collect(),
+                    })?,
+                );
+            }
+
+            let found = chars.len().div_ceil(2);
+            match width {
+                Some(expected) if expected != found => {
+                    return Err(WfcSampleError::RaggedRows {
+                        row,
+                        expected,
+                        found,
+                    })
+                }
+                _ => width = Some(found),
+            }
+        }
+
+        match width {
+            Some(width) if width > 0 => Ok(Self { width, walls }),
+            _ => Err(WfcSampleError::Empty),
+        }
+    }
+
+    /// Uses an existing floor as the sample.
+    #[must_use]
+    pub fn from_floor(floor: &Floor) -> Self {
+        Self {
+            width: floor.width.as_unbounded() as usize,
+            walls: floor
+                .data
+                .iter()
+                .copied()
+                .map(DungeonTile::is_wall)
+                .collect(),
+        }
+    }
+
+    /// How many tiles wide the sample is.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// How many tiles tall the sample is.
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.walls.len() / self.width
+    }
+
+    /// Returns `true` if the tile at the column and row is a wall.
+    pub(crate) fn is_wall(&self, column: usize, row: usize) -> bool {
+        self.walls[row * self.width + column]
+    }
+}
+
+impl Default for WfcSample {
+    fn default() -> Self {
+        Self::from_ascii(&DEFAULT_SAMPLE.join("\n")).unwrap()
+    }
+}
+
+impl fmt::Display for WfcSample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = self
+            .walls
+            .chunks(self.width)
+            .map(|row| {
+                row.iter()
+                    .map(|&wall| {
+                        if wall {
+                            DungeonTile::Wall
+                        } else {
+                            DungeonTile::Empty
+                        }
+                    })
+                    .flat_map(|tile| tile.to_ascii_chars())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+
+        f.write_str(&rows.join("\n"))
+    }
+}
+
+impl TryFrom<String> for WfcSample {
+    type Error = WfcSampleError;
+
+    fn try_from(ascii: String) -> Result<Self, Self::Error> {
+        Self::from_ascii(&ascii)
+    }
+}
+
+impl From<WfcSample> for String {
+    fn from(sample: WfcSample) -> Self {
+        sample.to_string()
+    }
+}
+
+/// Returns whether or not the glyph is a wall, or [`None`] if it isn't the
+/// glyph of any [`DungeonTile`]. A trailing single space is accepted as an
+/// empty tile, since editors tend to trim trailing whitespace.
+fn parse_glyph(glyph: &[char]) -> Option<bool> {
+    let tiles = [
+        DungeonTile::Empty,
+        DungeonTile::Wall,
+        DungeonTile::SecretDoor {
+            requires_key: false,
+            is_open: false,
+        },
+        DungeonTile::SecretPassage,
+        DungeonTile::TreasureChest {
+            contents: ChestId::new(0),
+        },
+        DungeonTile::Entrance,
+        DungeonTile::Exit,
+        DungeonTile::Key {
+            door: Point {
+                column: Column::new(BoundedInt::new_clamped(0)),
+                row: Row::new(BoundedInt::new_clamped(0)),
+            },
+        },
+    ];
+
+    if glyph == [' '] {
+        return Some(false);
+    }
+
+    tiles
+        .iter()
+        .find(|tile| tile.to_ascii_chars() == glyph)
+        .map(|tile| tile.is_wall())
+}
+
+/// Error returned when parsing a [`WfcSample`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WfcSampleError {
+    /// The sample had no tiles.
+    Empty,
+    /// A row of the sample had a different amount of tiles than the first.
+    RaggedRows {
+        /// The row, counting from the first non-empty line.
+        row: usize,
+        /// The amount of tiles in the first row.
+        expected: usize,
+        /// The amount of tiles in the row.
+        found: usize,
+    },
+    /// A glyph wasn't the glyph of any [`DungeonTile`].
+    InvalidGlyph {
+        /// The row, counting from the first non-empty line.
+        row: usize,
+        /// The column, in tiles.
+        column: usize,
+        /// The invalid glyph.
+        glyph: String,
+    },
+}
+
+impl fmt::Display for WfcSampleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("wfc sample has no tiles"),
+            Self::RaggedRows {
+                row,
+                expected,
+                found,
+            } => f.write_fmt(format_args!(
+                "wfc sample row {} has {} tiles, expected {}",
+                row, found, expected
+            )),
+            Self::InvalidGlyph { row, column, glyph } => f.write_fmt(format_args!(
+                "invalid glyph {:?} in wfc sample at row {}, column {}",
+                glyph, row, column
+            )),
+        }
+    }
+}
+
+impl Error for WfcSampleError {}
+
+/// Checks that the floor is [valid](Floor::validate), returning the first
+/// violation found as an error.
+pub(crate) fn check_invariants(floor: &Floor) -> Result<(), GenerationError> {
+    floor.validate().map_err(|violations| {
+        violations
+            .into_iter()
+            .next()
+            .expect("invalid floors have at least one violation")
+            .into()
+    })
+}
+
+#[cfg(test)]
+mod test_floor_generator {
+    use std::{convert::TryInto, num::NonZeroU16};
+
+    use super::*;
+    use crate::{test_utils::point, Dungeon};
+
+    #[test]
+    fn test_every_algorithm_upholds_the_floor_invariants() {
+        let algorithms = vec![
+            FloorAlgorithm::CellularAutomata,
+            FloorAlgorithm::Bsp(BspGenerator::default()),
+            FloorAlgorithm::DrunkardsWalk(DrunkardsWalkGenerator::default()),
+            FloorAlgorithm::Maze(MazeGenerator::default()),
+            FloorAlgorithm::Wfc(WfcGenerator::default()),
+        ];
+        let config = GenerationConfig {
+            algorithms: algorithms.clone(),
+            ..GenerationConfig::default()
+        };
+
+        for seed in 0..4 {
+            for (width, height) in [(10, 10), (37, 21), (80, 60)] {
+                // `try_new` checks the invariants of every floor it generates
+                let dungeon = Dungeon::try_new(
+                    height.try_into().unwrap(),
+                    width.try_into().unwrap(),
+                    NonZeroU16::new(algorithms.len() as u16).unwrap(),
+                    DungeonType::Cave,
+                    &config,
+                    Seed::new(seed),
+                    false,
+                )
+                .unwrap_or_else(|err| panic!("seed {}, {}x{}: {}", seed, width, height, err));
+
+                // every floor uses it's own algorithm, so they all look different
+                for (id, floor) in dungeon.floors.iter().enumerate() {
+                    let request = FloorRequest {
+                        id: FloorId::new(id as u16),
+                        dungeon_type: DungeonType::Cave,
+                        width: width.try_into().unwrap(),
+                        height: height.try_into().unwrap(),
+                        config: &config,
+                        seed: Seed::new(seed),
+                        entrance: None,
+                        gif_output: false,
+                    };
+                    assert_eq!(&algorithms[id].generate(&request).unwrap(), floor);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_generators_are_checked() {
+        /// A floor with no exit.
+        struct NoExit;
+
+        impl FloorGenerator for NoExit {
+            fn generate(&self, request: &FloorRequest<'_>) -> Result<Floor, GenerationError> {
+                let mut data = vec![
+                    DungeonTile::Empty;
+                    (request.width.as_unbounded() * request.height.as_unbounded())
+                        as usize
+                ];
+                data[0] = DungeonTile::Entrance;
+
+                Ok(Floor {
+                    width: request.width,
+                    height: request.height,
+                    data,
+                    chests: vec![],
+                    spawn_points: vec![],
+                    entities: Default::default(),
+                    vaults: vec![],
+                })
+            }
+        }
+
+        let config = GenerationConfig::default();
+        let result = Floor::try_with_generator(
+            &NoExit,
+            &FloorRequest {
+                id: FloorId::new(0),
+                dungeon_type: DungeonType::Cave,
+                width: 20.try_into().unwrap(),
+                height: 20.try_into().unwrap(),
+                config: &config,
+                seed: Seed::new(0),
+                entrance: None,
+                gif_output: false,
+            },
+        );
+
+        assert!(matches!(
+            result,
+            Err(GenerationError::InvalidEntrancesAndExits {
+                entrances: 1,
+                exits: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn test_wfc_samples_round_trip_through_ascii() {
+        let ascii = [
+            "████████████████",
+            "██    ██EN    ██",
+            "██  TC    ██  ██",
+            "████████████████",
+        ]
+        .join("\n");
+
+        let sample = WfcSample::from_ascii(&ascii).unwrap();
+        assert_eq!((sample.width(), sample.height()), (8, 4));
+
+        // only the walls of the sample are kept
+        assert_eq!(
+            sample.to_string(),
+            ascii.replace("EN", "  ").replace("TC", "  ")
+        );
+
+        let json = serde_json::to_string(&sample).unwrap();
+        assert_eq!(serde_json::from_str::<WfcSample>(&json).unwrap(), sample);
+
+        assert_eq!(WfcSample::from_ascii("\n\n"), Err(WfcSampleError::Empty));
+        assert_eq!(
+            WfcSample::from_ascii("████\n██"),
+            Err(WfcSampleError::RaggedRows {
+                row: 1,
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(
+            WfcSample::from_ascii("██??"),
+            Err(WfcSampleError::InvalidGlyph {
+                row: 0,
+                column: 1,
+                glyph: "??".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_wfc_learns_from_existing_floors() {
+        let config = GenerationConfig::default();
+        let request = |width: i32, height: i32| FloorRequest {
+            id: FloorId::new(0),
+            dungeon_type: DungeonType::Cave,
+            width: width.try_into().unwrap(),
+            height: height.try_into().unwrap(),
+            config: &config,
+            seed: Seed::new(7),
+            entrance: None,
+            gif_output: false,
+        };
+
+        let maze = Floor::try_with_generator(&MazeGenerator::default(), &request(15, 15)).unwrap();
+        let generator = WfcGenerator {
+            sample: WfcSample::from_floor(&maze),
+            ..WfcGenerator::default()
+        };
+
+        for (width, height) in [(10, 10), (60, 20)] {
+            let floor = Floor::try_with_generator(&generator, &request(width, height)).unwrap();
+            assert_eq!(floor, generator.generate(&request(width, height)).unwrap());
+        }
+
+        let too_large = GenerationConfig {
+            algorithms: vec![FloorAlgorithm::Wfc(WfcGenerator {
+                sample: WfcSample::from_ascii("██  \n  ██").unwrap(),
+                ..WfcGenerator::default()
+            })],
+            ..GenerationConfig::default()
+        };
+        assert!(too_large.validate().is_err());
+    }
+
+    #[test]
+    fn test_requested_entrances_are_checked() {
+        let config = GenerationConfig::default();
+        let request = |entrance: Point| FloorRequest {
+            id: FloorId::new(0),
+            dungeon_type: DungeonType::Cave,
+            width: 20.try_into().unwrap(),
+            height: 20.try_into().unwrap(),
+            config: &config,
+            seed: Seed::new(0),
+            entrance: Some(entrance),
+            gif_output: false,
+        };
+
+        assert!(matches!(
+            Floor::try_with_generator(&MazeGenerator::default(), &request(point(0, 5))),
+            Err(GenerationError::InvalidEntrance(_))
+        ));
+        assert!(matches!(
+            Floor::try_with_generator(&MazeGenerator::default(), &request(point(5, 19))),
+            Err(GenerationError::InvalidEntrance(_))
+        ));
+
+        /// Always places the entrance at the same point, ignoring the request.
+        struct Stubborn;
+
+        impl FloorGenerator for Stubborn {
+            fn generate(&self, request: &FloorRequest<'_>) -> Result<Floor, GenerationError> {
+                MazeGenerator::default().generate(&FloorRequest {
+                    entrance: Some(point(5, 5)),
+                    ..*request
+                })
+            }
+        }
+
+        assert!(matches!(
+            Floor::try_with_generator(&Stubborn, &request(point(2, 2))),
+            Err(GenerationError::EntranceMismatch { requested, found })
+                if requested == point(2, 2) && found == point(5, 5)
+        ));
+        assert_eq!(
+            Floor::try_with_generator(&MazeGenerator::default(), &request(point(2, 2)))
+                .unwrap()
+                .entrance(),
+            Some(point(2, 2))
+        );
+    }
+}
@@ -0,0 +1,521 @@
+use std::{convert::TryFrom, error::Error, fmt, path::Path};
+
+use bounded_int::BoundedInt;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::{MAX_FLOOR_SIZE, MIN_FLOOR_SIZE};
+
+/// The shape of a [`Floor`](crate::Floor), for floors that aren't simply
+/// rectangles: round towers, rings around a central pit, L-shaped halls, or
+/// any shape at all drawn as a [`MaskBitmap`]. See
+/// [`GenerationConfig::mask`](crate::GenerationConfig::mask).
+///
+/// Every tile outside of the shape is masked out: it is a wall before the
+/// floor is filled in, and stays a wall no matter what. Smoothing treats
+/// masked tiles the same as the edges of the floor, the tunnels and secret
+/// passages joining the caves never go through them, and the entrance and
+/// the exit are never placed on them.
+///
+/// The shapes are stretched to fit the inside of the floor (everything but
+/// the 1 tile border around it), so a circle on a floor that isn't square is
+/// an ellipse.
+///
+/// # Examples
+/// ```rust
+/// use dungeon::{DungeonType, Floor, FloorId, FloorMask, GenerationConfig, Seed};
+/// use std::convert::TryInto;
+///
+/// let config = GenerationConfig {
+///     mask: FloorMask::Ring { inner_radius: 0.4 },
+///     ..GenerationConfig::default()
+/// };
+/// let (width, height) = (50.try_into().unwrap(), 50.try_into().unwrap());
+///
+/// let floor = Floor::try_new(
+///     FloorId::new(0),
+///     DungeonType::Cave,
+///     width,
+///     height,
+///     &config,
+///     Seed::new(0),
+///     false,
+/// )
+/// .unwrap();
+///
+/// // nothing but walls outside of the ring
+/// let masked = config.mask.solid_tiles(width, height);
+/// assert!(floor
+///     .data
+///     .iter()
+///     .zip(masked)
+///     .all(|(tile, masked)| !masked || tile.is_wall()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum FloorMask {
+    /// Nothing is masked out; the floor fills the whole rectangle.
+    #[default]
+    Rectangle,
+    /// Only the circle (or ellipse) touching the edges of the floor is kept.
+    Circle,
+    /// Only the ring between the circle touching the edges of the floor and
+    /// a smaller circle in the middle of it is kept.
+    Ring {
+        /// The radius of the inner circle, as a fraction of the radius of the
+        /// outer circle. Must be at least `0` and less than `1`.
+        inner_radius: f64,
+    },
+    /// Only an L shape is kept, running down the left edge of the floor and
+    /// along the bottom edge; the top right corner is masked out.
+    LShape {
+        /// How much of the floor's width the vertical arm of the L takes up,
+        /// and how much of it's height the horizontal arm takes up. Must be
+        /// greater than `0` and at most `1` (which keeps the whole floor).
+        thickness: f64,
+    },
+    /// Only the open pixels of the bitmap are kept. The bitmap is scaled to
+    /// the size of the floor, so it doesn't have to be the same size.
+    Bitmap(MaskBitmap),
+}
+
+impl FloorMask {
+    /// Returns whether or not each tile of a floor of the provided size is
+    /// masked out, row by row. The edges of the floor are only included if
+    /// the shape itself masks them out.
+    #[must_use]
+    pub fn solid_tiles(
+        &self,
+        width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+    ) -> Vec<bool> {
+        let width = width.as_unbounded() as usize;
+        let height = height.as_unbounded() as usize;
+
+        // where the centre of the tile sits within the inside of the floor,
+        // from `0` at the top (or left) edge to `1` at the bottom (or right)
+        let position =
+            |tile: usize, tiles: usize| ((tile as f64 - 0.5) / (tiles - 2) as f64).clamp(0.0, 1.0);
+
+        (0..height)
+            .flat_map(|row| (0..width).map(move |column| (row, column)))
+            .map(|(row, column)| {
+                let (y, x) = (position(row, height), position(column, width));
+                // the squared distance from the middle of the floor, `1` at
+                // the edges of the circle touching the edges of the floor
+                let distance = (x * 2.0 - 1.0).powi(2) + (y * 2.0 - 1.0).powi(2);
+
+                match self {
+                    FloorMask::Rectangle => false,
+                    FloorMask::Circle => distance > 1.0,
+                    FloorMask::Ring { inner_radius } => {
+                        distance > 1.0 || distance < inner_radius.powi(2)
+                    }
+                    FloorMask::LShape { thickness } => x >= *thickness && y < 1.0 - thickness,
+                    FloorMask::Bitmap(bitmap) => bitmap.is_solid(
+                        ((y * bitmap.height() as f64) as usize).min(bitmap.height() - 1),
+                        ((x * bitmap.width as f64) as usize).min(bitmap.width - 1),
+                    ),
+                }
+            })
+            .collect()
+    }
+}
+
+/// A hand-drawn [`FloorMask`], where each pixel is either solid (masked out)
+/// or open.
+///
+/// Bitmaps are written as ascii grids, with a `#` for every solid pixel and a
+/// `.` for every open one, which is also how they are serialized. They can be
+/// loaded from images too; see [`MaskBitmap::from_image`].
+///
+/// # Examples
+/// ```rust
+/// use dungeon::MaskBitmap;
+///
+/// // a diamond shaped arena
+/// let bitmap = MaskBitmap::from_ascii("##..##\n#....#\n......\n#....#\n##..##").unwrap();
+///
+/// assert_eq!((bitmap.width(), bitmap.height()), (6, 5));
+/// assert!(bitmap.is_solid(0, 0));
+/// assert!(!bitmap.is_solid(2, 0));
+/// assert_eq!(MaskBitmap::from_ascii(&bitmap.to_string()).unwrap(), bitmap);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MaskBitmap {
+    /// How many pixels wide the bitmap is.
+    width: usize,
+    /// Whether or not each pixel of the bitmap is solid, row by row.
+    solid: Vec<bool>,
+}
+
+impl MaskBitmap {
+    /// Parses a bitmap from an ascii grid of `#`s (solid) and `.`s (open).
+    /// Every row must have the same amount of pixels; leading and trailing
+    /// empty lines are ignored.
+    ///
+    /// # Errors
+    /// Errors if the grid is empty, the rows aren't all the same length, or
+    /// a character isn't a `#` or a `.`.
+    pub fn from_ascii(ascii: &str) -> Result<Self, MaskBitmapError> {
+        let lines = ascii.lines().collect::<Vec<_>>();
+        let first = lines.iter().position(|line| !line.is_empty());
+        let last = lines.iter().rposition(|line| !line.is_empty());
+        let lines = match (first, last) {
+            (Some(first), Some(last)) => &lines[first..=last],
+            _ => return Err(MaskBitmapError::Empty),
+        };
+
+        let width = lines[0].chars().count();
+        let mut solid = vec![];
+
+        for (row, line) in lines.iter().enumerate() {
+            let found = line.chars().count();
+            if found != width {
+                return Err(MaskBitmapError::RaggedRows {
+                    row,
+                    expected: width,
+                    found,
+                });
+            }
+
+            for (column, character) in line.chars().enumerate() {
+                solid.push(match character {
+                    '#' => true,
+                    '.' => false,
+                    _ => {
+                        return Err(MaskBitmapError::InvalidCharacter {
+                            row,
+                            column,
+                            character,
+                        })
+                    }
+                });
+            }
+        }
+
+        Ok(Self { width, solid })
+    }
+
+    /// Creates a bitmap from an image, with a pixel for every pixel of the
+    /// image. Dark pixels (less than half brightness) and transparent pixels
+    /// (less than half opaque) are solid; every other pixel is open.
+    ///
+    /// # Errors
+    /// Errors if the image has no pixels.
+    pub fn from_image(image: &DynamicImage) -> Result<Self, MaskBitmapError> {
+        let image = image.to_luma_alpha8();
+        if image.width() == 0 || image.height() == 0 {
+            return Err(MaskBitmapError::Empty);
+        }
+
+        Ok(Self {
+            width: image.width() as usize,
+            solid: image
+                .pixels()
+                .map(|pixel| {
+                    let [luma, alpha] = pixel.0;
+                    luma < 128 || alpha < 128
+                })
+                .collect(),
+        })
+    }
+
+    /// Loads a bitmap from an image file (such as a PNG); see
+    /// [`MaskBitmap::from_image`].
+    ///
+    /// # Errors
+    /// Errors if the file can't be read or decoded, or if the image has no
+    /// pixels.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, MaskBitmapError> {
+        Self::from_image(&image::open(path)?)
+    }
+
+    /// How many pixels wide the bitmap is.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// How many pixels tall the bitmap is.
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.solid.len() / self.width
+    }
+
+    /// Returns `true` if the pixel at the row and column is solid.
+    ///
+    /// # Panics
+    /// Panics if the pixel is outside of the bitmap.
+    #[must_use]
+    pub fn is_solid(&self, row: usize, column: usize) -> bool {
+        assert!(column < self.width, "column is outside of the bitmap");
+        self.solid[row * self.width + column]
+    }
+}
+
+impl fmt::Display for MaskBitmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = self
+            .solid
+            .chunks(self.width)
+            .map(|row| {
+                row.iter()
+                    .map(|&solid| if solid { '#' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+
+        f.write_str(&rows.join("\n"))
+    }
+}
+
+impl TryFrom<String> for MaskBitmap {
+    type Error = MaskBitmapError;
+
+    fn try_from(ascii: String) -> Result<Self, Self::Error> {
+        Self::from_ascii(&ascii)
+    }
+}
+
+impl From<MaskBitmap> for String {
+    fn from(bitmap: MaskBitmap) -> Self {
+        bitmap.to_string()
+    }
+}
+
+/// Error returned when creating a [`MaskBitmap`] fails.
+#[derive(Debug)]
+pub enum MaskBitmapError {
+    /// The bitmap had no pixels.
+    Empty,
+    /// A row of the bitmap had a different amount of pixels than the first.
+    RaggedRows {
+        /// The row, counting from the first non-empty line.
+        row: usize,
+        /// The amount of pixels in the first row.
+        expected: usize,
+        /// The amount of pixels in the row.
+        found: usize,
+    },
+    /// A character of the ascii grid wasn't a `#` or a `.`.
+    InvalidCharacter {
+        /// The row, counting from the first non-empty line.
+        row: usize,
+        /// The column.
+        column: usize,
+        /// The invalid character.
+        character: char,
+    },
+    /// The image couldn't be read or decoded.
+    Image(image::ImageError),
+}
+
+impl fmt::Display for MaskBitmapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("mask bitmap has no pixels"),
+            Self::RaggedRows {
+                row,
+                expected,
+                found,
+            } => f.write_fmt(format_args!(
+                "mask bitmap row {} has {} pixels, expected {}",
+                row, found, expected
+            )),
+            Self::InvalidCharacter {
+                row,
+                column,
+                character,
+            } => f.write_fmt(format_args!(
+                "invalid character {:?} in mask bitmap at row {}, column {}",
+                character, row, column
+            )),
+            Self::Image(err) => f.write_fmt(format_args!("failed to load mask image: {}", err)),
+        }
+    }
+}
+
+impl Error for MaskBitmapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Image(err) => Some(err),
+            Self::Empty | Self::RaggedRows { .. } | Self::InvalidCharacter { .. } => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for MaskBitmapError {
+    fn from(err: image::ImageError) -> Self {
+        Self::Image(err)
+    }
+}
+
+#[cfg(test)]
+mod test_floor_mask {
+    use std::{convert::TryInto, num::NonZeroU16};
+
+    use super::*;
+    use crate::{
+        test_utils::point, BspGenerator, CellularAutomataGenerator, Dungeon, DungeonType, Floor,
+        FloorAlgorithm, FloorId, FloorRequest, GenerationConfig, GenerationConfigError,
+        GenerationError, MazeGenerator, Seed,
+    };
+
+    #[test]
+    fn test_floor_masks() {
+        let (width, height) = (50.try_into().unwrap(), 40.try_into().unwrap());
+
+        // a tower with a pillar in the middle, drawn as an image
+        let image = image::GrayImage::from_fn(16, 16, |x, y| {
+            let (dx, dy) = (f64::from(x) - 7.5, f64::from(y) - 7.5);
+            let distance = dx.hypot(dy);
+            image::Luma([if (2.0..8.0).contains(&distance) {
+                255
+            } else {
+                0
+            }])
+        });
+        let path = std::env::temp_dir().join("dungeon_test_mask.png");
+        image.save(&path).unwrap();
+        let bitmap = MaskBitmap::open(&path).unwrap();
+        assert_eq!((bitmap.width(), bitmap.height()), (16, 16));
+        assert!(bitmap.is_solid(0, 0) && bitmap.is_solid(8, 8) && !bitmap.is_solid(8, 2));
+
+        let masks = vec![
+            FloorMask::Circle,
+            FloorMask::Ring { inner_radius: 0.5 },
+            FloorMask::LShape { thickness: 0.5 },
+            FloorMask::Bitmap(bitmap),
+        ];
+        let algorithms = vec![
+            FloorAlgorithm::CellularAutomata,
+            FloorAlgorithm::Bsp(BspGenerator::default()),
+            FloorAlgorithm::Maze(MazeGenerator::default()),
+        ];
+
+        for mask in masks {
+            let masked = mask.solid_tiles(width, height);
+            assert!(masked.iter().any(|&masked| masked));
+            assert!(masked.iter().any(|&masked| !masked));
+
+            for algorithm in &algorithms {
+                for dungeon_type in [DungeonType::Cave, DungeonType::Forest] {
+                    let config = GenerationConfig {
+                        mask: mask.clone(),
+                        algorithms: vec![algorithm.clone()],
+                        ..GenerationConfig::default()
+                    };
+                    let floor = Floor::try_new(
+                        FloorId::new(0),
+                        dungeon_type,
+                        width,
+                        height,
+                        &config,
+                        Seed::new(0),
+                        false,
+                    )
+                    .unwrap();
+
+                    for (index, tile) in floor.data.iter().enumerate() {
+                        assert!(!masked[index] || tile.is_wall(), "{:?}: {:?}", mask, tile);
+                    }
+                    assert!(floor.stats().open_tiles > 100);
+                }
+            }
+        }
+
+        // the entrance can't be requested outside of the mask
+        let config = GenerationConfig {
+            mask: FloorMask::Circle,
+            ..GenerationConfig::default()
+        };
+        let request = |entrance| FloorRequest {
+            id: FloorId::new(0),
+            dungeon_type: DungeonType::Cave,
+            width,
+            height,
+            config: &config,
+            seed: Seed::new(0),
+            entrance: Some(entrance),
+            gif_output: false,
+        };
+        assert!(matches!(
+            Floor::try_with_generator(&CellularAutomataGenerator, &request(point(2, 2))),
+            Err(GenerationError::InvalidEntrance(_))
+        ));
+        let floor = Floor::try_with_generator(&CellularAutomataGenerator, &request(point(20, 25)));
+        assert_eq!(floor.unwrap().entrance(), Some(point(20, 25)));
+
+        // linked floors line up inside of the mask
+        let dungeon = Dungeon::try_new(
+            height,
+            width,
+            NonZeroU16::new(3).unwrap(),
+            DungeonType::Cave,
+            &GenerationConfig {
+                link_floors: true,
+                ..config.clone()
+            },
+            Seed::new(1),
+            false,
+        )
+        .unwrap();
+        for floors in dungeon.floors.windows(2) {
+            assert_eq!(floors[0].exit(), floors[1].entrance());
+        }
+
+        for (mask, name) in [
+            (FloorMask::Ring { inner_radius: 1.0 }, "ring inner radius"),
+            (FloorMask::LShape { thickness: 0.0 }, "l shape thickness"),
+            (
+                FloorMask::LShape {
+                    thickness: f64::NAN,
+                },
+                "l shape thickness",
+            ),
+        ] {
+            let config = GenerationConfig {
+                mask,
+                ..GenerationConfig::default()
+            };
+            assert!(matches!(
+                config.validate(),
+                Err(GenerationConfigError::MaskParameter { name: found, .. }) if found == name
+            ));
+        }
+
+        let bitmap = MaskBitmap::from_ascii("\n#..#\n....\n#..#\n").unwrap();
+        let config = GenerationConfig {
+            mask: FloorMask::Bitmap(bitmap),
+            ..GenerationConfig::default()
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(json.contains(r##""Bitmap":"#..#\n....\n#..#""##));
+        assert_eq!(
+            serde_json::from_str::<GenerationConfig>(&json).unwrap(),
+            config
+        );
+
+        assert!(matches!(
+            MaskBitmap::from_ascii("#..#\n..."),
+            Err(MaskBitmapError::RaggedRows {
+                row: 1,
+                expected: 4,
+                found: 3
+            })
+        ));
+        assert!(matches!(
+            MaskBitmap::from_ascii("#..#\n.x.."),
+            Err(MaskBitmapError::InvalidCharacter {
+                row: 1,
+                column: 1,
+                character: 'x'
+            })
+        ));
+        assert!(matches!(
+            MaskBitmap::from_ascii("\n\n"),
+            Err(MaskBitmapError::Empty)
+        ));
+    }
+}
@@ -0,0 +1,318 @@
+use std::{cmp::Ordering, collections::BTreeSet, convert::TryInto};
+
+use crate::{Column, Floor, Point, Row};
+
+/// Returns every point that can be seen from the origin, using symmetric
+/// shadowcasting. The origin itself is always visible.
+///
+/// Tiles that [block sight](crate::DungeonTile::blocks_sight) are visible themselves,
+/// but hide everything behind them. If a radius is provided, only the points
+/// within that (euclidean) distance of the origin are visible.
+///
+/// The field of view is symmetric: if `b` is visible from `a`, then `a` is
+/// visible from `b` (as long as neither of them blocks sight).
+///
+/// See <https://www.albertford.com/shadowcasting/>
+///
+/// # Examples
+/// ```rust
+/// use dungeon::{fov, DungeonType, Floor, FloorId, GenerationConfig, Seed};
+/// use std::convert::TryInto;
+///
+/// let floor = Floor::with_seed(
+///     FloorId::new(0),
+///     DungeonType::Cave,
+///     50.try_into().unwrap(),
+///     50.try_into().unwrap(),
+///     &GenerationConfig::default(),
+///     Seed::new(0),
+///     false,
+/// );
+///
+/// let entrance = floor.entrance().unwrap();
+/// let visible = fov::field_of_view(&floor, entrance, Some(8));
+///
+/// assert!(visible.contains(&entrance));
+/// assert!(visible
+///     .iter()
+///     .all(|&point| fov::line_of_sight(&floor, entrance, point)));
+/// ```
+#[must_use]
+pub fn field_of_view(floor: &Floor, origin: Point, radius: Option<u16>) -> BTreeSet<Point> {
+    let mut visible = BTreeSet::new();
+
+    let max_depth = match radius {
+        Some(radius) => i32::from(radius),
+        None => floor.width.as_unbounded().max(floor.height.as_unbounded()),
+    };
+    let radius_squared = radius.map(|radius| i32::from(radius).pow(2));
+
+    cast(floor, origin, max_depth, |point, row, column| {
+        if radius_squared.is_none_or(|radius_squared| row * row + column * column <= radius_squared)
+        {
+            visible.insert(point);
+        }
+    });
+
+    visible
+}
+
+/// Returns `true` if `to` can be seen from `from`, i.e. if `to` is in the
+/// [`field_of_view`] of `from` with no radius.
+///
+/// Line of sight is symmetric: `line_of_sight(floor, a, b)` is always the same
+/// as `line_of_sight(floor, b, a)`, as long as neither of the points
+/// [block sight](crate::DungeonTile::blocks_sight).
+#[must_use]
+pub fn line_of_sight(floor: &Floor, from: Point, to: Point) -> bool {
+    let (from_row, from_column) = coordinates(from);
+    let (to_row, to_column) = coordinates(to);
+    let depth = (to_row - from_row)
+        .abs()
+        .max((to_column - from_column).abs());
+
+    if in_bounds(floor, from_row, from_column).is_none()
+        || in_bounds(floor, to_row, to_column).is_none()
+    {
+        return false;
+    }
+
+    // nothing past the target needs to be scanned
+    let mut seen = false;
+    cast(floor, from, depth, |point, _, _| seen |= point == to);
+    seen
+}
+
+/// Runs symmetric shadowcasting out from the origin, up to the max depth,
+/// calling `reveal` with every visible point and it's offset from the origin
+/// (in rows and columns).
+fn cast(floor: &Floor, origin: Point, max_depth: i32, mut reveal: impl FnMut(Point, i32, i32)) {
+    let (origin_row, origin_column) = coordinates(origin);
+    if let Some(point) = in_bounds(floor, origin_row, origin_column) {
+        reveal(point, 0, 0);
+    }
+
+    for quadrant in [
+        Quadrant::North,
+        Quadrant::East,
+        Quadrant::South,
+        Quadrant::West,
+    ] {
+        // each row is scanned from it's start slope to it's end slope
+        let mut rows = vec![(1, Slope::new(-1, 1), Slope::new(1, 1))];
+
+        while let Some((depth, mut start, end)) = rows.pop() {
+            if depth > max_depth {
+                continue;
+            }
+
+            // the tile of the row at the column, and whether or not it blocks
+            // sight (tiles outside of the floor block sight, but aren't
+            // revealed)
+            let tile = |column: i32| {
+                let (row_offset, column_offset) = quadrant.transform(depth, column);
+                let point = in_bounds(
+                    floor,
+                    origin_row + row_offset,
+                    origin_column + column_offset,
+                );
+                let blocks = point.is_none_or(|point| floor.at(point).blocks_sight());
+                (point, blocks, row_offset, column_offset)
+            };
+
+            let mut previous_blocks = None;
+            for column in start.round_ties_up(depth)..=end.round_ties_down(depth) {
+                let (point, blocks, row_offset, column_offset) = tile(column);
+
+                let is_symmetric = start.at_most(depth, column) && end.at_least(depth, column);
+                if let Some(point) = point {
+                    if blocks || is_symmetric {
+                        reveal(point, row_offset, column_offset);
+                    }
+                }
+
+                match (previous_blocks, blocks) {
+                    (Some(true), false) => start = Slope::of(depth, column),
+                    (Some(false), true) => rows.push((depth + 1, start, Slope::of(depth, column))),
+                    _ => {}
+                }
+                previous_blocks = Some(blocks);
+            }
+
+            if previous_blocks == Some(false) {
+                rows.push((depth + 1, start, end));
+            }
+        }
+    }
+}
+
+/// Returns the row and column of the point.
+fn coordinates(point: Point) -> (i32, i32) {
+    (
+        point.row.get().as_unbounded(),
+        point.column.get().as_unbounded(),
+    )
+}
+
+/// Returns the point at the row and column, if it's inside the floor.
+fn in_bounds(floor: &Floor, row: i32, column: i32) -> Option<Point> {
+    if row < 0
+        || column < 0
+        || row >= floor.height.as_unbounded()
+        || column >= floor.width.as_unbounded()
+    {
+        return None;
+    }
+
+    Some(Point {
+        row: Row::new(row.try_into().ok()?),
+        column: Column::new(column.try_into().ok()?),
+    })
+}
+
+/// One of the four 90 degree cones around the origin that are scanned
+/// separately.
+#[derive(Debug, Clone, Copy)]
+enum Quadrant {
+    /// The rows above the origin.
+    North,
+    /// The columns right of the origin.
+    East,
+    /// The rows below the origin.
+    South,
+    /// The columns left of the origin.
+    West,
+}
+
+impl Quadrant {
+    /// Transforms the depth and column within the quadrant into an offset (in
+    /// rows and columns) from the origin.
+    const fn transform(self, depth: i32, column: i32) -> (i32, i32) {
+        match self {
+            Quadrant::North => (-depth, column),
+            Quadrant::South => (depth, column),
+            Quadrant::East => (column, depth),
+            Quadrant::West => (column, -depth),
+        }
+    }
+}
+
+/// An exact slope, as a fraction with a positive denominator.
+#[derive(Debug, Clone, Copy)]
+struct Slope {
+    /// The numerator.
+    numerator: i32,
+    /// The denominator. Always positive.
+    denominator: i32,
+}
+
+impl Slope {
+    /// Creates a new slope. The denominator must be positive.
+    const fn new(numerator: i32, denominator: i32) -> Self {
+        Self {
+            numerator,
+            denominator,
+        }
+    }
+
+    /// The slope of the left edge of the tile at the column in the row at the
+    /// depth.
+    const fn of(depth: i32, column: i32) -> Self {
+        Self::new(2 * column - 1, 2 * depth)
+    }
+
+    /// Returns `depth * slope` rounded to the nearest whole number, rounding
+    /// halves up.
+    fn round_ties_up(self, depth: i32) -> i32 {
+        (2 * depth * self.numerator + self.denominator).div_euclid(2 * self.denominator)
+    }
+
+    /// Returns `depth * slope` rounded to the nearest whole number, rounding
+    /// halves down.
+    fn round_ties_down(self, depth: i32) -> i32 {
+        -(self.denominator - 2 * depth * self.numerator).div_euclid(2 * self.denominator)
+    }
+
+    /// Compares `depth * slope` to the column.
+    fn compare(self, depth: i32, column: i32) -> Ordering {
+        (depth * self.numerator).cmp(&(column * self.denominator))
+    }
+
+    /// Returns `true` if `depth * slope <= column`.
+    fn at_most(self, depth: i32, column: i32) -> bool {
+        self.compare(depth, column) != Ordering::Greater
+    }
+
+    /// Returns `true` if `depth * slope >= column`.
+    fn at_least(self, depth: i32, column: i32) -> bool {
+        self.compare(depth, column) != Ordering::Less
+    }
+}
+
+#[cfg(test)]
+mod test_fov {
+    use super::*;
+    use crate::{
+        test_utils::{cave_floor, floor_from_rows, point},
+        GenerationConfig,
+    };
+
+    #[test]
+    fn test_field_of_view() {
+        let floor = floor_from_rows(&[
+            "##########",
+            "#........#",
+            "#........#",
+            "#...#....#",
+            "#........#",
+            "#######D##",
+            "#........#",
+            "#######d##",
+            "#........#",
+            "##########",
+        ]);
+
+        let visible = field_of_view(&floor, point(1, 4), None);
+        // the pillar hides the tile right behind it, but is visible itself
+        assert!(visible.contains(&point(3, 4)));
+        assert!(!visible.contains(&point(4, 4)));
+        assert!(visible.contains(&point(4, 1)));
+        assert!(visible.contains(&point(0, 0)));
+        // the closed secret door blocks sight, but the open one doesn't
+        assert!(visible.contains(&point(5, 7)));
+        assert!(!visible.contains(&point(6, 7)));
+        let visible = field_of_view(&floor, point(6, 7), None);
+        assert!(visible.contains(&point(8, 7)));
+
+        let visible = field_of_view(&floor, point(1, 1), Some(3));
+        assert!(visible.contains(&point(1, 4)));
+        assert!(visible.contains(&point(3, 3)));
+        assert!(!visible.contains(&point(1, 5)));
+        assert!(!visible.contains(&point(3, 4)));
+
+        assert!(line_of_sight(&floor, point(1, 1), point(1, 1)));
+        assert!(!line_of_sight(&floor, point(1, 1), point(8, 8)));
+        assert!(!line_of_sight(&floor, point(1, 1), point(12, 8)));
+    }
+
+    #[test]
+    fn test_line_of_sight_is_symmetric() {
+        let floor = cave_floor(40, &GenerationConfig::default(), 0);
+
+        let open = floor
+            .iter_points_and_tiles()
+            .filter(|(_, tile)| !tile.blocks_sight())
+            .map(|(point, _)| point)
+            .step_by(7)
+            .collect::<Vec<_>>();
+
+        for &a in &open {
+            let visible = field_of_view(&floor, a, None);
+            for &b in &open {
+                let seen = line_of_sight(&floor, a, b);
+                assert_eq!(seen, line_of_sight(&floor, b, a));
+                assert_eq!(seen, visible.contains(&b));
+            }
+        }
+    }
+}
@@ -0,0 +1,597 @@
+use std::{error::Error, fmt};
+
+use noise::Billow;
+use serde::{Deserialize, Serialize};
+
+use crate::{FloorAlgorithm, FloorId, FloorMask, LootTable, LootTableError, Vault};
+
+/// The parameters used to generate a [`Floor`](crate::Floor).
+///
+/// The [`Default`] implementation contains the values that have been tuned to
+/// generate the standard cave floors. Every field can be tweaked (or loaded
+/// from a file, since the config is serializable) to change the way floors
+/// look without recompiling.
+///
+/// # Examples
+/// ```rust
+/// use dungeon::GenerationConfig;
+///
+/// let config: GenerationConfig = serde_json::from_str(
+///     &serde_json::to_string(&GenerationConfig::default()).unwrap()
+/// ).unwrap();
+///
+/// assert_eq!(config, GenerationConfig::default());
+/// assert!(config.validate().is_ok());
+///
+/// let invalid = GenerationConfig {
+///     wall_percent_chance: 101,
+///     ..GenerationConfig::default()
+/// };
+///
+/// assert!(invalid.validate().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationConfig {
+    /// The percent chance (`0..=100`) of a wall being placed during the
+    /// initial noise generation.
+    pub wall_percent_chance: u8,
+    /// The parameters for the noise map, used when tracing the path between
+    /// the entrance and the exit and when connecting the caves.
+    pub noise: NoiseConfig,
+    /// The cellular automata pass run right after the initial random fill,
+    /// forming the caves.
+    pub initial_smoothing: SmoothingPass,
+    /// How many connections to build between the caves after they have been
+    /// formed.
+    pub connection_iterations: BuildConnectionIterations,
+    /// Whether or not the paths connecting the caves are widened.
+    pub wide_connections: bool,
+    /// Whether or not the paths connecting the caves follow the noise map. If
+    /// `false`, the paths will be as short as possible.
+    pub connections_use_noise_map: bool,
+    /// The cellular automata pass run after the caves have been connected,
+    /// smoothing out the connections.
+    pub final_smoothing: SmoothingPass,
+    /// The treasure chests placed in the floor.
+    pub treasure: TreasureConfig,
+    /// The spawn points for monsters placed in the floor.
+    pub monsters: MonsterConfig,
+    /// The parameters specific to [`DungeonType::Forest`] floors.
+    ///
+    /// Forest floors ignore [`wall_percent_chance`], the smoothing passes and
+    /// the connection parameters above (other than
+    /// [`connection_iterations`]), using these instead.
+    ///
+    /// [`DungeonType::Forest`]: crate::DungeonType::Forest
+    /// [`wall_percent_chance`]: GenerationConfig::wall_percent_chance
+    /// [`connection_iterations`]: GenerationConfig::connection_iterations
+    pub forest: ForestConfig,
+    /// The algorithms used to generate the floors. Floor `n` uses the
+    /// algorithm at index `n % algorithms.len()`, so a single algorithm is
+    /// used for every floor and several are cycled through.
+    ///
+    /// If empty, every floor uses [`FloorAlgorithm::CellularAutomata`].
+    pub algorithms: Vec<FloorAlgorithm>,
+    /// Whether or not the floors of a [`Dungeon`](crate::Dungeon) are linked,
+    /// so that the entrance of each floor is at the same point as the exit of
+    /// the floor before it. Linked floors have to be generated one after
+    /// another, and each floor depends on the one before it.
+    pub link_floors: bool,
+    /// The minimum walking distance (in steps) between the entrance and the
+    /// exit of every floor. If the exit ends up closer than this, it is moved
+    /// to the tile furthest away from the entrance; if that is still too
+    /// close, generation fails with [`GenerationError::ExitTooClose`].
+    ///
+    /// Locked doors can't be walked through, so with a minimum distance set,
+    /// an exit behind one is moved as well. With the default of `0`, the exit
+    /// is never moved.
+    ///
+    /// [`GenerationError::ExitTooClose`]: crate::GenerationError::ExitTooClose
+    pub min_exit_distance: u16,
+    /// The shape of the floors; every tile outside of it is always a wall.
+    /// See [`FloorMask`].
+    ///
+    /// Ignored by the chunks of a [`World`](crate::World), which have to
+    /// line up with the chunks around them.
+    pub mask: FloorMask,
+    /// The hand-designed rooms stamped into the caves of the floors. See
+    /// [`Vault`].
+    ///
+    /// Only used by [`FloorAlgorithm::CellularAutomata`] floors (and the
+    /// chunks of a [`World`](crate::World), which are generated the same way).
+    pub vaults: VaultConfig,
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        Self {
+            wall_percent_chance: 52,
+            noise: NoiseConfig::default(),
+            initial_smoothing: SmoothingPass {
+                iterations: 3,
+                new_wall_iterations: 4,
+            },
+            connection_iterations: BuildConnectionIterations::Finite(20),
+            wide_connections: true,
+            connections_use_noise_map: true,
+            final_smoothing: SmoothingPass {
+                iterations: 7,
+                new_wall_iterations: 0,
+            },
+            treasure: TreasureConfig::default(),
+            monsters: MonsterConfig::default(),
+            forest: ForestConfig::default(),
+            algorithms: vec![FloorAlgorithm::default()],
+            link_floors: false,
+            min_exit_distance: 0,
+            mask: FloorMask::Rectangle,
+            vaults: VaultConfig::default(),
+        }
+    }
+}
+
+impl GenerationConfig {
+    /// Checks that all of the parameters are within their valid ranges.
+    ///
+    /// # Errors
+    /// Returns the first invalid parameter found, if any.
+    pub fn validate(&self) -> Result<(), GenerationConfigError> {
+        for chance in [
+            self.wall_percent_chance,
+            self.forest.clearing_tree_percent_chance,
+            self.forest.thicket_tree_percent_chance,
+        ] {
+            if chance > 100 {
+                return Err(GenerationConfigError::WallPercentChance(chance));
+            }
+        }
+
+        if !(1..=Billow::MAX_OCTAVES).contains(&self.noise.octaves) {
+            return Err(GenerationConfigError::NoiseOctaves(self.noise.octaves));
+        }
+
+        for (name, value) in [
+            ("frequency", self.noise.frequency),
+            ("lacunarity", self.noise.lacunarity),
+            ("persistence", self.noise.persistence),
+        ] {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(GenerationConfigError::NoiseParameter { name, value });
+            }
+        }
+
+        if self.treasure.min_chests > self.treasure.max_chests {
+            return Err(GenerationConfigError::TreasureChestRange {
+                min: self.treasure.min_chests,
+                max: self.treasure.max_chests,
+            });
+        }
+
+        self.treasure
+            .loot
+            .validate()
+            .map_err(GenerationConfigError::LootTable)?;
+
+        if self.vaults.min_vaults > self.vaults.max_vaults {
+            return Err(GenerationConfigError::VaultRange {
+                min: self.vaults.min_vaults,
+                max: self.vaults.max_vaults,
+            });
+        }
+
+        if !(0.0..=1.0).contains(&self.monsters.density) {
+            return Err(GenerationConfigError::MonsterDensity(self.monsters.density));
+        }
+
+        let mask_parameter = match self.mask {
+            FloorMask::Ring { inner_radius } if !(0.0..1.0).contains(&inner_radius) => {
+                Some(("ring inner radius", inner_radius))
+            }
+            FloorMask::LShape { thickness } if !(thickness > 0.0 && thickness <= 1.0) => {
+                Some(("l shape thickness", thickness))
+            }
+            _ => None,
+        };
+        if let Some((name, value)) = mask_parameter {
+            return Err(GenerationConfigError::MaskParameter { name, value });
+        }
+
+        for algorithm in &self.algorithms {
+            match algorithm {
+                FloorAlgorithm::Bsp(bsp)
+                    if bsp.min_room_size == 0
+                        || bsp.min_leaf_size < bsp.min_room_size.saturating_add(2) =>
+                {
+                    return Err(GenerationConfigError::BspSizes {
+                        min_leaf_size: bsp.min_leaf_size,
+                        min_room_size: bsp.min_room_size,
+                    })
+                }
+                FloorAlgorithm::DrunkardsWalk(walk)
+                    if !(1..=100).contains(&walk.floor_percent) || walk.walk_length == 0 =>
+                {
+                    return Err(GenerationConfigError::DrunkardsWalk {
+                        floor_percent: walk.floor_percent,
+                        walk_length: walk.walk_length,
+                    })
+                }
+                FloorAlgorithm::Maze(maze) if maze.braid_percent_chance > 100 => {
+                    return Err(GenerationConfigError::BraidPercentChance(
+                        maze.braid_percent_chance,
+                    ))
+                }
+                FloorAlgorithm::Wfc(wfc)
+                    if !(2..=4).contains(&wfc.pattern_size)
+                        || usize::from(wfc.pattern_size) > wfc.sample.width()
+                        || usize::from(wfc.pattern_size) > wfc.sample.height() =>
+                {
+                    return Err(GenerationConfigError::WfcPatternSize {
+                        pattern_size: wfc.pattern_size,
+                        sample_width: wfc.sample.width(),
+                        sample_height: wfc.sample.height(),
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the algorithm used to generate the floor with the provided id.
+    /// See [`GenerationConfig::algorithms`].
+    #[must_use]
+    pub fn algorithm_for(&self, id: FloorId) -> &FloorAlgorithm {
+        if self.algorithms.is_empty() {
+            &FloorAlgorithm::CellularAutomata
+        } else {
+            &self.algorithms[usize::from(id.get()) % self.algorithms.len()]
+        }
+    }
+}
+
+/// The parameters passed to the [`Billow`] noise function used to create the
+/// noise map.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoiseConfig {
+    /// The amount of octaves. Must be between `1` and [`Billow::MAX_OCTAVES`].
+    pub octaves: usize,
+    /// The frequency of the first octave. Higher values create smaller, more
+    /// frequent features.
+    pub frequency: f64,
+    /// The multiplier applied to the frequency of each successive octave.
+    pub lacunarity: f64,
+    /// The multiplier applied to the amplitude of each successive octave.
+    pub persistence: f64,
+}
+
+impl Default for NoiseConfig {
+    /// These ⭐ magic numbers ⭐ have been fine tuned to work well.
+    ///
+    /// Don't touch 'em
+    fn default() -> Self {
+        Self {
+            octaves: 1,
+            frequency: 5.0,
+            lacunarity: 0.001,
+            persistence: 0.001,
+        }
+    }
+}
+
+/// A pass of the cellular automata over the floor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SmoothingPass {
+    /// How many times the cellular automata is run.
+    pub iterations: usize,
+    /// For how many of the iterations new walls are created in large open
+    /// areas. Creating new walls breaks up big caves into smaller ones.
+    pub new_wall_iterations: usize,
+}
+
+/// How many iterations there should be when generating the connections between
+/// the caves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BuildConnectionIterations {
+    /// Until there is only 1 scc left (the caves are fully connected).
+    FullyConnect,
+    /// A finite amount of times, or until the caves are fully connected. Each
+    /// iteration tries to connect one cave to a cave it can't reach yet,
+    /// cycling through the caves as many times as needed.
+    Finite(u8),
+    /// Until there are at most the specified amount of sccs; guaranteed
+    /// to be `<=` the specified amount.
+    Until(u8),
+}
+
+/// The parameters for the treasure chests placed in a floor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TreasureConfig {
+    /// The minimum amount of chests to place, inclusive.
+    pub min_chests: u16,
+    /// The maximum amount of chests to place, inclusive.
+    pub max_chests: u16,
+    /// How the tiles the chests are placed on are picked.
+    pub placement: TreasurePlacement,
+    /// The loot table the chests are filled from.
+    pub loot: LootTable,
+}
+
+impl Default for TreasureConfig {
+    fn default() -> Self {
+        Self {
+            min_chests: 5,
+            max_chests: 10,
+            placement: TreasurePlacement::default(),
+            loot: LootTable::default(),
+        }
+    }
+}
+
+/// How the tiles for the treasure chests are picked.
+///
+/// Whatever the strategy, a chest is never placed where it would cut off part
+/// of the floor (e.g. in the middle of a corridor), so every chest, key and
+/// the exit can still be reached from the entrance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TreasurePlacement {
+    /// Any empty tile in the open, with nothing but empty tiles around it.
+    #[default]
+    Random,
+    /// Like [`TreasurePlacement::Random`], but the tiles with the lowest
+    /// values in the noise map are picked first, clustering the chests.
+    Noise,
+    /// Tiles out of the way are picked first: dead ends and alcoves (tiles
+    /// with walls on several sides), far away from the path between the
+    /// entrance and the exit, so that finding the chests takes exploring.
+    Secluded,
+}
+
+/// The parameters for the monster spawn points placed in a floor. See
+/// [`Floor::spawn_points`](crate::Floor::spawn_points).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonsterConfig {
+    /// How many spawn points are placed per tile of each cave, between `0`
+    /// and `1`; with a density of `0.01`, a cave of 300 tiles gets 3 spawn
+    /// points. Caves without enough open space get fewer.
+    pub density: f64,
+    /// The minimum walking distance (in steps) between the entrance and every
+    /// spawn point, so that the player isn't ambushed the moment they arrive.
+    pub min_entrance_distance: u16,
+}
+
+impl Default for MonsterConfig {
+    fn default() -> Self {
+        Self {
+            density: 0.01,
+            min_entrance_distance: 10,
+        }
+    }
+}
+
+/// The parameters for the [`Vault`]s stamped into a floor. See
+/// [`Floor::vaults`](crate::Floor::vaults).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VaultConfig {
+    /// The minimum amount of vaults to place, inclusive.
+    pub min_vaults: u16,
+    /// The maximum amount of vaults to place, inclusive.
+    pub max_vaults: u16,
+    /// The vaults to pick from. Each vault placed is picked at random, so the
+    /// same vault can be placed more than once. If there is nowhere left for
+    /// a vault to go (with room around it, away from the other vaults, the
+    /// entrance and the exit, and with a door opening onto a tile that can be
+    /// reached from the entrance), fewer vaults are placed.
+    ///
+    /// Vaults are left exactly as they were drawn: no treasure chests, keys
+    /// or spawn points are placed inside of them.
+    pub prefabs: Vec<Vault>,
+}
+
+impl Default for VaultConfig {
+    fn default() -> Self {
+        Self {
+            min_vaults: 1,
+            max_vaults: 1,
+            prefabs: vec![],
+        }
+    }
+}
+
+/// The parameters for [`DungeonType::Forest`] floors.
+///
+/// The noise map splits the floor into clearings and thickets, each with their
+/// own chance of a tree (wall) being placed.
+///
+/// [`DungeonType::Forest`]: crate::DungeonType::Forest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ForestConfig {
+    /// The percent chance (`0..=100`) of a tree being placed in a clearing.
+    pub clearing_tree_percent_chance: u8,
+    /// The percent chance (`0..=100`) of a tree being placed in a thicket.
+    pub thicket_tree_percent_chance: u8,
+    /// The cellular automata pass that turns the random trees into clusters.
+    pub smoothing: SmoothingPass,
+    /// Whether or not the trails between the clearings are widened.
+    pub wide_trails: bool,
+}
+
+impl Default for ForestConfig {
+    fn default() -> Self {
+        Self {
+            clearing_tree_percent_chance: 30,
+            thicket_tree_percent_chance: 65,
+            smoothing: SmoothingPass {
+                iterations: 4,
+                new_wall_iterations: 0,
+            },
+            wide_trails: false,
+        }
+    }
+}
+
+/// Error returned from [`GenerationConfig::validate`] when a parameter is
+/// invalid.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenerationConfigError {
+    /// [`GenerationConfig::wall_percent_chance`], or one of the tree percent
+    /// chances in the [`ForestConfig`], was greater than 100.
+    ///
+    /// Contains the invalid value.
+    WallPercentChance(u8),
+    /// [`NoiseConfig::octaves`] was not between `1` and
+    /// [`Billow::MAX_OCTAVES`].
+    ///
+    /// Contains the invalid value.
+    NoiseOctaves(usize),
+    /// One of the floating point parameters of the [`NoiseConfig`] was not a
+    /// finite, positive number.
+    NoiseParameter {
+        /// The name of the parameter.
+        name: &'static str,
+        /// The invalid value.
+        value: f64,
+    },
+    /// [`TreasureConfig::min_chests`] was greater than
+    /// [`TreasureConfig::max_chests`].
+    TreasureChestRange {
+        /// The minimum amount of chests.
+        min: u16,
+        /// The maximum amount of chests.
+        max: u16,
+    },
+    /// The [`TreasureConfig::loot`] table was invalid.
+    LootTable(LootTableError),
+    /// [`VaultConfig::min_vaults`] was greater than
+    /// [`VaultConfig::max_vaults`].
+    VaultRange {
+        /// The minimum amount of vaults.
+        min: u16,
+        /// The maximum amount of vaults.
+        max: u16,
+    },
+    /// [`MonsterConfig::density`] was not between `0` and `1`.
+    ///
+    /// Contains the invalid value.
+    MonsterDensity(f64),
+    /// [`BspGenerator::min_room_size`] was `0`, or wasn't at least 2 smaller
+    /// than [`BspGenerator::min_leaf_size`].
+    ///
+    /// [`BspGenerator::min_room_size`]: crate::BspGenerator::min_room_size
+    /// [`BspGenerator::min_leaf_size`]: crate::BspGenerator::min_leaf_size
+    BspSizes {
+        /// The minimum leaf size.
+        min_leaf_size: u8,
+        /// The minimum room size.
+        min_room_size: u8,
+    },
+    /// [`DrunkardsWalkGenerator::floor_percent`] wasn't between `1` and `100`,
+    /// or [`DrunkardsWalkGenerator::walk_length`] was `0`.
+    ///
+    /// [`DrunkardsWalkGenerator::floor_percent`]: crate::DrunkardsWalkGenerator::floor_percent
+    /// [`DrunkardsWalkGenerator::walk_length`]: crate::DrunkardsWalkGenerator::walk_length
+    DrunkardsWalk {
+        /// The floor percent.
+        floor_percent: u8,
+        /// The walk length.
+        walk_length: u16,
+    },
+    /// [`MazeGenerator::braid_percent_chance`] was greater than 100.
+    ///
+    /// Contains the invalid value.
+    ///
+    /// [`MazeGenerator::braid_percent_chance`]: crate::MazeGenerator::braid_percent_chance
+    BraidPercentChance(u8),
+    /// [`WfcGenerator::pattern_size`] wasn't between `2` and `4`, or was
+    /// larger than the sample.
+    ///
+    /// [`WfcGenerator::pattern_size`]: crate::WfcGenerator::pattern_size
+    WfcPatternSize {
+        /// The pattern size.
+        pattern_size: u8,
+        /// The width of the sample.
+        sample_width: usize,
+        /// The height of the sample.
+        sample_height: usize,
+    },
+    /// The `inner_radius` of a [`FloorMask::Ring`] wasn't at least `0` and
+    /// less than `1`, or the `thickness` of a [`FloorMask::LShape`] wasn't
+    /// greater than `0` and at most `1`.
+    MaskParameter {
+        /// The name of the parameter.
+        name: &'static str,
+        /// The invalid value.
+        value: f64,
+    },
+}
+
+impl fmt::Display for GenerationConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WallPercentChance(chance) => f.write_fmt(format_args!(
+                "wall percent chance must be at most 100, got {}",
+                chance
+            )),
+            Self::NoiseOctaves(octaves) => f.write_fmt(format_args!(
+                "noise octaves must be between 1 and {}, got {}",
+                Billow::MAX_OCTAVES,
+                octaves
+            )),
+            Self::NoiseParameter { name, value } => f.write_fmt(format_args!(
+                "noise {} must be a finite, positive number, got {}",
+                name, value
+            )),
+            Self::TreasureChestRange { min, max } => f.write_fmt(format_args!(
+                "minimum treasure chests ({}) must not be greater than the maximum ({})",
+                min, max
+            )),
+            Self::LootTable(err) => f.write_fmt(format_args!("invalid loot table: {}", err)),
+            Self::VaultRange { min, max } => f.write_fmt(format_args!(
+                "minimum vaults ({}) must not be greater than the maximum ({})",
+                min, max
+            )),
+            Self::MonsterDensity(density) => f.write_fmt(format_args!(
+                "monster density must be between 0 and 1, got {}",
+                density
+            )),
+            Self::BspSizes {
+                min_leaf_size,
+                min_room_size,
+            } => f.write_fmt(format_args!(
+                "bsp minimum room size ({}) must be at least 1, and at least 2 smaller than the minimum leaf size ({})",
+                min_room_size, min_leaf_size
+            )),
+            Self::DrunkardsWalk {
+                floor_percent,
+                walk_length,
+            } => f.write_fmt(format_args!(
+                "drunkard's walk floor percent ({}) must be between 1 and 100, and walk length ({}) must be at least 1",
+                floor_percent, walk_length
+            )),
+            Self::BraidPercentChance(chance) => f.write_fmt(format_args!(
+                "maze braid percent chance must be at most 100, got {}",
+                chance
+            )),
+            Self::WfcPatternSize {
+                pattern_size,
+                sample_width,
+                sample_height,
+            } => f.write_fmt(format_args!(
+                "wfc pattern size ({}) must be between 2 and 4, and fit within the {}x{} sample",
+                pattern_size, sample_width, sample_height
+            )),
+            Self::MaskParameter { name, value } => f.write_fmt(format_args!(
+                "mask {} is out of range, got {}",
+                name, value
+            )),
+        }
+    }
+}
+
+impl Error for GenerationConfigError {}
@@ -0,0 +1,239 @@
+use std::{error::Error, fmt};
+
+use bounded_int::BoundedIntError;
+
+use crate::{DungeonTile, FloorViolation, GenerationConfigError, Point, WorldPoint};
+
+/// Error returned when generating a [`Floor`](crate::Floor) or a
+/// [`Dungeon`](crate::Dungeon) fails.
+///
+/// Generation is deterministic, so retrying with the same [`Seed`] and
+/// parameters will fail in the same way; retry with a different seed instead.
+///
+/// [`Seed`]: crate::Seed
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum GenerationError {
+    /// The [`GenerationConfig`](crate::GenerationConfig) was invalid.
+    InvalidConfig(GenerationConfigError),
+    /// The dimensions of the floor were outside of the bounds
+    /// [`MIN_FLOOR_SIZE`](crate::MIN_FLOOR_SIZE) and
+    /// [`MAX_FLOOR_SIZE`](crate::MAX_FLOOR_SIZE), or the generated floor
+    /// didn't have the right amount of tiles for it's dimensions.
+    InvalidDimensions {
+        /// The width of the floor.
+        width: i32,
+        /// The height of the floor.
+        height: i32,
+    },
+    /// A value calculated during generation didn't fit within the bounds of
+    /// the floor.
+    OutOfBounds(BoundedIntError),
+    /// There was no path between the entrance and the exit of the floor.
+    UnreachableExit {
+        /// The entrance of the floor.
+        entrance: Point,
+        /// The exit of the floor.
+        exit: Point,
+    },
+    /// A path connecting two caves contained it's own start or end point as
+    /// one of the points in between.
+    InvalidConnectionPath {
+        /// The start of the path.
+        start: Point,
+        /// The end of the path.
+        end: Point,
+    },
+    /// A tile that was placed during generation was overwritten by a later
+    /// stage.
+    TileOverwritten {
+        /// Where the tile was placed.
+        point: Point,
+        /// The tile that should be at the point.
+        expected: DungeonTile,
+        /// The tile that was actually found at the point.
+        found: DungeonTile,
+    },
+    /// A carved floor had less than 2 open tiles connected to each other, or
+    /// the [mask](crate::GenerationConfig::mask) of a floor left less than 2
+    /// tiles open, so there was no room for both an entrance and an exit.
+    ///
+    /// Contains the amount of connected open tiles.
+    TooFewOpenTiles(usize),
+    /// The generated floor didn't have exactly one entrance and one exit.
+    InvalidEntrancesAndExits {
+        /// How many entrances the floor had.
+        entrances: usize,
+        /// How many exits the floor had.
+        exits: usize,
+    },
+    /// A tile of the generated floor that isn't a wall couldn't be reached
+    /// from the entrance.
+    UnreachableTile {
+        /// The entrance of the floor.
+        entrance: Point,
+        /// The first tile found that couldn't be reached.
+        tile: Point,
+    },
+    /// The requested [entrance](crate::FloorRequest::entrance) was on or
+    /// outside the edge of the floor, or was masked out by the floor's
+    /// [mask](crate::GenerationConfig::mask).
+    InvalidEntrance(Point),
+    /// The generator didn't place the entrance at the requested
+    /// [entrance](crate::FloorRequest::entrance).
+    EntranceMismatch {
+        /// The requested entrance.
+        requested: Point,
+        /// Where the entrance was actually placed.
+        found: Point,
+    },
+    /// Even the furthest tile from the entrance was closer than
+    /// [`GenerationConfig::min_exit_distance`](crate::GenerationConfig::min_exit_distance).
+    ExitTooClose {
+        /// The walking distance to the furthest tile from the entrance.
+        distance: i32,
+        /// The minimum distance.
+        min: u16,
+    },
+    /// The key to the locked door at the point couldn't be reached without
+    /// going through the door first, so the door could never be opened.
+    LockedOut(Point),
+    /// The [contents](crate::DungeonTile::TreasureChest::contents) of the
+    /// treasure chest at the point weren't in [`Floor::chests`].
+    ///
+    /// [`Floor::chests`]: crate::Floor::chests
+    MissingChestContents(Point),
+    /// The generated floor wasn't [valid](crate::Floor::validate), for a
+    /// reason not covered by any of the other variants.
+    InvalidFloor(FloorViolation),
+    /// Wave function collapse kept running into contradictions (areas where
+    /// none of the patterns of the sample fit).
+    ///
+    /// Contains how many attempts were made.
+    WfcContradiction(u8),
+    /// The tile at the point was masked out by the floor's
+    /// [mask](crate::GenerationConfig::mask), but wasn't a wall.
+    MaskedTileOpen(Point),
+    /// The point was so far out that the chunk of the
+    /// [`World`](crate::World) it would be in can't be represented.
+    OutsideOfWorld(WorldPoint),
+    /// Encoding the floor as a gif failed.
+    Encoding(gif::EncodingError),
+}
+
+impl fmt::Display for GenerationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenerationError::InvalidConfig(err) => {
+                f.write_fmt(format_args!("invalid generation config: {}", err))
+            }
+            GenerationError::InvalidDimensions { width, height } => f.write_fmt(format_args!(
+                "invalid floor dimensions {}x{}, both must be between {} and {}",
+                width,
+                height,
+                crate::MIN_FLOOR_SIZE,
+                crate::MAX_FLOOR_SIZE
+            )),
+            GenerationError::OutOfBounds(err) => {
+                f.write_fmt(format_args!("value out of bounds of the floor: {}", err))
+            }
+            GenerationError::UnreachableExit { entrance, exit } => f.write_fmt(format_args!(
+                "no path found from the entrance at {:?} to the exit at {:?}",
+                entrance, exit
+            )),
+            GenerationError::InvalidConnectionPath { start, end } => f.write_fmt(format_args!(
+                "connection path from {:?} to {:?} contains it's own start or end",
+                start, end
+            )),
+            GenerationError::TileOverwritten {
+                point,
+                expected,
+                found,
+            } => f.write_fmt(format_args!(
+                "expected {:?} at {:?}, found {:?}",
+                expected, point, found
+            )),
+            GenerationError::TooFewOpenTiles(count) => f.write_fmt(format_args!(
+                "expected at least 2 connected open tiles, found {}",
+                count
+            )),
+            GenerationError::InvalidEntrancesAndExits { entrances, exits } => {
+                f.write_fmt(format_args!(
+                    "expected exactly 1 entrance and 1 exit, found {} entrances and {} exits",
+                    entrances, exits
+                ))
+            }
+            GenerationError::UnreachableTile { entrance, tile } => f.write_fmt(format_args!(
+                "tile at {:?} can't be reached from the entrance at {:?}",
+                tile, entrance
+            )),
+            GenerationError::InvalidEntrance(entrance) => f.write_fmt(format_args!(
+                "requested entrance at {:?} is not inside the floor",
+                entrance
+            )),
+            GenerationError::EntranceMismatch { requested, found } => f.write_fmt(format_args!(
+                "entrance was requested at {:?}, but placed at {:?}",
+                requested, found
+            )),
+            GenerationError::ExitTooClose { distance, min } => f.write_fmt(format_args!(
+                "the furthest tile from the entrance is {} steps away, expected at least {}",
+                distance, min
+            )),
+            GenerationError::LockedOut(door) => f.write_fmt(format_args!(
+                "the key to the locked door at {:?} can't be reached without opening it",
+                door
+            )),
+            GenerationError::MissingChestContents(chest) => f.write_fmt(format_args!(
+                "the treasure chest at {:?} has no contents",
+                chest
+            )),
+            GenerationError::WfcContradiction(attempts) => f.write_fmt(format_args!(
+                "wave function collapse ran into a contradiction on all {} attempts",
+                attempts
+            )),
+            GenerationError::MaskedTileOpen(point) => f.write_fmt(format_args!(
+                "tile at {:?} is masked out, but isn't a wall",
+                point
+            )),
+            GenerationError::OutsideOfWorld(point) => {
+                f.write_fmt(format_args!("{:?} is outside of the world", point))
+            }
+            GenerationError::InvalidFloor(violation) => {
+                f.write_fmt(format_args!("invalid floor: {}", violation))
+            }
+            GenerationError::Encoding(err) => {
+                f.write_fmt(format_args!("failed to encode gif: {}", err))
+            }
+        }
+    }
+}
+
+impl Error for GenerationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GenerationError::InvalidConfig(err) => Some(err),
+            GenerationError::OutOfBounds(err) => Some(err),
+            GenerationError::InvalidFloor(err) => Some(err),
+            GenerationError::Encoding(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<GenerationConfigError> for GenerationError {
+    fn from(err: GenerationConfigError) -> Self {
+        GenerationError::InvalidConfig(err)
+    }
+}
+
+impl From<BoundedIntError> for GenerationError {
+    fn from(err: BoundedIntError) -> Self {
+        GenerationError::OutOfBounds(err)
+    }
+}
+
+impl From<gif::EncodingError> for GenerationError {
+    fn from(err: gif::EncodingError) -> Self {
+        GenerationError::Encoding(err)
+    }
+}
@@ -1,4 +1,3 @@
-#![warn(missing_docs)]
 #![allow(clippy::needless_continue)]
 #![warn(missing_docs, clippy::missing_docs_in_private_items)]
 
@@ -7,7 +6,7 @@
 //!
 //! # Examples
 //! ```rust
-//! use dungeon::{Dungeon, DungeonType};
+//! use dungeon::{Dungeon, DungeonType, GenerationConfig};
 //! use std::{
 //!     convert::TryInto,
 //!     num::NonZeroU16
@@ -18,21 +17,50 @@
 //!     100.try_into().unwrap(),
 //!     NonZeroU16::new(10).unwrap(),
 //!     DungeonType::Cave,
+//!     &GenerationConfig::default(),
 //!     false,
 //! );
 //! ```
 
 pub use crate::dungeon_tile::DungeonTile;
 use border::BorderId;
-pub use floor_builder::FloorBuilder;
+pub use distance_map::DistanceMap;
+pub use doors::DoorError;
+pub use entities::{Entities, Entity, EntityError, EntityId, EntityKind};
+pub use exploration_map::{ExplorationMap, ExplorationState};
+pub use floor_builder::{FloorBuilder, MAX_FLOOR_SIZE, MIN_FLOOR_SIZE};
+pub use floor_generator::{
+    BspGenerator, CellularAutomataGenerator, DrunkardsWalkGenerator, FloorAlgorithm,
+    FloorGenerator, FloorRequest, MazeGenerator, WfcGenerator, WfcSample, WfcSampleError,
+};
+pub use floor_mask::{FloorMask, MaskBitmap, MaskBitmapError};
+pub use generation_config::{
+    BuildConnectionIterations, ForestConfig, GenerationConfig, GenerationConfigError,
+    MonsterConfig, NoiseConfig, SmoothingPass, TreasureConfig, TreasurePlacement, VaultConfig,
+};
+pub use generation_error::GenerationError;
+pub use loot::{ChestId, Item, LoadLootTableError, LootEntry, LootTable, LootTableError, Rarity};
+pub use pathing::{Connectivity, PathOptions};
 pub use point::*;
+use rayon::prelude::*;
+pub use regions::{Passage, Region, RegionId, Regions};
+pub use schema::LoadDungeonError;
+pub use seed::Seed;
 use serde::{Deserialize, Serialize};
-use std::{convert::TryInto, fmt, num::NonZeroU16, usize};
+pub use stats::FloorStats;
+use std::{convert::TryInto, fmt, num::NonZeroU16};
+pub use validation::FloorViolation;
+pub use vault::{PlacedVault, Vault, VaultLayout, VaultLayoutError, VaultTile};
+pub use world::{ChunkId, World, WorldPoint};
 
 /// The various things a tile can be in a dungeon floor.
 ///
 /// See the type-level documentation for more information.
 pub mod dungeon_tile;
+/// Field of view and line of sight within a [`Floor`].
+///
+/// See the function-level documentation for more information.
+pub mod fov;
 /// A 1-dimensional type representing a 2-dimensional grid, indexable by a
 /// [`Point`].
 ///
@@ -49,20 +77,110 @@ mod border;
 /// See the type-level documentation for more information.
 mod connection_path;
 
+/// A grid of walking distances to a set of goals in a [`Floor`].
+///
+/// See the type-level documentation for more information.
+mod distance_map;
+
+/// The player's memory of which tiles of a [`Floor`] they have seen.
+///
+/// See the type-level documentation for more information.
+mod exploration_map;
+
+/// Opening, closing and unlocking the doors of a [`Floor`].
+///
+/// See the type-level documentation for more information.
+mod doors;
+
+/// Monsters, items and props that live on a [`Floor`] apart from it's tiles.
+///
+/// See the type-level documentation for more information.
+mod entities;
+
 /// Builder struct for a [`Floor`].
 ///
 /// See the type-level documentation for more information.
 mod floor_builder;
 
+/// The algorithms that can be used to generate a [`Floor`].
+///
+/// See the type-level documentation for more information.
+mod floor_generator;
+
+/// The shapes a [`Floor`] can be generated in.
+///
+/// See the type-level documentation for more information.
+mod floor_mask;
+
+/// The parameters used to generate a [`Floor`].
+///
+/// See the type-level documentation for more information.
+mod generation_config;
+
+/// Error returned when generating a [`Floor`] or [`Dungeon`] fails.
+///
+/// See the type-level documentation for more information.
+mod generation_error;
+
+/// Items, and the loot tables that fill the treasure chests of a [`Floor`].
+///
+/// See the type-level documentation for more information.
+mod loot;
+
+/// Finding paths through a [`Floor`].
+///
+/// See the type-level documentation for more information.
+mod pathing;
+
 /// A point somewhere in a [`Floor`].
 ///
 /// See the type-level documentation for more information.
 mod point;
 
+/// The regions of a [`Floor`] and the secret passages connecting them.
+///
+/// See the type-level documentation for more information.
+mod regions;
+
+/// Loading saved floors and dungeons, and migrating them from older versions.
+///
+/// See the type-level documentation for more information.
+mod schema;
+
+/// The seed used to deterministically generate a [`Dungeon`] or [`Floor`].
+///
+/// See the type-level documentation for more information.
+mod seed;
+
+/// Metrics describing the layout and difficulty of a [`Floor`].
+///
+/// See the type-level documentation for more information.
+mod stats;
+
+/// Checking that a [`Floor`] is sane.
+///
+/// See the type-level documentation for more information.
+mod validation;
+
+/// Hand-designed rooms stamped into the caves of a [`Floor`].
+///
+/// See the type-level documentation for more information.
+mod vault;
+
+/// Open worlds made of lazily generated, [`Floor`]-sized chunks.
+///
+/// See the type-level documentation for more information.
+mod world;
+
+/// Fixtures shared by the tests of every module.
+///
+/// See the function-level documentation for more information.
+#[cfg(test)]
+mod test_utils;
+
 pub use point::Point;
 pub use point_index::PointIndex;
 
-use crate::floor_builder::{MAX_FLOOR_SIZE, MIN_FLOOR_SIZE};
 use bounded_int::BoundedInt;
 
 /// A connection between two points on two different [`Border`]s.
@@ -82,7 +200,7 @@ struct Connection {
 ///
 /// # Examples
 /// ```rust
-/// use dungeon::{Dungeon, DungeonType};
+/// use dungeon::{Dungeon, DungeonType, GenerationConfig};
 /// use std::{
 ///     convert::TryInto,
 ///     num::NonZeroU16
@@ -93,14 +211,24 @@ struct Connection {
 ///     150.try_into().unwrap(),
 ///     NonZeroU16::new(10).unwrap(),
 ///     DungeonType::Cave,
+///     &GenerationConfig::default(),
 ///     false,
 /// );
 /// ```
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Dungeon {
-    /// The type of the dungeon. This only affects the way the dungeon
-    /// is presented aesthetically.
+    /// The version of the format the dungeon was saved in, which is always
+    /// [`Dungeon::SCHEMA_VERSION`] for dungeons created by this version of
+    /// the crate. Dungeons saved before the format was versioned are version
+    /// `0`. Load saved dungeons with [`Dungeon::from_json`] to migrate them.
+    #[serde(default)]
+    pub schema_version: u32,
+    /// The type of the dungeon. This affects both the way the floors are
+    /// generated and the way the dungeon is presented aesthetically.
     pub dungeon_type: DungeonType,
+    /// The seed the dungeon was generated with. Generating a dungeon with this
+    /// seed and the same parameters will produce the exact same dungeon.
+    pub seed: Seed,
     /// The floors of the dungeon. Will never be empty.
     // TODO: Maybe use https://docs.rs/vec1/1.8.0/vec1/? It seems to be fairly well maintained.
     pub floors: Vec<Floor>,
@@ -109,8 +237,10 @@ pub struct Dungeon {
 impl Dungeon {
     /// Encodes the dungeon to a gif, with each floor being a frame, and
     /// returns the image as bytes.
-    #[must_use]
-    pub fn to_gif(&self) -> Vec<u8> {
+    ///
+    /// # Errors
+    /// Errors with [`GenerationError::Encoding`] if the floors fail to encode.
+    pub fn to_gif(&self) -> Result<Vec<u8>, GenerationError> {
         use gif::{Encoder, Frame, Repeat};
         use std::borrow::Cow;
 
@@ -134,10 +264,9 @@ impl Dungeon {
                     .as_unbounded()
                     .try_into()
                     .unwrap(),
-                &DungeonTile::COLOR_MAP,
-            )
-            .unwrap();
-            encoder.set_repeat(Repeat::Infinite).unwrap();
+                DungeonTile::color_map(self.dungeon_type),
+            )?;
+            encoder.set_repeat(Repeat::Infinite)?;
             for floor in &self.floors {
                 let frame = Frame {
                     width: floor.width.as_unbounded().try_into().unwrap(),
@@ -152,15 +281,19 @@ impl Dungeon {
                     delay: 300,
                     ..Frame::default()
                 };
-                encoder.write_frame(&frame).unwrap();
+                encoder.write_frame(&frame)?;
             }
         }
-        image
+        Ok(image)
     }
 }
 
 /// A floor of a [`Dungeon`].
+///
+/// Deserializing a floor fails if it doesn't have exactly `width * height`
+/// tiles. Nothing else is checked; see [`Floor::validate`].
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "schema::SavedFloor")]
 pub struct Floor {
     /// width
     pub width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
@@ -168,18 +301,209 @@ pub struct Floor {
     pub height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
     /// data
     pub data: Vec<DungeonTile>,
+    /// The contents of the treasure chests, indexed by the
+    /// [`ChestId`] of every [`DungeonTile::TreasureChest`].
+    #[serde(default)]
+    pub chests: Vec<Vec<Item>>,
+    /// The points monsters should be spawned at, spread out over the open
+    /// areas of the caves. See [`MonsterConfig`].
+    #[serde(default)]
+    pub spawn_points: Vec<Point>,
+    /// The monsters, items and props on the floor. See [`Entities`].
+    #[serde(default)]
+    pub entities: Entities,
+    /// The hand-designed rooms stamped into the floor. See [`VaultConfig`].
+    #[serde(default)]
+    pub vaults: Vec<PlacedVault>,
 }
 
 impl Floor {
-    /// Creates a new floor with the given parameters.
+    /// Creates a new floor with the given parameters and a random seed.
+    ///
+    /// # Panics
+    /// Panics if generation fails. See [`Floor::try_new`] for a non-panicking
+    /// version of this function.
     #[must_use]
     pub fn new(
         id: FloorId,
+        dungeon_type: DungeonType,
         width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
         height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        config: &GenerationConfig,
         gif_output: bool,
     ) -> Self {
-        FloorBuilder::create(id, width, height, gif_output)
+        Self::with_seed(
+            id,
+            dungeon_type,
+            width,
+            height,
+            config,
+            Seed::random(),
+            gif_output,
+        )
+    }
+
+    /// Creates a new floor with the given parameters, generated from the
+    /// provided [`Seed`].
+    ///
+    /// The same id, dungeon type, dimensions, config and seed will always
+    /// produce the same floor. Note that a floor generated with this function
+    /// is identical to the floor with the same id in a [`Dungeon`] generated
+    /// with [`Dungeon::with_seed`] using the same parameters, unless the floors
+    /// are [linked](GenerationConfig::link_floors).
+    ///
+    /// # Panics
+    /// Panics if generation fails. See [`Floor::try_new`] for a non-panicking
+    /// version of this function.
+    #[must_use]
+    pub fn with_seed(
+        id: FloorId,
+        dungeon_type: DungeonType,
+        width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        config: &GenerationConfig,
+        seed: Seed,
+        gif_output: bool,
+    ) -> Self {
+        Self::try_new(id, dungeon_type, width, height, config, seed, gif_output)
+            .unwrap_or_else(|err| panic!("failed to generate floor {}: {}", id, err))
+    }
+
+    /// Tries to create a new floor with the given parameters, generated from
+    /// the provided [`Seed`].
+    ///
+    /// This is the same as [`Floor::with_seed`], except it returns an error
+    /// instead of panicking if generation fails.
+    ///
+    /// # Errors
+    /// Errors if the [`GenerationConfig`] is invalid, if the dimensions are
+    /// outside of [`MIN_FLOOR_SIZE`] and [`MAX_FLOOR_SIZE`], or if any stage of
+    /// the generation fails. See [`GenerationError`] for all the possible
+    /// errors.
+    pub fn try_new(
+        id: FloorId,
+        dungeon_type: DungeonType,
+        width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        config: &GenerationConfig,
+        seed: Seed,
+        gif_output: bool,
+    ) -> Result<Self, GenerationError> {
+        Self::try_with_generator(
+            config.algorithm_for(id),
+            &FloorRequest {
+                id,
+                dungeon_type,
+                width,
+                height,
+                config,
+                seed,
+                entrance: None,
+                gif_output,
+            },
+        )
+    }
+
+    /// Tries to create a new floor for the request using the provided
+    /// [`FloorGenerator`], instead of the one selected by
+    /// [`GenerationConfig::algorithms`].
+    ///
+    /// # Errors
+    /// Errors if the [`GenerationConfig`] is invalid, if the generator fails,
+    /// or if the generated floor doesn't have exactly one entrance and one
+    /// exit with every open tile reachable from the entrance, has a locked
+    /// door whose key can't be reached without opening it, or has a treasure
+    /// chest without contents. Also errors
+    /// if the request has an [entrance](FloorRequest::entrance) that is on or
+    /// outside the edge of the floor or is masked out by the
+    /// [mask](GenerationConfig::mask), or that the generator didn't place the
+    /// entrance at, and if any tile masked out by the mask isn't a wall.
+    pub fn try_with_generator<G: FloorGenerator + ?Sized>(
+        generator: &G,
+        request: &FloorRequest<'_>,
+    ) -> Result<Self, GenerationError> {
+        request.config.validate()?;
+
+        if let Some(entrance) = request.entrance {
+            let row = entrance.row.get().as_unbounded();
+            let column = entrance.column.get().as_unbounded();
+            if row < 1
+                || column < 1
+                || row >= request.height.as_unbounded() - 1
+                || column >= request.width.as_unbounded() - 1
+            {
+                return Err(GenerationError::InvalidEntrance(entrance));
+            }
+        }
+
+        let masked = request
+            .config
+            .mask
+            .solid_tiles(request.width, request.height);
+        if let Some(entrance) = request.entrance {
+            if masked[entrance.to_index(request.width.as_unbounded() as usize)] {
+                return Err(GenerationError::InvalidEntrance(entrance));
+            }
+        }
+
+        let floor = generator.generate(request)?;
+        floor_generator::check_invariants(&floor)?;
+
+        let open_masked_tile = floor
+            .data
+            .iter()
+            .zip(&masked)
+            .position(|(tile, &masked)| masked && !tile.is_wall());
+        if let Some(index) = open_masked_tile {
+            return Err(GenerationError::MaskedTileOpen(Point::from_index(
+                index,
+                floor.width.as_unbounded() as usize,
+            )?));
+        }
+
+        match (request.entrance, floor.entrance()) {
+            (Some(requested), Some(found)) if requested != found => {
+                Err(GenerationError::EntranceMismatch { requested, found })
+            }
+            _ => Ok(floor),
+        }
+    }
+
+    /// Returns the point of the floor's [`DungeonTile::Entrance`], or [`None`]
+    /// if it doesn't have one.
+    #[must_use]
+    pub fn entrance(&self) -> Option<Point> {
+        self.find_tile(DungeonTile::is_entrance)
+    }
+
+    /// Returns the point of the floor's [`DungeonTile::Exit`], or [`None`] if
+    /// it doesn't have one.
+    #[must_use]
+    pub fn exit(&self) -> Option<Point> {
+        self.find_tile(DungeonTile::is_exit)
+    }
+
+    /// Returns the point of the first tile that matches the predicate.
+    fn find_tile(&self, predicate: impl Fn(DungeonTile) -> bool) -> Option<Point> {
+        let width = self.width.as_unbounded() as usize;
+
+        self.data
+            .iter()
+            .position(|&tile| predicate(tile))
+            .and_then(|index| Point::from_index(index, width).ok())
+    }
+
+    /// Returns the contents of the treasure chest at the point, or [`None`] if
+    /// there is no chest there.
+    #[must_use]
+    pub fn chest(&self, point: Point) -> Option<&[Item]> {
+        match self.data.get(self.index(point)?) {
+            Some(DungeonTile::TreasureChest { contents }) => self
+                .chests
+                .get(usize::from(contents.get()))
+                .map(Vec::as_slice),
+            _ => None,
+        }
     }
 
     /// Returns an iterator over the tiles in the floor and their respective
@@ -201,6 +525,12 @@ impl Floor {
             })
     }
 
+    /// Returns the index of the point in [`Floor::data`], if it's inside the
+    /// floor.
+    pub(crate) fn index(&self, point: Point) -> Option<usize> {
+        point.index_in(self.width, self.height)
+    }
+
     /// Returns a refrence to the tile at the specified point.
     #[must_use]
     pub fn at(&self, point: Point) -> &DungeonTile {
@@ -218,6 +548,20 @@ impl Floor {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct FloorId(u16);
 
+impl FloorId {
+    /// Creates a new [`FloorId`] with the given id.
+    #[must_use]
+    pub const fn new(id: u16) -> Self {
+        Self(id)
+    }
+
+    /// Returns the inner value.
+    #[must_use]
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+}
+
 impl fmt::Display for FloorId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_fmt(format_args!("{}", self.0))
@@ -225,25 +569,223 @@ impl fmt::Display for FloorId {
 }
 
 impl Dungeon {
-    /// Creates a new dungeon with the specified paramaters.
+    /// Creates a new dungeon with the specified paramaters and a random seed.
+    ///
+    /// # Panics
+    /// Panics if generation fails. See [`Dungeon::try_new`] for a
+    /// non-panicking version of this function.
     #[must_use]
     pub fn new(
         height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
         width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
         floor_count: NonZeroU16,
         dungeon_type: DungeonType,
+        config: &GenerationConfig,
         gif_output: bool,
     ) -> Self {
-        Self {
+        Self::with_seed(
+            height,
+            width,
+            floor_count,
             dungeon_type,
-            floors: (0_u16..floor_count.get())
-                // .into_par_iter()
-                .map(|id| {
-                    println!("floor {}", id);
-                    FloorBuilder::create(FloorId(id), width, height, gif_output)
-                })
-                .collect(),
+            config,
+            Seed::random(),
+            gif_output,
+        )
+    }
+
+    /// Creates a new dungeon with the specified paramaters, generated from
+    /// the provided [`Seed`].
+    ///
+    /// The same parameters and seed will always produce the same dungeon.
+    ///
+    /// # Panics
+    /// Panics if generation fails. See [`Dungeon::try_new`] for a
+    /// non-panicking version of this function.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use dungeon::{Dungeon, DungeonType, GenerationConfig, Seed};
+    /// use std::{
+    ///     convert::TryInto,
+    ///     num::NonZeroU16
+    /// };
+    ///
+    /// let create = || Dungeon::with_seed(
+    ///     50.try_into().unwrap(),
+    ///     50.try_into().unwrap(),
+    ///     NonZeroU16::new(2).unwrap(),
+    ///     DungeonType::Cave,
+    ///     &GenerationConfig::default(),
+    ///     Seed::from("reproducible"),
+    ///     false,
+    /// );
+    ///
+    /// assert_eq!(create(), create());
+    /// ```
+    #[must_use]
+    pub fn with_seed(
+        height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        floor_count: NonZeroU16,
+        dungeon_type: DungeonType,
+        config: &GenerationConfig,
+        seed: Seed,
+        gif_output: bool,
+    ) -> Self {
+        Self::try_new(
+            height,
+            width,
+            floor_count,
+            dungeon_type,
+            config,
+            seed,
+            gif_output,
+        )
+        .unwrap_or_else(|err| panic!("failed to generate dungeon: {}", err))
+    }
+
+    /// Tries to create a new dungeon with the specified paramaters, generated
+    /// from the provided [`Seed`].
+    ///
+    /// This is the same as [`Dungeon::with_seed`], except it returns an error
+    /// instead of panicking if generation fails, so that generation can be
+    /// retried with a different seed.
+    ///
+    /// Unless the floors are [linked](GenerationConfig::link_floors), they
+    /// are generated in parallel. Every floor is generated from the seed and
+    /// it's own id alone, so the dungeon is exactly the same as if the floors
+    /// had been generated one after the other.
+    ///
+    /// # Errors
+    /// Errors if any of the floors fail to generate. See [`Floor::try_new`].
+    /// If several floors fail, the error of the first one is returned.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use dungeon::{Dungeon, DungeonType, GenerationConfig, GenerationError, Seed};
+    /// use std::{
+    ///     convert::TryInto,
+    ///     num::NonZeroU16
+    /// };
+    ///
+    /// let invalid_config = GenerationConfig {
+    ///     wall_percent_chance: 150,
+    ///     ..GenerationConfig::default()
+    /// };
+    ///
+    /// assert!(matches!(
+    ///     Dungeon::try_new(
+    ///         50.try_into().unwrap(),
+    ///         50.try_into().unwrap(),
+    ///         NonZeroU16::new(1).unwrap(),
+    ///         DungeonType::Cave,
+    ///         &invalid_config,
+    ///         Seed::random(),
+    ///         false,
+    ///     ),
+    ///     Err(GenerationError::InvalidConfig(_))
+    /// ));
+    /// ```
+    pub fn try_new(
+        height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        floor_count: NonZeroU16,
+        dungeon_type: DungeonType,
+        config: &GenerationConfig,
+        seed: Seed,
+        gif_output: bool,
+    ) -> Result<Self, GenerationError> {
+        if config.link_floors {
+            let mut floors = Vec::<Floor>::with_capacity(floor_count.get().into());
+
+            for id in 0_u16..floor_count.get() {
+                let request = FloorRequest {
+                    id: FloorId(id),
+                    dungeon_type,
+                    width,
+                    height,
+                    config,
+                    seed,
+                    entrance: floors.last().and_then(Floor::exit),
+                    gif_output,
+                };
+                floors.push(Floor::try_with_generator(
+                    config.algorithm_for(request.id),
+                    &request,
+                )?);
+            }
+
+            return Ok(Self {
+                schema_version: Self::SCHEMA_VERSION,
+                dungeon_type,
+                seed,
+                floors,
+            });
         }
+
+        // the results are collected before looking for errors, so that the
+        // same error is returned no matter which floor finishes first
+        let floors = (0_u16..floor_count.get())
+            .into_par_iter()
+            .map(|id| {
+                Floor::try_new(
+                    FloorId(id),
+                    dungeon_type,
+                    width,
+                    height,
+                    config,
+                    seed,
+                    gif_output,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            schema_version: Self::SCHEMA_VERSION,
+            dungeon_type,
+            seed,
+            floors: floors.into_iter().collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Returns where the player arrives on the next floor after taking the
+    /// exit of the floor with the provided id: the entrance of the next floor.
+    ///
+    /// If the floors are [linked](GenerationConfig::link_floors), this is the
+    /// same point as the exit that was taken.
+    ///
+    /// Returns [`None`] if the floor is the last one (or doesn't exist).
+    ///
+    /// # Examples
+    /// ```rust
+    /// use dungeon::{Dungeon, DungeonType, FloorId, GenerationConfig, Seed};
+    /// use std::{
+    ///     convert::TryInto,
+    ///     num::NonZeroU16
+    /// };
+    ///
+    /// let dungeon = Dungeon::with_seed(
+    ///     40.try_into().unwrap(),
+    ///     40.try_into().unwrap(),
+    ///     NonZeroU16::new(3).unwrap(),
+    ///     DungeonType::Cave,
+    ///     &GenerationConfig {
+    ///         link_floors: true,
+    ///         ..GenerationConfig::default()
+    ///     },
+    ///     Seed::new(0),
+    ///     false,
+    /// );
+    ///
+    /// assert_eq!(dungeon.arrival(FloorId::new(0)), dungeon.floors[0].exit());
+    /// assert_eq!(dungeon.arrival(FloorId::new(2)), None);
+    /// ```
+    #[must_use]
+    pub fn arrival(&self, from: FloorId) -> Option<Point> {
+        self.floors
+            .get(usize::from(from.get()) + 1)
+            .and_then(Floor::entrance)
     }
 
     /// Returns the dungeon as JSON.
@@ -258,27 +800,25 @@ impl Dungeon {
 }
 
 /// The different types of dungeon a [`Dungeon`] can be.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+///
+/// Each type has it's own recipe for generating floors, and it's own palette.
+/// See [`DungeonTile::color_map`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum DungeonType {
     /// A cave dungeon.
     ///
-    /// Should be rocky and have a 'gloomy' atmosphere to it.
+    /// Should be rocky and have a 'gloomy' atmosphere to it. Floors are made
+    /// of cellular automata caves connected by wide tunnels.
     Cave,
     /// A forest dungeon.
     ///
-    /// Should be lucious, overgrown, and *very* green.
+    /// Should be lucious, overgrown, and *very* green. Floors are made of open
+    /// clearings and dense clusters of trees, connected by narrow, winding
+    /// trails. See [`ForestConfig`].
     Forest,
 }
 
-/// Returns the distance between two [`Point`]s.
-// TODO: Make this an instance method on [`Point`].
-fn distance(from: Point, to: Point) -> f64 {
-    (((from.row.get().as_unbounded() - to.row.get().as_unbounded()).pow(2)
-        + (from.column.get().as_unbounded() - to.column.get().as_unbounded()).pow(2)) as f64)
-        .sqrt()
-}
-
 #[cfg(test)]
 mod test_dungeon {
     use std::{convert::TryInto, fs};
@@ -292,6 +832,7 @@ mod test_dungeon {
             50.try_into().unwrap(),
             NonZeroU16::new(10).unwrap(),
             DungeonType::Cave,
+            &GenerationConfig::default(),
             false,
         );
 
@@ -300,6 +841,108 @@ mod test_dungeon {
         fs::write("./test.json", contents_json).unwrap();
     }
 
+    #[test]
+    fn test_seeded_dungeon_creation_is_deterministic() {
+        let create = |seed: Seed| {
+            Dungeon::with_seed(
+                60.try_into().unwrap(),
+                80.try_into().unwrap(),
+                NonZeroU16::new(6).unwrap(),
+                DungeonType::Cave,
+                &GenerationConfig::default(),
+                seed,
+                false,
+            )
+        };
+
+        let first = create("tester report".into());
+        let second = create("tester report".into());
+
+        assert_eq!(first, second);
+        assert_eq!(first.to_json().unwrap(), second.to_json().unwrap());
+
+        // the floors are generated in parallel, but each floor generated on it's
+        // own is the same as that floor in the dungeon
+        for (id, floor) in (0..).zip(&first.floors) {
+            assert_eq!(
+                &Floor::with_seed(
+                    FloorId::new(id),
+                    DungeonType::Cave,
+                    80.try_into().unwrap(),
+                    60.try_into().unwrap(),
+                    &GenerationConfig::default(),
+                    "tester report".into(),
+                    false,
+                ),
+                floor
+            );
+        }
+
+        assert_ne!(first, create("a different seed".into()));
+    }
+
+    #[test]
+    fn test_forest_dungeon_creation() {
+        let create = |dungeon_type| {
+            Dungeon::with_seed(
+                60.try_into().unwrap(),
+                80.try_into().unwrap(),
+                NonZeroU16::new(2).unwrap(),
+                dungeon_type,
+                &GenerationConfig::default(),
+                Seed::new(7),
+                false,
+            )
+        };
+
+        let forest = create(DungeonType::Forest);
+
+        for floor in &forest.floors {
+            assert_eq!(floor.data.iter().filter(|t| t.is_entrance()).count(), 1);
+            assert_eq!(floor.data.iter().filter(|t| t.is_exit()).count(), 1);
+        }
+
+        // the dungeon type changes the generation recipe, not just the palette
+        assert_ne!(forest.floors, create(DungeonType::Cave).floors);
+    }
+
+    #[test]
+    fn test_linked_floors_line_up() {
+        let config = GenerationConfig {
+            algorithms: vec![
+                FloorAlgorithm::CellularAutomata,
+                FloorAlgorithm::Bsp(BspGenerator::default()),
+                FloorAlgorithm::DrunkardsWalk(DrunkardsWalkGenerator::default()),
+                FloorAlgorithm::Maze(MazeGenerator::default()),
+                FloorAlgorithm::Wfc(WfcGenerator::default()),
+            ],
+            link_floors: true,
+            ..GenerationConfig::default()
+        };
+
+        for dungeon_type in [DungeonType::Cave, DungeonType::Forest] {
+            for seed in 0..3 {
+                let dungeon = Dungeon::try_new(
+                    45.try_into().unwrap(),
+                    60.try_into().unwrap(),
+                    NonZeroU16::new(10).unwrap(),
+                    dungeon_type,
+                    &config,
+                    Seed::new(seed),
+                    false,
+                )
+                .unwrap_or_else(|err| panic!("seed {}: {}", seed, err));
+
+                for (id, floors) in dungeon.floors.windows(2).enumerate() {
+                    let exit = floors[0].exit().unwrap();
+                    assert_eq!(floors[1].entrance(), Some(exit));
+                    assert_eq!(dungeon.arrival(FloorId::new(id as u16)), Some(exit));
+                }
+                assert_eq!(dungeon.arrival(FloorId::new(9)), None);
+            }
+        }
+    }
+
     // #[test]
     // pub(crate) fn test_border_finding() {
     //     let floor_builder = FloorBuilder::<Blank>::blank(50, 100);
@@ -0,0 +1,492 @@
+use std::{error::Error, fmt};
+
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::FloorId;
+
+/// An item that can be found in a treasure chest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Item {
+    /// The name of the item.
+    pub name: String,
+    /// How rare the item is.
+    pub rarity: Rarity,
+    /// How many of the item there are in the stack. Defaults to `1`.
+    #[serde(default = "one")]
+    pub quantity: u16,
+}
+
+/// Returns `1`, the default [`Item::quantity`].
+const fn one() -> u16 {
+    1
+}
+
+/// How rare an [`Item`] is. The deeper a floor is and the more hidden a chest
+/// is, the more likely the rarer items are to be found in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Rarity {
+    /// Found everywhere.
+    Common,
+    /// Found often enough.
+    Uncommon,
+    /// Hard to come by.
+    Rare,
+    /// Very hard to come by.
+    Epic,
+    /// Almost never found.
+    Legendary,
+}
+
+impl Rarity {
+    /// Returns the tier of the rarity, starting at `0` for
+    /// [`Rarity::Common`].
+    #[must_use]
+    pub const fn tier(self) -> i32 {
+        match self {
+            Rarity::Common => 0,
+            Rarity::Uncommon => 1,
+            Rarity::Rare => 2,
+            Rarity::Epic => 3,
+            Rarity::Legendary => 4,
+        }
+    }
+}
+
+/// An item of a [`LootTable`], along with how likely it is to be picked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LootEntry {
+    /// The item.
+    pub item: Item,
+    /// The base weight of the item; an item with twice the weight of another
+    /// is twice as likely to be picked, before the weights are scaled by
+    /// rarity (see [`LootTable::weights`]).
+    pub weight: u32,
+}
+
+/// The id of the contents of a [`DungeonTile::TreasureChest`]. See
+/// [`Floor::chest`](crate::Floor::chest).
+///
+/// [`DungeonTile::TreasureChest`]: crate::DungeonTile::TreasureChest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ChestId(u16);
+
+impl ChestId {
+    /// Creates a new [`ChestId`] with the given id.
+    #[must_use]
+    pub const fn new(id: u16) -> Self {
+        Self(id)
+    }
+
+    /// Returns the inner value.
+    #[must_use]
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+}
+
+/// A weighted, data-driven table of the items that treasure chests are filled
+/// with. Loot tables can be loaded from JSON with [`LootTable::from_json`] or
+/// from RON with [`LootTable::from_ron`].
+///
+/// Every chest gets between [`min_items`](LootTable::min_items) and
+/// [`max_items`](LootTable::max_items) items, each one picked at random from
+/// the [entries](LootTable::entries) by their [weights](LootTable::weights).
+///
+/// # Examples
+/// ```rust
+/// use dungeon::{FloorId, LootTable, Rarity};
+///
+/// let table = LootTable::from_ron(
+///     r#"(
+///         min_items: 1,
+///         max_items: 2,
+///         depth_bonus: 0.1,
+///         hidden_bonus: 0.5,
+///         entries: [
+///             (item: (name: "Torch", rarity: Common), weight: 10),
+///             (item: (name: "Crown", rarity: Legendary), weight: 1),
+///         ],
+///     )"#,
+/// )
+/// .unwrap();
+///
+/// // crowns are more likely to be found deep down, in a hidden chest
+/// let shallow = table.weights(FloorId::new(0), 0);
+/// let deep = table.weights(FloorId::new(20), 2);
+/// assert!(deep[1] / deep[0] > shallow[1] / shallow[0]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LootTable {
+    /// The minimum amount of items in a chest, inclusive.
+    pub min_items: u8,
+    /// The maximum amount of items in a chest, inclusive.
+    pub max_items: u8,
+    /// How much more likely the rarer items get on every floor further down.
+    pub depth_bonus: f64,
+    /// How much more likely the rarer items get for every secret door between
+    /// the entrance and the chest.
+    pub hidden_bonus: f64,
+    /// The items that can be picked.
+    pub entries: Vec<LootEntry>,
+}
+
+impl Default for LootTable {
+    fn default() -> Self {
+        let entry = |name: &str, rarity, quantity, weight| LootEntry {
+            item: Item {
+                name: name.to_string(),
+                rarity,
+                quantity,
+            },
+            weight,
+        };
+
+        Self {
+            min_items: 1,
+            max_items: 3,
+            depth_bonus: 0.1,
+            hidden_bonus: 0.5,
+            entries: vec![
+                entry("Gold Coins", Rarity::Common, 25, 50),
+                entry("Healing Potion", Rarity::Common, 1, 30),
+                entry("Torch", Rarity::Common, 3, 20),
+                entry("Silver Ring", Rarity::Uncommon, 1, 12),
+                entry("Steel Sword", Rarity::Uncommon, 1, 10),
+                entry("Enchanted Scroll", Rarity::Rare, 1, 5),
+                entry("Mithril Armor", Rarity::Epic, 1, 2),
+                entry("Dragon's Eye", Rarity::Legendary, 1, 1),
+            ],
+        }
+    }
+}
+
+impl LootTable {
+    /// Loads a loot table from JSON, and checks that it's valid.
+    ///
+    /// # Errors
+    /// Errors if the JSON can't be parsed, or if the table isn't valid (see
+    /// [`LootTable::validate`]).
+    pub fn from_json(json: &str) -> Result<Self, LoadLootTableError> {
+        let table: Self = serde_json::from_str(json).map_err(LoadLootTableError::Json)?;
+        table.validate()?;
+        Ok(table)
+    }
+
+    /// Loads a loot table from RON, and checks that it's valid.
+    ///
+    /// # Errors
+    /// Errors if the RON can't be parsed, or if the table isn't valid (see
+    /// [`LootTable::validate`]).
+    pub fn from_ron(ron: &str) -> Result<Self, LoadLootTableError> {
+        let table: Self = ron::from_str(ron).map_err(LoadLootTableError::Ron)?;
+        table.validate()?;
+        Ok(table)
+    }
+
+    /// Checks that the table can be rolled.
+    ///
+    /// # Errors
+    /// Errors if there are no entries with a weight above `0`, if
+    /// [`min_items`](LootTable::min_items) is greater than
+    /// [`max_items`](LootTable::max_items), if one of the bonuses isn't a
+    /// finite, non-negative number, or if the [weights](LootTable::weights)
+    /// of a chest on the deepest floor, behind as many secret doors as there
+    /// can be, don't add up to a finite number.
+    pub fn validate(&self) -> Result<(), LootTableError> {
+        if self.entries.iter().all(|entry| entry.weight == 0) {
+            return Err(LootTableError::NoEntries);
+        }
+
+        if self.min_items > self.max_items {
+            return Err(LootTableError::ItemRange {
+                min: self.min_items,
+                max: self.max_items,
+            });
+        }
+
+        for (name, value) in [
+            ("depth bonus", self.depth_bonus),
+            ("hidden bonus", self.hidden_bonus),
+        ] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(LootTableError::Bonus { name, value });
+            }
+        }
+
+        // the weights only grow the deeper and the more hidden the chest is
+        let max_total = self
+            .weights(FloorId::new(u16::MAX), u16::MAX)
+            .iter()
+            .sum::<f64>();
+        if !max_total.is_finite() {
+            return Err(LootTableError::WeightOverflow);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the weight of every entry for a chest on the provided floor,
+    /// behind the provided amount of secret doors.
+    ///
+    /// The base weight of every entry is multiplied by `(1 + depth *
+    /// depth_bonus + hidden * hidden_bonus)` once for every
+    /// [tier](Rarity::tier) of it's rarity, so common items keep their weight
+    /// while the rarer items get more and more likely.
+    #[must_use]
+    pub fn weights(&self, floor: FloorId, hidden: u16) -> Vec<f64> {
+        let bonus =
+            1.0 + f64::from(floor.get()) * self.depth_bonus + f64::from(hidden) * self.hidden_bonus;
+
+        self.entries
+            .iter()
+            .map(|entry| f64::from(entry.weight) * bonus.powi(entry.item.rarity.tier()))
+            .collect()
+    }
+
+    /// Picks the items for a chest on the provided floor, behind the provided
+    /// amount of secret doors. See [`LootTable::weights`].
+    ///
+    /// # Panics
+    /// Panics if the table isn't [valid](LootTable::validate).
+    pub fn roll(&self, rng: &mut impl Rng, floor: FloorId, hidden: u16) -> Vec<Item> {
+        let weights = WeightedIndex::new(self.weights(floor, hidden))
+            .expect("the loot table should be validated before it's rolled");
+
+        (0..rng.gen_range(self.min_items..=self.max_items))
+            .map(|_| self.entries[weights.sample(rng)].item.clone())
+            .collect()
+    }
+}
+
+/// Error returned from [`LootTable::validate`] when a [`LootTable`] is
+/// invalid.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LootTableError {
+    /// There were no entries with a weight above `0`.
+    NoEntries,
+    /// [`LootTable::min_items`] was greater than [`LootTable::max_items`].
+    ItemRange {
+        /// The minimum amount of items.
+        min: u8,
+        /// The maximum amount of items.
+        max: u8,
+    },
+    /// One of the bonuses wasn't a finite, non-negative number.
+    Bonus {
+        /// The name of the bonus.
+        name: &'static str,
+        /// The invalid value.
+        value: f64,
+    },
+    /// The bonuses were so large that the weights of a deep or well hidden
+    /// chest added up to more than can be represented.
+    WeightOverflow,
+}
+
+impl fmt::Display for LootTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LootTableError::NoEntries => {
+                f.write_str("loot table must have at least one entry with a weight above 0")
+            }
+            LootTableError::ItemRange { min, max } => f.write_fmt(format_args!(
+                "minimum items ({}) must not be greater than the maximum ({})",
+                min, max
+            )),
+            LootTableError::Bonus { name, value } => f.write_fmt(format_args!(
+                "loot {} must be a finite, non-negative number, got {}",
+                name, value
+            )),
+            LootTableError::WeightOverflow => {
+                f.write_str("loot bonuses are too large, the weights of deep chests overflow")
+            }
+        }
+    }
+}
+
+impl Error for LootTableError {}
+
+/// Error returned when loading a [`LootTable`] fails.
+#[derive(Debug)]
+pub enum LoadLootTableError {
+    /// The JSON couldn't be parsed.
+    Json(serde_json::Error),
+    /// The RON couldn't be parsed.
+    Ron(ron::Error),
+    /// The table was parsed, but isn't valid.
+    Invalid(LootTableError),
+}
+
+impl fmt::Display for LoadLootTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadLootTableError::Json(err) => {
+                f.write_fmt(format_args!("failed to parse loot table json: {}", err))
+            }
+            LoadLootTableError::Ron(err) => {
+                f.write_fmt(format_args!("failed to parse loot table ron: {}", err))
+            }
+            LoadLootTableError::Invalid(err) => {
+                f.write_fmt(format_args!("invalid loot table: {}", err))
+            }
+        }
+    }
+}
+
+impl Error for LoadLootTableError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadLootTableError::Json(err) => Some(err),
+            LoadLootTableError::Ron(err) => Some(err),
+            LoadLootTableError::Invalid(err) => Some(err),
+        }
+    }
+}
+
+impl From<LootTableError> for LoadLootTableError {
+    fn from(err: LootTableError) -> Self {
+        LoadLootTableError::Invalid(err)
+    }
+}
+
+#[cfg(test)]
+mod test_loot {
+    use std::convert::TryInto;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::{DungeonType, Floor, GenerationConfig, GenerationConfigError, Seed};
+
+    #[test]
+    fn test_default_table_rolls_on_the_deepest_floor() {
+        let table = LootTable::default();
+        assert_eq!(table.validate(), Ok(()));
+
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let items = table.roll(&mut rng, FloorId::new(u16::MAX), u16::MAX);
+        assert!((table.min_items..=table.max_items).contains(&(items.len() as u8)));
+    }
+
+    #[test]
+    fn test_overflowing_weights_are_invalid() {
+        let table = LootTable {
+            depth_bonus: 1e100,
+            ..LootTable::default()
+        };
+
+        assert_eq!(table.validate(), Err(LootTableError::WeightOverflow));
+    }
+
+    #[test]
+    fn test_loot_tables() {
+        let json = r#"{
+            "min_items": 2,
+            "max_items": 2,
+            "depth_bonus": 1.0,
+            "hidden_bonus": 0.0,
+            "entries": [
+                { "item": { "name": "Rope", "rarity": "Common", "quantity": 2 }, "weight": 3 },
+                { "item": { "name": "Lantern", "rarity": "Rare" }, "weight": 1 }
+            ]
+        }"#;
+        let table = LootTable::from_json(json).unwrap();
+        assert_eq!(table.entries[1].item.quantity, 1);
+
+        let ron = r#"(
+            min_items: 2,
+            max_items: 2,
+            depth_bonus: 1.0,
+            hidden_bonus: 0.0,
+            entries: [
+                (item: (name: "Rope", rarity: Common, quantity: 2), weight: 3),
+                (item: (name: "Lantern", rarity: Rare), weight: 1),
+            ],
+        )"#;
+        assert_eq!(LootTable::from_ron(ron).unwrap(), table);
+
+        // common items keep their weight, rare ones get more likely the deeper
+        // the floor is
+        assert_eq!(table.weights(FloorId::new(0), 0), vec![3.0, 1.0]);
+        assert_eq!(table.weights(FloorId::new(1), 5), vec![3.0, 4.0]);
+
+        assert!(matches!(
+            LootTable::from_json("{}"),
+            Err(LoadLootTableError::Json(_))
+        ));
+        assert!(matches!(
+            LootTable::from_ron(&ron.replace("min_items: 2", "min_items: 3")),
+            Err(LoadLootTableError::Invalid(LootTableError::ItemRange {
+                min: 3,
+                max: 2
+            }))
+        ));
+        assert!(matches!(
+            LootTable::from_ron(
+                &ron.replace("weight: 3", "weight: 0")
+                    .replace("weight: 1", "weight: 0")
+            ),
+            Err(LoadLootTableError::Invalid(LootTableError::NoEntries))
+        ));
+
+        let mut config = GenerationConfig::default();
+        config.treasure.loot.depth_bonus = f64::NAN;
+        assert!(matches!(
+            config.validate(),
+            Err(GenerationConfigError::LootTable(
+                LootTableError::Bonus { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_treasure_chests_are_filled() {
+        let mut config = GenerationConfig::default();
+        config.treasure.loot = LootTable::from_ron(
+            r#"(
+                min_items: 1,
+                max_items: 4,
+                depth_bonus: 0.5,
+                hidden_bonus: 1.0,
+                entries: [
+                    (item: (name: "Pebble", rarity: Common), weight: 100),
+                    (item: (name: "Crown", rarity: Legendary), weight: 1),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        // deep enough for the depth bonus to make a difference
+        let floor = Floor::with_seed(
+            FloorId::new(3),
+            DungeonType::Cave,
+            60.try_into().unwrap(),
+            60.try_into().unwrap(),
+            &config,
+            Seed::new(0),
+            false,
+        );
+
+        let chests = floor
+            .iter_points_and_tiles()
+            .filter(|(_, tile)| tile.is_treasure_chest())
+            .map(|(point, _)| floor.chest(point).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(chests.len(), floor.chests.len());
+        assert!(!chests.is_empty());
+        for items in chests {
+            assert!((1..=4).contains(&items.len()));
+            assert!(items
+                .iter()
+                .all(|item| item.name == "Pebble" || item.rarity == Rarity::Legendary));
+        }
+        assert_eq!(floor.chest(floor.entrance().unwrap()), None);
+
+        // the contents are saved with the floor
+        let json = serde_json::to_string(&floor).unwrap();
+        assert_eq!(serde_json::from_str::<Floor>(&json).unwrap(), floor);
+    }
+}
@@ -0,0 +1,310 @@
+use std::fmt;
+
+use pathfinding::prelude::astar;
+
+use crate::{DungeonTile, Floor, Point};
+
+/// Which of the tiles around a tile can be stepped to directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Only the 4 tiles directly above, below, left and right.
+    Four,
+    /// The 4 orthogonal tiles, plus the 4 diagonal tiles. Diagonal steps cost
+    /// the same as orthogonal ones, but can't cut the corner of a tile that
+    /// can't be walked over.
+    Eight,
+}
+
+/// The options used when finding a path through a [`Floor`] with
+/// [`Floor::path`].
+///
+/// # Examples
+/// ```rust
+/// use dungeon::{Connectivity, DungeonTile, PathOptions};
+///
+/// // avoid secret passages unless there's no other way
+/// let cost = |_, tile: DungeonTile| Some(if tile.is_secret_passage() { 10 } else { 1 });
+///
+/// let options = PathOptions {
+///     connectivity: Connectivity::Eight,
+///     cost: Some(&cost),
+///     ..PathOptions::default()
+/// };
+/// ```
+#[derive(Clone, Copy)]
+pub struct PathOptions<'a> {
+    /// Which tiles can be stepped to from each tile.
+    pub connectivity: Connectivity,
+    /// Whether or not closed [`DungeonTile::SecretDoor`]s can be walked
+    /// through. Open secret doors can always be walked through.
+    pub secret_doors_passable: bool,
+    /// The cost of stepping onto a tile. Returning [`None`] makes the tile
+    /// impassable. Only called for tiles that could otherwise be walked over;
+    /// [solid](DungeonTile::is_solid) tiles are never walkable.
+    ///
+    /// If [`None`], every step costs `1`.
+    pub cost: Option<&'a dyn Fn(Point, DungeonTile) -> Option<u32>>,
+}
+
+impl Default for PathOptions<'_> {
+    fn default() -> Self {
+        Self {
+            connectivity: Connectivity::Four,
+            secret_doors_passable: false,
+            cost: None,
+        }
+    }
+}
+
+impl fmt::Debug for PathOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PathOptions")
+            .field("connectivity", &self.connectivity)
+            .field("secret_doors_passable", &self.secret_doors_passable)
+            .field("cost", &self.cost.map(|_| ".."))
+            .finish()
+    }
+}
+
+impl Floor {
+    /// Finds the cheapest walkable path between the two points, returning the
+    /// points along the path (including both `from` and `to`) and it's total
+    /// cost.
+    ///
+    /// Returns [`None`] if there is no path, or if either point is outside of
+    /// the floor or can't be walked over.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use dungeon::{DungeonType, Floor, FloorId, GenerationConfig, PathOptions, Seed};
+    /// use std::convert::TryInto;
+    ///
+    /// let floor = Floor::with_seed(
+    ///     FloorId::new(0),
+    ///     DungeonType::Cave,
+    ///     50.try_into().unwrap(),
+    ///     50.try_into().unwrap(),
+    ///     &GenerationConfig::default(),
+    ///     Seed::new(0),
+    ///     false,
+    /// );
+    ///
+    /// let (entrance, exit) = (floor.entrance().unwrap(), floor.exit().unwrap());
+    /// let options = PathOptions {
+    ///     secret_doors_passable: true,
+    ///     ..PathOptions::default()
+    /// };
+    ///
+    /// let (path, cost) = floor.path(entrance, exit, &options).unwrap();
+    /// assert_eq!(path.first(), Some(&entrance));
+    /// assert_eq!(path.last(), Some(&exit));
+    /// assert_eq!(cost as usize, path.len() - 1);
+    /// ```
+    #[must_use]
+    pub fn path(
+        &self,
+        from: Point,
+        to: Point,
+        options: &PathOptions<'_>,
+    ) -> Option<(Vec<Point>, u32)> {
+        let width = self.width.as_unbounded() as usize;
+        let from = self.walkable_index(from, options)?;
+        let to = self.walkable_index(to, options)?;
+
+        let (to_row, to_column) = ((to / width) as isize, (to % width) as isize);
+        // every step costs at least 1 unless the costs are custom, so the
+        // amount of steps is a lower bound for the cost
+        let heuristic = |&index: &usize| -> u32 {
+            if options.cost.is_some() {
+                return 0;
+            }
+            let rows = ((index / width) as isize - to_row).unsigned_abs() as u32;
+            let columns = ((index % width) as isize - to_column).unsigned_abs() as u32;
+            match options.connectivity {
+                Connectivity::Four => rows + columns,
+                Connectivity::Eight => rows.max(columns),
+            }
+        };
+
+        let (path, cost) = astar(
+            &from,
+            |&index| self.steps(index, options),
+            heuristic,
+            |&index| index == to,
+        )?;
+
+        let path = path
+            .into_iter()
+            .map(|index| Point::from_index(index, width))
+            .collect::<Result<_, _>>()
+            .ok()?;
+
+        Some((path, cost))
+    }
+
+    /// Returns the index of the point if it's inside the floor and can be
+    /// walked over.
+    fn walkable_index(&self, point: Point, options: &PathOptions<'_>) -> Option<usize> {
+        let (row, column) = (
+            point.row.get().as_unbounded(),
+            point.column.get().as_unbounded(),
+        );
+        if row >= self.height.as_unbounded() || column >= self.width.as_unbounded() {
+            return None;
+        }
+
+        let index = point.to_index(self.width.as_unbounded() as usize);
+        self.step_cost(index, options).map(|_| index)
+    }
+
+    /// Returns the cost of stepping onto the tile at the index, or [`None`] if
+    /// it can't be walked over.
+    fn step_cost(&self, index: usize, options: &PathOptions<'_>) -> Option<u32> {
+        let tile = self.data[index];
+        let passable = match tile {
+            DungeonTile::SecretDoor { is_open, .. } => is_open || options.secret_doors_passable,
+            _ => !tile.is_solid(),
+        };
+
+        match (passable, options.cost) {
+            (false, _) => None,
+            (true, None) => Some(1),
+            (true, Some(cost)) => Point::from_index(index, self.width.as_unbounded() as usize)
+                .ok()
+                .and_then(|point| cost(point, tile)),
+        }
+    }
+
+    /// Returns the tiles that can be stepped to from the tile at the index,
+    /// along with the cost of stepping onto them.
+    fn steps(&self, index: usize, options: &PathOptions<'_>) -> Vec<(usize, u32)> {
+        let width = self.width.as_unbounded() as isize;
+        let height = self.height.as_unbounded() as isize;
+        let (row, column) = (index as isize / width, index as isize % width);
+
+        let neighbour = |d_row: isize, d_column: isize| {
+            let (row, column) = (row + d_row, column + d_column);
+            ((0..height).contains(&row) && (0..width).contains(&column))
+                .then(|| (row * width + column) as usize)
+        };
+        let cost = |d_row, d_column| {
+            neighbour(d_row, d_column)
+                .and_then(|next| self.step_cost(next, options).map(|cost| (next, cost)))
+        };
+
+        let mut steps = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .iter()
+            .filter_map(|&(d_row, d_column)| cost(d_row, d_column))
+            .collect::<Vec<_>>();
+
+        if options.connectivity == Connectivity::Eight {
+            steps.extend(
+                [(-1, -1), (-1, 1), (1, -1), (1, 1)]
+                    .iter()
+                    // no cutting corners
+                    .filter(|&&(d_row, d_column)| {
+                        cost(d_row, 0).is_some() && cost(0, d_column).is_some()
+                    })
+                    .filter_map(|&(d_row, d_column)| cost(d_row, d_column)),
+            );
+        }
+
+        steps
+    }
+}
+
+#[cfg(test)]
+mod test_pathing {
+    use super::*;
+    use crate::test_utils::{floor_from_rows, point};
+
+    #[test]
+    fn test_paths_follow_the_connectivity() {
+        let floor = floor_from_rows(&[
+            "##########",
+            "#........#",
+            "#........#",
+            "#........#",
+            "#.....#..#",
+            "#....#...#",
+            "#........#",
+            "#........#",
+            "#........#",
+            "##########",
+        ]);
+
+        let four = PathOptions::default();
+        let eight = PathOptions {
+            connectivity: Connectivity::Eight,
+            ..PathOptions::default()
+        };
+
+        let (path, cost) = floor.path(point(1, 1), point(8, 8), &four).unwrap();
+        assert_eq!((path.len(), cost), (15, 14));
+        assert_eq!(path.first(), Some(&point(1, 1)));
+        assert_eq!(path.last(), Some(&point(8, 8)));
+
+        // the straight diagonal is blocked, and the one next to it would cut
+        // between the two walls in the middle
+        let (path, cost) = floor.path(point(1, 1), point(8, 8), &eight).unwrap();
+        assert_eq!(cost, 9);
+        assert!(!path
+            .windows(2)
+            .any(|step| step == [point(4, 5), point(5, 6)] || step == [point(5, 6), point(4, 5)]));
+
+        assert_eq!(
+            floor.path(point(1, 1), point(1, 1), &four),
+            Some((vec![point(1, 1)], 0))
+        );
+        assert_eq!(floor.path(point(1, 1), point(0, 0), &four), None);
+        assert_eq!(floor.path(point(1, 1), point(12, 3), &four), None);
+    }
+
+    #[test]
+    fn test_paths_respect_secret_doors_and_custom_costs() {
+        let floor = floor_from_rows(&[
+            "##########",
+            "#...#....#",
+            "#...D....#",
+            "#...#....#",
+            "#...#....#",
+            "#...d....#",
+            "#...#....#",
+            "#...#....#",
+            "#...#....#",
+            "##########",
+        ]);
+
+        let closed = PathOptions::default();
+        let (path, _) = floor.path(point(2, 2), point(2, 6), &closed).unwrap();
+        assert!(path.contains(&point(5, 4)));
+
+        let passable = PathOptions {
+            secret_doors_passable: true,
+            ..PathOptions::default()
+        };
+        let (path, cost) = floor.path(point(2, 2), point(2, 6), &passable).unwrap();
+        assert!(path.contains(&point(2, 4)));
+        assert_eq!(cost, 4);
+
+        // make the closed door very expensive, and the open one impassable
+        let cost = |point: Point, tile: DungeonTile| match tile {
+            DungeonTile::SecretDoor { is_open: true, .. } => None,
+            DungeonTile::SecretDoor { .. } => Some(100),
+            _ => Some(point.row.get().as_unbounded() as u32 % 2 + 1),
+        };
+        let custom = PathOptions {
+            secret_doors_passable: true,
+            cost: Some(&cost),
+            ..PathOptions::default()
+        };
+        let (path, _) = floor.path(point(2, 2), point(2, 6), &custom).unwrap();
+        assert!(path.contains(&point(2, 4)));
+
+        let blocked = PathOptions {
+            cost: Some(&cost),
+            ..PathOptions::default()
+        };
+        assert_eq!(floor.path(point(2, 2), point(2, 6), &blocked), None);
+    }
+}
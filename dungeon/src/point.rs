@@ -1,14 +1,20 @@
-use std::ops::{Add, Sub};
+use std::{
+    collections::VecDeque,
+    convert::TryInto,
+    ops::{Add, Sub},
+};
 
 use bounded_int::{
     ops::{BoundedIntOverflowError, BoundedIntUnderflowError},
-    BoundedInt,
+    BoundedInt, BoundedIntError,
 };
 
-use crate::floor_builder::MAX_FLOOR_SIZE;
+use serde::{Deserialize, Serialize};
+
+use crate::floor_builder::{MAX_FLOOR_SIZE, MIN_FLOOR_SIZE};
 
 /// A point somewhere in a [`Floor`](crate::Floor).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Point {
     /// How many columns across the point is.
     ///
@@ -23,24 +29,116 @@ pub struct Point {
 /// Wrapper type around a [`BoundedInt`] that represents the row position of a
 /// [`Point`]. Note that the [`BoundedInt`] is bound on the maximum and minimum
 /// that a point can be, not the floor size itself.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Row(pub(super) BoundedInt<0, { MAX_FLOOR_SIZE }>);
 
 /// Wrapper type around a [`BoundedInt`] that represents the column position of
 /// a [`Point`]. Note that the [`BoundedInt`] is bound on the maximum and
 /// minimum that a point can be, not the floor size itself.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Column(pub(super) BoundedInt<0, { MAX_FLOOR_SIZE }>);
 
 impl Point {
     /// Returns the euclidean distance between two points.
-    fn distance(self, to: Point) -> f64 {
+    pub(crate) fn distance(self, to: Point) -> f64 {
         (((self.row.get().as_unbounded() - to.row.get().as_unbounded()).pow(2)
             + (self.column.get().as_unbounded() - to.column.get().as_unbounded()).pow(2))
             as f64)
             .sqrt()
     }
 
+    /// Returns the point at the index of a row-major grid with the provided
+    /// width. The inverse of [`PointIndex::at`](crate::PointIndex::at).
+    pub(crate) fn from_index(index: usize, width: usize) -> Result<Self, BoundedIntError> {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+        Ok(Self {
+            row: Row::new(((index / width) as i32).try_into()?),
+            column: Column::new(((index % width) as i32).try_into()?),
+        })
+    }
+
+    /// Returns the index of the point in a row-major grid with the provided
+    /// width. The inverse of [`Point::from_index`].
+    pub(crate) fn to_index(self, width: usize) -> usize {
+        self.row.get().as_unbounded() as usize * width + self.column.get().as_unbounded() as usize
+    }
+
+    /// Returns the index of the point in a row-major grid with the provided
+    /// width and height, if it's inside of it.
+    pub(crate) fn index_in(
+        self,
+        width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+    ) -> Option<usize> {
+        let row = self.row.get().as_unbounded();
+        let column = self.column.get().as_unbounded();
+
+        (row < height.as_unbounded() && column < width.as_unbounded())
+            .then(|| self.to_index(width.as_unbounded() as usize))
+    }
+
+    /// Returns the indices of the tiles up, down, left and right of the tile
+    /// at the index of a row-major grid with the provided width and height,
+    /// leaving out the ones outside of the grid.
+    pub(crate) fn neighbour_indices(
+        index: usize,
+        width: usize,
+        height: usize,
+    ) -> impl Iterator<Item = usize> {
+        let (row, column) = (index / width, index % width);
+
+        IntoIterator::into_iter([
+            (row > 0).then(|| index - width),
+            (row + 1 < height).then(|| index + width),
+            (column > 0).then(|| index - 1),
+            (column + 1 < width).then(|| index + 1),
+        ])
+        .flatten()
+    }
+
+    /// Finds the groups of tiles of a row-major grid with the provided width
+    /// and height that are connected up, down, left and right, out of the
+    /// tiles at the indices `belongs` returns `true` for. The groups are
+    /// numbered in the order their first tile comes in `starts`, and list
+    /// their tiles in the order they were reached in, starting from that one.
+    ///
+    /// Returns the number of the group every tile is in, if any, along with
+    /// the groups.
+    pub(crate) fn connected_groups(
+        width: usize,
+        height: usize,
+        starts: impl IntoIterator<Item = usize>,
+        belongs: impl Fn(usize) -> bool,
+    ) -> (Vec<Option<usize>>, Vec<Vec<usize>>) {
+        let mut labels = vec![None; width * height];
+        let mut groups = vec![];
+
+        for start in starts {
+            if labels[start].is_some() || !belongs(start) {
+                continue;
+            }
+
+            labels[start] = Some(groups.len());
+            let mut group = vec![];
+            let mut queue = VecDeque::from(vec![start]);
+
+            while let Some(index) = queue.pop_front() {
+                group.push(index);
+
+                for neighbour in Self::neighbour_indices(index, width, height) {
+                    if labels[neighbour].is_none() && belongs(neighbour) {
+                        labels[neighbour] = Some(groups.len());
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+
+            groups.push(group);
+        }
+
+        (labels, groups)
+    }
+
     /// Adds the provided value to the row, saturating on the numeric bounds
     /// instead of overflowing or panicking.
     #[must_use]
@@ -174,6 +272,7 @@ impl Sub for Point {
     }
 }
 
+/// Implements the shared methods and operators for [`Row`] and [`Column`].
 macro_rules! impl_row_col {
     ($t:ty) => {
         impl $t {
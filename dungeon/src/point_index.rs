@@ -20,7 +20,7 @@ use super::Point;
 /// [a, b, c, d, e, f, g, h, i, j, k, l]
 ///  ^--------^  ^--------^  ^--------^
 /// ```
-
+///
 /// Where that would translate to this 2D grid:
 /// ```txt
 /// [
@@ -0,0 +1,363 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use crate::{DungeonTile, Floor, Point};
+
+/// A unique, opaque ID assigned to each [`Region`] of a floor. Regions are
+/// numbered in the order their first tile appears in, row by row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RegionId(usize);
+
+impl RegionId {
+    /// Creates a new [`RegionId`] with the given id.
+    #[must_use]
+    pub const fn new(id: usize) -> Self {
+        Self(id)
+    }
+
+    /// Returns the inner value.
+    #[must_use]
+    pub const fn get(self) -> usize {
+        self.0
+    }
+}
+
+/// An area of a floor, such as a cave or a room: a group of open tiles that
+/// can all be reached from each other without going through a secret
+/// passage. See [`Floor::regions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    /// The id of the region.
+    pub id: RegionId,
+    /// Every point in the region.
+    pub points: BTreeSet<Point>,
+    /// The top left corner of the region's bounding box.
+    pub top_left: Point,
+    /// The bottom right corner of the region's bounding box, inclusive.
+    pub bottom_right: Point,
+    /// The least amount of [passages](Passage) that have to be walked
+    /// through to get to the region from the region the entrance is in;
+    /// `Some(0)` for the region the entrance is in, and [`None`] if the floor
+    /// has no entrance or the region can't be reached from it at all. The
+    /// higher the depth, the more hidden the region is.
+    pub depth: Option<usize>,
+}
+
+impl Region {
+    /// Returns the amount of tiles in the region.
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.points.len()
+    }
+}
+
+/// A secret passage connecting two [regions](Region) of a floor, along with
+/// it's [doors](DungeonTile::SecretDoor).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Passage {
+    /// The regions the passage connects, lowest id first.
+    pub regions: (RegionId, RegionId),
+    /// Every point of the passage, including the doors.
+    pub points: BTreeSet<Point>,
+    /// The points of the doors of the passage.
+    pub doors: BTreeSet<Point>,
+    /// Whether or not any of the doors of the passage are
+    /// [locked](DungeonTile::is_locked).
+    pub is_locked: bool,
+}
+
+/// The [regions](Region) of a floor and the [passages](Passage) between them,
+/// forming an undirected graph with a node for every region and an edge for
+/// every passage. See [`Floor::regions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Regions {
+    /// Every region, indexed by it's id.
+    regions: Vec<Region>,
+    /// Every passage between two regions.
+    passages: Vec<Passage>,
+    /// The region of every tile of the floor, row by row.
+    labels: Vec<Option<RegionId>>,
+    /// The width of the floor.
+    width: usize,
+}
+
+impl Regions {
+    /// Returns the region with the id.
+    #[must_use]
+    pub fn get(&self, id: RegionId) -> Option<&Region> {
+        self.regions.get(id.get())
+    }
+
+    /// Returns every region, ordered by id.
+    #[must_use]
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// Returns every passage between two regions.
+    #[must_use]
+    pub fn passages(&self) -> &[Passage] {
+        &self.passages
+    }
+
+    /// Returns the id of the region the point is in, or [`None`] if it isn't
+    /// in any region (it's solid, part of a passage, or outside of the
+    /// floor).
+    #[must_use]
+    pub fn region_at(&self, point: Point) -> Option<RegionId> {
+        let column = point.column.get().as_unbounded() as usize;
+        if column >= self.width {
+            return None;
+        }
+
+        self.labels
+            .get(point.to_index(self.width))
+            .copied()
+            .flatten()
+    }
+
+    /// Returns an iterator over the regions adjacent to the region (the
+    /// regions connected to it by a passage), along with the passage that
+    /// connects them.
+    pub fn neighbours(&self, id: RegionId) -> impl Iterator<Item = (RegionId, &Passage)> + '_ {
+        self.passages
+            .iter()
+            .filter_map(move |passage| match passage.regions {
+                (a, b) if a == id => Some((b, passage)),
+                (a, b) if b == id => Some((a, passage)),
+                _ => None,
+            })
+    }
+}
+
+impl Floor {
+    /// Splits the floor up into it's [regions](Region) and the secret
+    /// [passages](Passage) connecting them.
+    ///
+    /// Every tile that can be walked over (including keys, the entrance and
+    /// the exit) is part of a region, except for
+    /// [secret doors](DungeonTile::SecretDoor) and
+    /// [secret passages](DungeonTile::SecretPassage), which make up the
+    /// passages. Since the caves of a generated floor are only connected by
+    /// secret passages, every passage leads to a hidden area, and the regions
+    /// that only a single passage leads to are dead ends.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use dungeon::{DungeonType, Floor, FloorId, GenerationConfig, Seed};
+    /// use std::convert::TryInto;
+    ///
+    /// let floor = Floor::with_seed(
+    ///     FloorId::new(0),
+    ///     DungeonType::Cave,
+    ///     80.try_into().unwrap(),
+    ///     80.try_into().unwrap(),
+    ///     &GenerationConfig::default(),
+    ///     Seed::new(0),
+    ///     false,
+    /// );
+    ///
+    /// let regions = floor.regions();
+    /// let start = regions.region_at(floor.entrance().unwrap()).unwrap();
+    /// assert_eq!(regions.get(start).unwrap().depth, Some(0));
+    ///
+    /// // every region of a generated floor can be reached from the entrance
+    /// assert!(regions.regions().iter().all(|region| region.depth.is_some()));
+    /// ```
+    #[must_use]
+    pub fn regions(&self) -> Regions {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.data.len() / width;
+
+        let neighbours = |index| Point::neighbour_indices(index, width, height);
+        let is_passage = |tile: DungeonTile| tile.is_secret_door() || tile.is_secret_passage();
+        let is_region = |tile: DungeonTile| !tile.is_solid() && !is_passage(tile);
+        let point = |index| Point::from_index(index, width).expect("the index is in the floor");
+
+        let (labels, groups) =
+            Point::connected_groups(width, height, 0..self.data.len(), |index| {
+                is_region(self.data[index])
+            });
+        let labels = labels
+            .into_iter()
+            .map(|label| label.map(RegionId::new))
+            .collect::<Vec<_>>();
+
+        let regions = groups
+            .into_iter()
+            .enumerate()
+            .map(|(id, tiles)| {
+                let points = tiles.into_iter().map(point).collect::<BTreeSet<_>>();
+                let (rows, columns): (Vec<_>, Vec<_>) =
+                    points.iter().map(|point| (point.row, point.column)).unzip();
+                let corner = |row, column| Point { row, column };
+
+                Region {
+                    id: RegionId::new(id),
+                    top_left: corner(
+                        *rows.iter().min().expect("regions aren't empty"),
+                        *columns.iter().min().expect("regions aren't empty"),
+                    ),
+                    bottom_right: corner(
+                        *rows.iter().max().expect("regions aren't empty"),
+                        *columns.iter().max().expect("regions aren't empty"),
+                    ),
+                    points,
+                    depth: None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // every group of passage tiles connects all of the regions around it
+        let mut visited = vec![false; self.data.len()];
+        let mut passages = vec![];
+
+        for start in 0..self.data.len() {
+            if visited[start] || !is_passage(self.data[start]) {
+                continue;
+            }
+
+            visited[start] = true;
+            let mut points = BTreeSet::new();
+            let mut touching = BTreeSet::new();
+            let mut queue = VecDeque::from(vec![start]);
+
+            while let Some(index) = queue.pop_front() {
+                points.insert(index);
+
+                for neighbour in neighbours(index) {
+                    if let Some(id) = labels[neighbour] {
+                        touching.insert(id);
+                    } else if !visited[neighbour] && is_passage(self.data[neighbour]) {
+                        visited[neighbour] = true;
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+
+            let doors = points
+                .iter()
+                .filter(|&&index| self.data[index].is_secret_door())
+                .map(|&index| point(index))
+                .collect::<BTreeSet<_>>();
+            let is_locked = points.iter().any(|&index| self.data[index].is_locked());
+            let points = points.into_iter().map(point).collect::<BTreeSet<_>>();
+
+            for (i, &a) in touching.iter().enumerate() {
+                for &b in touching.iter().skip(i + 1) {
+                    passages.push(Passage {
+                        regions: (a, b),
+                        points: points.clone(),
+                        doors: doors.clone(),
+                        is_locked,
+                    });
+                }
+            }
+        }
+
+        let mut regions = Regions {
+            regions,
+            passages,
+            labels,
+            width,
+        };
+
+        // breadth first search over the graph, out from the entrance
+        if let Some(start) = self
+            .entrance()
+            .and_then(|entrance| regions.region_at(entrance))
+        {
+            let mut depths = vec![None; regions.regions.len()];
+            depths[start.get()] = Some(0);
+            let mut queue = VecDeque::from(vec![start]);
+
+            while let Some(id) = queue.pop_front() {
+                let depth = depths[id.get()].map(|depth| depth + 1);
+                for (neighbour, _) in regions.neighbours(id) {
+                    if depths[neighbour.get()].is_none() {
+                        depths[neighbour.get()] = depth;
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+
+            for (region, depth) in regions.regions.iter_mut().zip(depths) {
+                region.depth = depth;
+            }
+        }
+
+        regions
+    }
+}
+
+#[cfg(test)]
+mod test_regions {
+    use super::*;
+    use crate::test_utils::{floor_from_rows, point};
+
+    #[test]
+    fn test_regions() {
+        let floor = floor_from_rows(&[
+            "##########",
+            "#<.#.....#",
+            "#..L.....#",
+            "####S#####",
+            "####S#####",
+            "#..#D###.#",
+            "#..#.#...#",
+            "#.d#.#.>.#",
+            "#..#.#...#",
+            "##########",
+        ]);
+
+        let regions = floor.regions();
+        let sizes = regions
+            .regions()
+            .iter()
+            .map(Region::size)
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, vec![4, 10, 7, 10, 3]);
+
+        let start = regions.region_at(point(1, 1)).unwrap();
+        let top_right = regions.region_at(point(1, 5)).unwrap();
+        let corridor = regions.region_at(point(6, 4)).unwrap();
+        let exit = regions.region_at(point(7, 7)).unwrap();
+        assert_eq!(regions.region_at(point(2, 3)), None);
+        assert_eq!(regions.region_at(point(0, 0)), None);
+
+        let region = regions.get(top_right).unwrap();
+        assert_eq!(
+            (region.top_left, region.bottom_right),
+            (point(1, 4), point(2, 8))
+        );
+
+        // the locked door joins the top two regions, and the passage below them
+        // leads down into the corridor
+        let passages = regions.passages();
+        assert_eq!(
+            passages
+                .iter()
+                .map(|passage| (passage.regions, passage.is_locked))
+                .collect::<Vec<_>>(),
+            vec![((start, top_right), true), ((top_right, corridor), false)]
+        );
+        assert_eq!(
+            passages[1].doors.iter().copied().collect::<Vec<_>>(),
+            vec![point(5, 4)]
+        );
+        assert_eq!(
+            regions
+                .neighbours(top_right)
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>(),
+            vec![start, corridor]
+        );
+        assert_eq!(regions.get(corridor).unwrap().depth, Some(2));
+
+        // the open door on the left is a passage to nowhere, and the exit is
+        // walled off
+        let left = regions.region_at(point(7, 1)).unwrap();
+        assert_eq!(regions.neighbours(left).count(), 0);
+        assert_eq!(regions.get(left).unwrap().depth, None);
+        assert_eq!(regions.get(exit).unwrap().depth, None);
+    }
+}
@@ -0,0 +1,299 @@
+use std::{convert::TryFrom, error::Error, fmt};
+
+use bounded_int::BoundedInt;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    Dungeon, DungeonTile, Entities, Floor, FloorViolation, Item, PlacedVault, Point,
+    MAX_FLOOR_SIZE, MIN_FLOOR_SIZE,
+};
+
+/// A [`Floor`] as it is saved, before the amount of tiles has been checked
+/// against it's dimensions.
+#[derive(Deserialize)]
+pub(crate) struct SavedFloor {
+    /// See [`Floor::width`].
+    width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+    /// See [`Floor::height`].
+    height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+    /// See [`Floor::data`].
+    data: Vec<DungeonTile>,
+    /// See [`Floor::chests`].
+    #[serde(default)]
+    chests: Vec<Vec<Item>>,
+    /// See [`Floor::spawn_points`].
+    #[serde(default)]
+    spawn_points: Vec<Point>,
+    /// See [`Floor::entities`].
+    #[serde(default)]
+    entities: Entities,
+    /// See [`Floor::vaults`].
+    #[serde(default)]
+    vaults: Vec<PlacedVault>,
+}
+
+impl TryFrom<SavedFloor> for Floor {
+    type Error = FloorViolation;
+
+    fn try_from(saved: SavedFloor) -> Result<Self, Self::Error> {
+        let tiles = saved.width.as_unbounded() as usize * saved.height.as_unbounded() as usize;
+
+        if saved.data.len() != tiles {
+            return Err(FloorViolation::InvalidDimensions {
+                width: saved.width.as_unbounded(),
+                height: saved.height.as_unbounded(),
+                tiles: saved.data.len(),
+            });
+        }
+
+        Ok(Self {
+            width: saved.width,
+            height: saved.height,
+            data: saved.data,
+            chests: saved.chests,
+            spawn_points: saved.spawn_points,
+            entities: saved.entities,
+            vaults: saved.vaults,
+        })
+    }
+}
+
+/// Error returned from [`Dungeon::from_json`] when loading a saved dungeon
+/// fails.
+#[derive(Debug)]
+pub enum LoadDungeonError {
+    /// The JSON couldn't be parsed into a dungeon, even after migrating it.
+    /// This includes floors whose amount of tiles doesn't match their
+    /// dimensions, and tiles that don't exist.
+    Json(serde_json::Error),
+    /// The dungeon was saved by a newer version of the crate, in a
+    /// [schema version](Dungeon::SCHEMA_VERSION) that this version doesn't
+    /// know about.
+    UnsupportedVersion {
+        /// The schema version the dungeon was saved in.
+        found: u32,
+        /// The newest schema version this version of the crate supports.
+        supported: u32,
+    },
+}
+
+impl fmt::Display for LoadDungeonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadDungeonError::Json(err) => {
+                f.write_fmt(format_args!("failed to parse dungeon json: {}", err))
+            }
+            LoadDungeonError::UnsupportedVersion { found, supported } => f.write_fmt(format_args!(
+                "dungeon was saved in schema version {}, but only versions up to {} are supported",
+                found, supported
+            )),
+        }
+    }
+}
+
+impl Error for LoadDungeonError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadDungeonError::Json(err) => Some(err),
+            LoadDungeonError::UnsupportedVersion { .. } => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for LoadDungeonError {
+    fn from(err: serde_json::Error) -> Self {
+        LoadDungeonError::Json(err)
+    }
+}
+
+/// Just the schema version of a saved dungeon, read before the rest of it.
+#[derive(Deserialize)]
+struct SchemaVersion {
+    /// See [`Dungeon::schema_version`].
+    #[serde(default)]
+    schema_version: u32,
+}
+
+/// The migrations that bring a saved dungeon up to date, as JSON. The
+/// migration at index `n` migrates a dungeon from schema version `n` to
+/// `n + 1`.
+const MIGRATIONS: [fn(&mut Value); Dungeon::SCHEMA_VERSION as usize] = [migrate_unversioned];
+
+impl Dungeon {
+    /// The schema version dungeons are currently saved in. Bumped every time
+    /// the saved format changes in a way that older saves can't be read as
+    /// is, along with a migration from the previous version; see
+    /// [`Dungeon::from_json`].
+    pub const SCHEMA_VERSION: u32 = 1;
+
+    /// Loads a dungeon saved with [`Dungeon::to_json`], migrating it to the
+    /// current [schema version](Dungeon::SCHEMA_VERSION) first if it was saved
+    /// by an older version of the crate.
+    ///
+    /// # Errors
+    /// Errors if the JSON isn't a valid dungeon, or if it was saved in a newer
+    /// schema version than this version of the crate supports.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use dungeon::{Dungeon, DungeonType, GenerationConfig, LoadDungeonError, Seed};
+    /// use std::{convert::TryInto, num::NonZeroU16};
+    ///
+    /// let dungeon = Dungeon::with_seed(
+    ///     50.try_into().unwrap(),
+    ///     50.try_into().unwrap(),
+    ///     NonZeroU16::new(2).unwrap(),
+    ///     DungeonType::Cave,
+    ///     &GenerationConfig::default(),
+    ///     Seed::new(0),
+    ///     false,
+    /// );
+    ///
+    /// let json = dungeon.to_json().unwrap();
+    /// assert_eq!(Dungeon::from_json(&json).unwrap(), dungeon);
+    ///
+    /// // saves from the future can't be loaded
+    /// let future = json.replace(r#""schema_version":1"#, r#""schema_version":99"#);
+    /// assert!(matches!(
+    ///     Dungeon::from_json(&future),
+    ///     Err(LoadDungeonError::UnsupportedVersion { found: 99, .. })
+    /// ));
+    /// ```
+    pub fn from_json(json: &str) -> Result<Self, LoadDungeonError> {
+        let mut dungeon: Value = serde_json::from_str(json)?;
+
+        let version = SchemaVersion::deserialize(&dungeon)?.schema_version;
+        if version > Self::SCHEMA_VERSION {
+            return Err(LoadDungeonError::UnsupportedVersion {
+                found: version,
+                supported: Self::SCHEMA_VERSION,
+            });
+        }
+
+        for migrate in &MIGRATIONS[version as usize..] {
+            migrate(&mut dungeon);
+        }
+        if let Some(dungeon) = dungeon.as_object_mut() {
+            dungeon.insert("schema_version".to_string(), Self::SCHEMA_VERSION.into());
+        }
+
+        Ok(Self::deserialize(dungeon)?)
+    }
+}
+
+/// Migrates a dungeon saved before the schema was versioned (version `0`).
+///
+/// The seed wasn't saved back then and can't be recovered, so it's set to
+/// `0`. Treasure chests had no contents, so every chest gets an empty one.
+fn migrate_unversioned(dungeon: &mut Value) {
+    let dungeon = match dungeon.as_object_mut() {
+        Some(dungeon) => dungeon,
+        None => return,
+    };
+
+    dungeon.entry("seed").or_insert_with(|| 0.into());
+
+    let floors = dungeon
+        .get_mut("floors")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten();
+
+    for floor in floors {
+        let mut chests = 0_u16;
+
+        let tiles = floor
+            .get_mut("data")
+            .and_then(Value::as_array_mut)
+            .into_iter()
+            .flatten();
+        for tile in tiles {
+            if let Some(chest) = tile.get_mut("TreasureChest") {
+                if chest.get("contents").is_none_or(Value::is_null) {
+                    *chest = serde_json::json!({ "contents": chests });
+                    chests += 1;
+                }
+            }
+        }
+
+        if let Some(floor) = floor.as_object_mut() {
+            floor
+                .entry("chests")
+                .or_insert_with(|| vec![Value::Array(vec![]); chests.into()].into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_schema {
+    use std::{convert::TryInto, num::NonZeroU16};
+
+    use super::*;
+    use crate::{DungeonType, GenerationConfig, Seed};
+
+    #[test]
+    fn test_loading_saved_dungeons() {
+        let dungeon = Dungeon::with_seed(
+            50.try_into().unwrap(),
+            50.try_into().unwrap(),
+            NonZeroU16::new(2).unwrap(),
+            DungeonType::Cave,
+            &GenerationConfig::default(),
+            Seed::new(3),
+            false,
+        );
+        assert_eq!(dungeon.schema_version, Dungeon::SCHEMA_VERSION);
+        let saved: serde_json::Value = serde_json::from_str(&dungeon.to_json().unwrap()).unwrap();
+
+        let mut truncated = saved.clone();
+        truncated["floors"][1]["data"].as_array_mut().unwrap().pop();
+        let err = Dungeon::from_json(&truncated.to_string()).unwrap_err();
+        assert!(matches!(err, LoadDungeonError::Json(_)));
+        assert!(err
+            .to_string()
+            .contains("a 50x50 floor must have 2500 tiles, found 2499"));
+
+        let mut unknown = saved.clone();
+        unknown["floors"][0]["data"][0] = "Lava".into();
+        let err = Dungeon::from_json(&unknown.to_string()).unwrap_err();
+        assert!(err.to_string().contains("unknown variant `Lava`"));
+
+        let mut future = saved.clone();
+        future["schema_version"] = 2.into();
+        assert!(matches!(
+            Dungeon::from_json(&future.to_string()),
+            Err(LoadDungeonError::UnsupportedVersion {
+                found: 2,
+                supported: 1
+            })
+        ));
+
+        // dungeons saved before the schema was versioned had no seed, and no
+        // chest contents
+        let mut unversioned = saved;
+        let object = unversioned.as_object_mut().unwrap();
+        object.remove("schema_version");
+        object.remove("seed");
+        for floor in object["floors"].as_array_mut().unwrap() {
+            let floor = floor.as_object_mut().unwrap();
+            floor.remove("chests");
+            floor.remove("spawn_points");
+            floor.remove("entities");
+            for tile in floor["data"].as_array_mut().unwrap() {
+                if let Some(chest) = tile.get_mut("TreasureChest") {
+                    chest["contents"] = serde_json::Value::Null;
+                }
+            }
+        }
+
+        let migrated = Dungeon::from_json(&unversioned.to_string()).unwrap();
+        assert_eq!(migrated.schema_version, Dungeon::SCHEMA_VERSION);
+        assert_eq!(migrated.seed, Seed::new(0));
+        for (migrated, floor) in migrated.floors.iter().zip(&dungeon.floors) {
+            assert_eq!(migrated.validate(), Ok(()));
+            assert_eq!(migrated.chests.len(), floor.chests.len());
+            assert!(migrated.chests.iter().all(Vec::is_empty));
+        }
+    }
+}
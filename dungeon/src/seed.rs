@@ -0,0 +1,99 @@
+use std::{convert::Infallible, fmt, str::FromStr};
+
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::FloorId;
+
+/// The seed used to generate a [`Dungeon`](crate::Dungeon) or a
+/// [`Floor`](crate::Floor).
+///
+/// Generating with the same seed and the same parameters will always produce
+/// the exact same result, so a seed can be shared to reproduce a dungeon.
+///
+/// Seeds can be created from a number, or from any string. A string that is a
+/// valid [`u64`] is parsed as that number (so that the [`Display`](fmt::Display)
+/// output of a seed round-trips through [`FromStr`]), and any other string is
+/// hashed into a number.
+///
+/// # Examples
+/// ```rust
+/// use dungeon::Seed;
+///
+/// let seed: Seed = "mossy cavern".parse().unwrap();
+///
+/// assert_eq!(seed, Seed::from("mossy cavern"));
+/// assert_eq!(seed, seed.to_string().parse().unwrap());
+/// assert_eq!(Seed::from(1234), "1234".parse().unwrap());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Seed(u64);
+
+impl Seed {
+    /// Creates a new seed from the provided value.
+    #[must_use]
+    pub const fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Creates a new random seed.
+    #[must_use]
+    pub fn random() -> Self {
+        Self(thread_rng().gen())
+    }
+
+    /// Returns the inner value.
+    #[must_use]
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+
+    /// Creates the rng used to generate the floor with the provided id.
+    ///
+    /// Every floor gets it's own stream of the same seed, so the floors of a
+    /// dungeon don't depend on each other (or on the order they are generated
+    /// in).
+    pub(crate) fn floor_rng(self, id: FloorId) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.0);
+        rng.set_stream(id.0.into());
+        rng
+    }
+}
+
+impl From<u64> for Seed {
+    fn from(seed: u64) -> Self {
+        Self(seed)
+    }
+}
+
+impl From<&str> for Seed {
+    /// Hashes the string into a seed using 64 bit FNV-1a.
+    ///
+    /// [`std::hash::Hash`] isn't used here since it's output isn't guaranteed
+    /// to be stable between releases, which would make shared seeds useless.
+    fn from(phrase: &str) -> Self {
+        /// FNV-1a 64 bit offset basis.
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        /// FNV-1a 64 bit prime.
+        const PRIME: u64 = 0x0100_0000_01b3;
+
+        Self(phrase.bytes().fold(OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+        }))
+    }
+}
+
+impl FromStr for Seed {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.parse().map_or_else(|_| Self::from(s), Self))
+    }
+}
+
+impl fmt::Display for Seed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("{}", self.0))
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{DistanceMap, DungeonTile, Floor, Point};
+
+/// Metrics describing the layout and difficulty of a [`Floor`], for filtering
+/// and ranking generated floors, or comparing generation settings. See
+/// [`Floor::stats`].
+///
+/// Unless stated otherwise, tiles are walked between up, down, left and right,
+/// and every tile that isn't [solid](DungeonTile::is_solid) can be walked
+/// over, including closed and locked secret doors.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FloorStats {
+    /// The amount of tiles that aren't walls.
+    pub open_tiles: usize,
+    /// The fraction of all tiles that aren't walls, between `0` and `1`.
+    pub open_ratio: f64,
+    /// The amount of caves (or rooms) the floor is split up into by it's
+    /// secret passages, the same as the amount of [regions](Floor::regions).
+    pub caves: usize,
+    /// The amount of groups of caves left after connecting them with secret
+    /// passages. Every generated floor has exactly `1`.
+    pub connected_caves: usize,
+    /// The amount of secret passages between two caves.
+    pub secret_passages: usize,
+    /// The walking distance from the entrance to the exit, or [`None`] if the
+    /// floor doesn't have both or the exit can't be reached.
+    pub path_length: Option<i32>,
+    /// The straight-line distance from the entrance to the exit, or [`None`]
+    /// if the floor doesn't have both.
+    pub straight_line_distance: Option<f64>,
+    /// The amount of walkable tiles with only a single walkable neighbour.
+    pub dead_ends: usize,
+    /// The amount of treasure chests.
+    pub chests: usize,
+    /// The amount of treasure chests that can be reached from the entrance.
+    pub reachable_chests: usize,
+    /// The amount of reachable treasure chests that can only be reached by
+    /// going through a secret passage.
+    pub hidden_chests: usize,
+    /// The average walking distance from the entrance to the reachable
+    /// treasure chests, or [`None`] if there are none.
+    pub average_chest_distance: Option<f64>,
+    /// The average width of the corridors, where the width at a walkable tile
+    /// is the shorter of the unbroken horizontal and vertical runs of
+    /// walkable tiles through it. `0` if nothing can be walked over.
+    pub average_corridor_width: f64,
+}
+
+impl FloorStats {
+    /// Returns how much longer the walk from the entrance to the exit is than
+    /// the straight line between them: `1` for a perfectly straight path, and
+    /// higher the more the path winds. [`None`] if either distance is
+    /// unknown, or the entrance and the exit are on the same tile.
+    #[must_use]
+    pub fn path_ratio(&self) -> Option<f64> {
+        let straight_line = self
+            .straight_line_distance
+            .filter(|&distance| distance > 0.0)?;
+
+        self.path_length
+            .map(|length| f64::from(length) / straight_line)
+    }
+}
+
+impl Floor {
+    /// Measures the floor; see [`FloorStats`].
+    ///
+    /// # Examples
+    /// ```rust
+    /// use dungeon::{DungeonType, Floor, FloorId, GenerationConfig, Seed};
+    /// use std::convert::TryInto;
+    ///
+    /// // only keep the floors with a long walk to the exit
+    /// let floors = (0..5)
+    ///     .map(|seed| {
+    ///         Floor::with_seed(
+    ///             FloorId::new(0),
+    ///             DungeonType::Cave,
+    ///             50.try_into().unwrap(),
+    ///             50.try_into().unwrap(),
+    ///             &GenerationConfig::default(),
+    ///             Seed::new(seed),
+    ///             false,
+    ///         )
+    ///     })
+    ///     .filter(|floor| floor.stats().path_length.is_some_and(|length| length >= 40))
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert!(!floors.is_empty());
+    /// for floor in &floors {
+    ///     let stats = floor.stats();
+    ///     assert_eq!(stats.connected_caves, 1);
+    ///     assert_eq!(stats.reachable_chests, stats.chests);
+    ///     assert!(stats.path_ratio().unwrap() >= 1.0);
+    /// }
+    /// ```
+    #[must_use]
+    pub fn stats(&self) -> FloorStats {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.data.len() / width;
+
+        let neighbours = |index| Point::neighbour_indices(index, width, height);
+        let walkable = |index: usize| !self.data[index].is_solid();
+
+        let open_tiles = self.data.iter().filter(|tile| !tile.is_wall()).count();
+
+        let regions = self.regions();
+        let connected_caves = {
+            let mut seen = vec![false; regions.regions().len()];
+            let mut groups = 0;
+
+            for region in regions.regions() {
+                if seen[region.id.get()] {
+                    continue;
+                }
+
+                groups += 1;
+                seen[region.id.get()] = true;
+                let mut queue = VecDeque::from(vec![region.id]);
+                while let Some(id) = queue.pop_front() {
+                    for (neighbour, _) in regions.neighbours(id) {
+                        if !seen[neighbour.get()] {
+                            seen[neighbour.get()] = true;
+                            queue.push_back(neighbour);
+                        }
+                    }
+                }
+            }
+
+            groups
+        };
+
+        let entrance = self.entrance();
+        let exit = self.exit();
+        let from_entrance =
+            DistanceMap::through_locked_doors(&self.data, self.width, self.height, entrance);
+
+        // the same map, but with every secret door and passage walled up
+        let without_secrets = self
+            .data
+            .iter()
+            .map(|&tile| {
+                if tile.is_secret_door() || tile.is_secret_passage() {
+                    DungeonTile::Wall
+                } else {
+                    tile
+                }
+            })
+            .collect::<Vec<_>>();
+        let without_secrets =
+            DistanceMap::through_locked_doors(&without_secrets, self.width, self.height, entrance);
+
+        // chests are solid, so they are reached by walking up next to them
+        let chest_distance = |map: &DistanceMap, index: usize| {
+            neighbours(index)
+                .filter_map(|neighbour| map.values()[neighbour])
+                .min()
+                .map(|distance| distance + 1)
+        };
+        let chests = (0..self.data.len())
+            .filter(|&index| self.data[index].is_treasure_chest())
+            .collect::<Vec<_>>();
+        let chest_distances = chests
+            .iter()
+            .filter_map(|&index| chest_distance(&from_entrance, index))
+            .collect::<Vec<_>>();
+        let hidden_chests = chests
+            .iter()
+            .filter(|&&index| chest_distance(&from_entrance, index).is_some())
+            .filter(|&&index| chest_distance(&without_secrets, index).is_none())
+            .count();
+
+        let walkable_tiles = (0..self.data.len())
+            .filter(|&index| walkable(index))
+            .collect::<Vec<_>>();
+        let dead_ends = walkable_tiles
+            .iter()
+            .filter(|&&index| neighbours(index).filter(|&n| walkable(n)).count() == 1)
+            .count();
+
+        // the length of the unbroken run of walkable tiles through every tile,
+        // in a single direction
+        let runs = |step: usize, len: usize, lines: usize, line_step: usize| {
+            let mut runs = vec![0_usize; self.data.len()];
+            for line in 0..lines {
+                let mut start = 0;
+                while start < len {
+                    let first = line * line_step + start * step;
+                    if !walkable(first) {
+                        start += 1;
+                        continue;
+                    }
+
+                    let end = (start..len)
+                        .find(|&i| !walkable(line * line_step + i * step))
+                        .unwrap_or(len);
+                    for i in start..end {
+                        runs[line * line_step + i * step] = end - start;
+                    }
+                    start = end;
+                }
+            }
+            runs
+        };
+        let horizontal = runs(1, width, height, width);
+        let vertical = runs(width, height, width, 1);
+        let total_width: usize = walkable_tiles
+            .iter()
+            .map(|&index| horizontal[index].min(vertical[index]))
+            .sum();
+
+        let average = |total: f64, count: usize| total / count as f64;
+
+        FloorStats {
+            open_tiles,
+            open_ratio: average(open_tiles as f64, self.data.len()),
+            caves: regions.regions().len(),
+            connected_caves,
+            secret_passages: regions.passages().len(),
+            path_length: exit.and_then(|exit| from_entrance.get(exit)),
+            straight_line_distance: entrance
+                .zip(exit)
+                .map(|(entrance, exit)| straight_line(entrance, exit)),
+            dead_ends,
+            chests: chests.len(),
+            reachable_chests: chest_distances.len(),
+            hidden_chests,
+            average_chest_distance: (!chest_distances.is_empty()).then(|| {
+                average(
+                    chest_distances.iter().map(|&d| f64::from(d)).sum(),
+                    chest_distances.len(),
+                )
+            }),
+            average_corridor_width: if walkable_tiles.is_empty() {
+                0.0
+            } else {
+                average(total_width as f64, walkable_tiles.len())
+            },
+        }
+    }
+}
+
+/// Returns the euclidean distance between the points.
+fn straight_line(a: Point, b: Point) -> f64 {
+    let rows = f64::from(a.row.get().as_unbounded() - b.row.get().as_unbounded());
+    let columns = f64::from(a.column.get().as_unbounded() - b.column.get().as_unbounded());
+
+    rows.hypot(columns)
+}
+
+#[cfg(test)]
+mod test_stats {
+    use super::*;
+    use crate::{
+        test_utils::{floor_from_rows, point},
+        ChestId,
+    };
+
+    #[test]
+    fn test_floor_stats() {
+        let mut floor = floor_from_rows(&[
+            "##########",
+            "#<.......#",
+            "#........#",
+            "#........#",
+            "####D#####",
+            "#........#",
+            "#........#",
+            "#.......>#",
+            "#.#......#",
+            "##########",
+        ]);
+        *floor.at_mut(point(5, 8)) = DungeonTile::TreasureChest {
+            contents: ChestId::new(0),
+        };
+        floor.chests.push(vec![]);
+
+        assert_eq!(
+            floor.stats(),
+            FloorStats {
+                open_tiles: 56,
+                open_ratio: 0.56,
+                caves: 2,
+                connected_caves: 1,
+                secret_passages: 1,
+                path_length: Some(13),
+                straight_line_distance: Some(85_f64.sqrt()),
+                dead_ends: 1,
+                chests: 1,
+                reachable_chests: 1,
+                hidden_chests: 1,
+                average_chest_distance: Some(11.0),
+                average_corridor_width: 212.0 / 55.0,
+            }
+        );
+        assert_eq!(floor.stats().path_ratio(), Some(13.0 / 85_f64.sqrt()));
+
+        *floor.at_mut(point(4, 4)) = DungeonTile::Wall;
+        let stats = floor.stats();
+        assert_eq!(stats.connected_caves, 2);
+        assert_eq!(stats.secret_passages, 0);
+        assert_eq!(stats.path_length, None);
+        assert_eq!(stats.path_ratio(), None);
+        assert_eq!(stats.reachable_chests, 0);
+        assert_eq!(stats.average_chest_distance, None);
+    }
+}
@@ -0,0 +1,58 @@
+use std::convert::TryInto;
+
+use crate::{Column, DungeonTile, DungeonType, Floor, FloorId, GenerationConfig, Point, Row, Seed};
+
+/// Generates the first floor of a square cave dungeon, `size` tiles wide and
+/// tall; most tests that need a real floor start from one of these.
+pub(crate) fn cave_floor(size: i32, config: &GenerationConfig, seed: u64) -> Floor {
+    Floor::with_seed(
+        FloorId::new(0),
+        DungeonType::Cave,
+        size.try_into().unwrap(),
+        size.try_into().unwrap(),
+        config,
+        Seed::new(seed),
+        false,
+    )
+}
+
+/// Builds a floor from rows of characters: `#` for walls, `.` for empty tiles,
+/// `D` for closed secret doors, `d` for open ones, `L` for locked ones, `S`
+/// for secret passages, `<` for the entrance and `>` for the exit.
+///
+/// # Panics
+/// Panics on any other character.
+pub(crate) fn floor_from_rows(rows: &[&str]) -> Floor {
+    Floor {
+        width: (rows[0].len() as i32).try_into().unwrap(),
+        height: (rows.len() as i32).try_into().unwrap(),
+        data: rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .map(|c| match c {
+                '#' => DungeonTile::Wall,
+                '.' => DungeonTile::Empty,
+                'D' | 'd' | 'L' => DungeonTile::SecretDoor {
+                    requires_key: c == 'L',
+                    is_open: c == 'd',
+                },
+                'S' => DungeonTile::SecretPassage,
+                '<' => DungeonTile::Entrance,
+                '>' => DungeonTile::Exit,
+                _ => panic!("unknown tile {:?}", c),
+            })
+            .collect(),
+        chests: vec![],
+        spawn_points: vec![],
+        entities: Default::default(),
+        vaults: vec![],
+    }
+}
+
+/// The point at the row and column.
+pub(crate) fn point(row: i32, column: i32) -> Point {
+    Point {
+        row: Row::new(row.try_into().unwrap()),
+        column: Column::new(column.try_into().unwrap()),
+    }
+}
@@ -0,0 +1,380 @@
+use std::{collections::VecDeque, error::Error, fmt};
+
+use crate::{doors, DungeonTile, Floor, GenerationError, Point};
+
+/// Something wrong with a [`Floor`], found by [`Floor::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloorViolation {
+    /// The amount of tiles in [`Floor::data`] wasn't `width * height`.
+    /// Nothing else is checked when the dimensions are wrong.
+    InvalidDimensions {
+        /// The width of the floor.
+        width: i32,
+        /// The height of the floor.
+        height: i32,
+        /// The amount of tiles in the floor.
+        tiles: usize,
+    },
+    /// The floor didn't have exactly one entrance and one exit. None of the
+    /// reachability checks are done without a single entrance.
+    EntrancesAndExits {
+        /// How many entrances the floor had.
+        entrances: usize,
+        /// How many exits the floor had.
+        exits: usize,
+    },
+    /// A tile on the edge of the floor wasn't a wall, so the player could
+    /// walk off of the floor.
+    OpenEdge(Point),
+    /// The exit couldn't be reached from the entrance, even going through
+    /// every secret door.
+    UnreachableExit {
+        /// The entrance of the floor.
+        entrance: Point,
+        /// The exit of the floor.
+        exit: Point,
+    },
+    /// A tile that isn't a wall couldn't be reached from the entrance, even
+    /// going through every secret door.
+    UnreachableTile {
+        /// The entrance of the floor.
+        entrance: Point,
+        /// The tile that couldn't be reached.
+        tile: Point,
+    },
+    /// The [contents](DungeonTile::TreasureChest::contents) of the treasure
+    /// chest at the point weren't in [`Floor::chests`].
+    MissingChestContents(Point),
+    /// The [key](DungeonTile::Key) at the point doesn't unlock anything,
+    /// since there is no secret door where it points to.
+    KeyWithoutDoor(Point),
+    /// The key to the locked door at the point couldn't be reached without
+    /// going through the door first, so the door could never be opened.
+    LockedOut(Point),
+}
+
+impl fmt::Display for FloorViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FloorViolation::InvalidDimensions {
+                width,
+                height,
+                tiles,
+            } => f.write_fmt(format_args!(
+                "a {}x{} floor must have {} tiles, found {}",
+                width,
+                height,
+                i64::from(*width) * i64::from(*height),
+                tiles
+            )),
+            FloorViolation::EntrancesAndExits { entrances, exits } => f.write_fmt(format_args!(
+                "expected exactly one entrance and one exit, found {} entrances and {} exits",
+                entrances, exits
+            )),
+            FloorViolation::OpenEdge(point) => f.write_fmt(format_args!(
+                "the tile at {:?} is on the edge of the floor, but isn't a wall",
+                point
+            )),
+            FloorViolation::UnreachableExit { entrance, exit } => f.write_fmt(format_args!(
+                "no path found from the entrance at {:?} to the exit at {:?}",
+                entrance, exit
+            )),
+            FloorViolation::UnreachableTile { entrance, tile } => f.write_fmt(format_args!(
+                "the tile at {:?} can't be reached from the entrance at {:?}",
+                tile, entrance
+            )),
+            FloorViolation::MissingChestContents(chest) => f.write_fmt(format_args!(
+                "the treasure chest at {:?} has no contents",
+                chest
+            )),
+            FloorViolation::KeyWithoutDoor(key) => {
+                f.write_fmt(format_args!("the key at {:?} doesn't unlock any door", key))
+            }
+            FloorViolation::LockedOut(door) => f.write_fmt(format_args!(
+                "the key to the locked door at {:?} can't be reached without opening it",
+                door
+            )),
+        }
+    }
+}
+
+impl Error for FloorViolation {}
+
+impl From<FloorViolation> for GenerationError {
+    fn from(violation: FloorViolation) -> Self {
+        match violation {
+            FloorViolation::InvalidDimensions { width, height, .. } => {
+                GenerationError::InvalidDimensions { width, height }
+            }
+            FloorViolation::EntrancesAndExits { entrances, exits } => {
+                GenerationError::InvalidEntrancesAndExits { entrances, exits }
+            }
+            FloorViolation::UnreachableExit { entrance, exit } => {
+                GenerationError::UnreachableExit { entrance, exit }
+            }
+            FloorViolation::UnreachableTile { entrance, tile } => {
+                GenerationError::UnreachableTile { entrance, tile }
+            }
+            FloorViolation::MissingChestContents(chest) => {
+                GenerationError::MissingChestContents(chest)
+            }
+            FloorViolation::LockedOut(door) => GenerationError::LockedOut(door),
+            FloorViolation::OpenEdge(_) | FloorViolation::KeyWithoutDoor(_) => {
+                GenerationError::InvalidFloor(violation)
+            }
+        }
+    }
+}
+
+impl Floor {
+    /// Checks that the floor is sane, returning every problem found with it.
+    ///
+    /// A valid floor has `width * height` tiles, exactly one entrance and one
+    /// exit, and walls all along it's edges. Every tile that isn't a wall can
+    /// be reached from the entrance without walking over any
+    /// [solid](DungeonTile::is_solid) tiles (walking through the secret
+    /// doors, and up to the treasure chests), every treasure chest has
+    /// contents, every key belongs to a secret door, and the key to every
+    /// locked door can be reached without going through the door.
+    ///
+    /// Every generated floor is valid; this is for checking floors that have
+    /// been loaded from a file or edited by hand.
+    ///
+    /// # Errors
+    /// Errors with every violation found if the floor isn't valid, in the
+    /// order they are listed in [`FloorViolation`].
+    ///
+    /// # Examples
+    /// ```rust
+    /// use dungeon::{DungeonTile, DungeonType, Floor, FloorId, FloorViolation, GenerationConfig, Seed};
+    /// use std::convert::TryInto;
+    ///
+    /// let mut floor = Floor::with_seed(
+    ///     FloorId::new(0),
+    ///     DungeonType::Cave,
+    ///     50.try_into().unwrap(),
+    ///     50.try_into().unwrap(),
+    ///     &GenerationConfig::default(),
+    ///     Seed::new(0),
+    ///     false,
+    /// );
+    /// assert_eq!(floor.validate(), Ok(()));
+    ///
+    /// let exit = floor.exit().unwrap();
+    /// *floor.at_mut(exit) = DungeonTile::Empty;
+    /// assert_eq!(
+    ///     floor.validate(),
+    ///     Err(vec![FloorViolation::EntrancesAndExits {
+    ///         entrances: 1,
+    ///         exits: 0
+    ///     }])
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<FloorViolation>> {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.height.as_unbounded() as usize;
+        let point = |index| Point::from_index(index, width).expect("the index is in the floor");
+
+        if self.data.len() != width * height {
+            return Err(vec![FloorViolation::InvalidDimensions {
+                width: self.width.as_unbounded(),
+                height: self.height.as_unbounded(),
+                tiles: self.data.len(),
+            }]);
+        }
+
+        let mut violations = vec![];
+
+        let entrances = self.data.iter().filter(|tile| tile.is_entrance()).count();
+        let exits = self.data.iter().filter(|tile| tile.is_exit()).count();
+        if entrances != 1 || exits != 1 {
+            violations.push(FloorViolation::EntrancesAndExits { entrances, exits });
+        }
+
+        violations.extend(
+            (0..self.data.len())
+                .filter(|&index| {
+                    let (row, column) = (index / width, index % width);
+                    row == 0 || column == 0 || row + 1 == height || column + 1 == width
+                })
+                .filter(|&index| !self.data[index].is_wall())
+                .map(|index| FloorViolation::OpenEdge(point(index))),
+        );
+
+        let entrance = self
+            .data
+            .iter()
+            .position(|tile| tile.is_entrance())
+            .filter(|_| entrances == 1);
+
+        if let Some(entrance) = entrance {
+            let reached = self.reachable_from(entrance);
+
+            if let Some(exit) = self.exit().filter(|_| exits == 1) {
+                if !reached[exit.to_index(width)] {
+                    violations.push(FloorViolation::UnreachableExit {
+                        entrance: point(entrance),
+                        exit,
+                    });
+                }
+            }
+
+            violations.extend(
+                (0..self.data.len())
+                    .filter(|&index| !reached[index] && !self.data[index].is_wall())
+                    .filter(|&index| !self.data[index].is_exit())
+                    .map(|index| FloorViolation::UnreachableTile {
+                        entrance: point(entrance),
+                        tile: point(index),
+                    }),
+            );
+        }
+
+        violations.extend(
+            self.data
+                .iter()
+                .enumerate()
+                .filter(|&(_, tile)| match tile {
+                    DungeonTile::TreasureChest { contents } => {
+                        usize::from(contents.get()) >= self.chests.len()
+                    }
+                    _ => false,
+                })
+                .map(|(index, _)| FloorViolation::MissingChestContents(point(index))),
+        );
+
+        violations.extend(
+            self.data
+                .iter()
+                .enumerate()
+                .filter(|&(_, tile)| match tile {
+                    DungeonTile::Key { door } => !self
+                        .index(*door)
+                        .is_some_and(|door| self.data[door].is_secret_door()),
+                    _ => false,
+                })
+                .map(|(index, _)| FloorViolation::KeyWithoutDoor(point(index))),
+        );
+
+        if let Some(entrance) = entrance {
+            if let Some(door) = doors::first_locked_out_door(&self.data, width, entrance) {
+                violations.push(FloorViolation::LockedOut(door));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Returns which tiles can be reached from the tile at the index, row by
+    /// row, walking over everything that isn't [solid](DungeonTile::is_solid).
+    /// Solid tiles (other than walls) next to a walkable tile can be reached,
+    /// but not walked over.
+    fn reachable_from(&self, start: usize) -> Vec<bool> {
+        let width = self.width.as_unbounded() as usize;
+        let height = self.data.len() / width;
+
+        let mut reached = vec![false; self.data.len()];
+        reached[start] = true;
+        let mut queue = VecDeque::from(vec![start]);
+
+        while let Some(index) = queue.pop_front() {
+            for neighbour in Point::neighbour_indices(index, width, height) {
+                if !reached[neighbour] && !self.data[neighbour].is_wall() {
+                    reached[neighbour] = true;
+                    // solid tiles (treasure chests) can be reached, but not
+                    // walked over
+                    if !self.data[neighbour].is_solid() {
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+        }
+
+        reached
+    }
+}
+
+#[cfg(test)]
+mod test_validation {
+    use super::*;
+    use crate::{
+        test_utils::{floor_from_rows, point},
+        ChestId,
+    };
+
+    #[test]
+    fn test_floor_validation() {
+        let valid = floor_from_rows(&[
+            "##########",
+            "#<.......#",
+            "#........#",
+            "#........#",
+            "####L#####",
+            "#........#",
+            "#........#",
+            "#........#",
+            "#.......>#",
+            "##########",
+        ]);
+        let mut floor = valid.clone();
+        *floor.at_mut(point(2, 2)) = DungeonTile::Key { door: point(4, 4) };
+        assert_eq!(floor.validate(), Ok(()));
+
+        // the key is behind it's own door
+        let mut locked_out = valid.clone();
+        *locked_out.at_mut(point(6, 2)) = DungeonTile::Key { door: point(4, 4) };
+        assert_eq!(
+            locked_out.validate(),
+            Err(vec![FloorViolation::LockedOut(point(4, 4))])
+        );
+
+        // every violation is reported, not just the first
+        let mut broken = floor.clone();
+        *broken.at_mut(point(0, 3)) = DungeonTile::Empty;
+        *broken.at_mut(point(8, 8)) = DungeonTile::Empty;
+        *broken.at_mut(point(1, 8)) = DungeonTile::Exit;
+        *broken.at_mut(point(1, 7)) = DungeonTile::Exit;
+        *broken.at_mut(point(2, 8)) = DungeonTile::Key { door: point(1, 1) };
+        *broken.at_mut(point(3, 5)) = DungeonTile::TreasureChest {
+            contents: ChestId::new(0),
+        };
+        assert_eq!(
+            broken.validate(),
+            Err(vec![
+                FloorViolation::EntrancesAndExits {
+                    entrances: 1,
+                    exits: 2
+                },
+                FloorViolation::OpenEdge(point(0, 3)),
+                FloorViolation::MissingChestContents(point(3, 5)),
+                FloorViolation::KeyWithoutDoor(point(2, 8)),
+            ])
+        );
+
+        let mut walled_off = valid.clone();
+        *walled_off.at_mut(point(4, 4)) = DungeonTile::Wall;
+        assert_eq!(
+            walled_off.validate().unwrap_err()[0],
+            FloorViolation::UnreachableExit {
+                entrance: point(1, 1),
+                exit: point(8, 8)
+            }
+        );
+        assert_eq!(walled_off.validate().unwrap_err().len(), 32);
+
+        let mut truncated = valid;
+        truncated.data.pop();
+        assert_eq!(
+            truncated.validate(),
+            Err(vec![FloorViolation::InvalidDimensions {
+                width: 10,
+                height: 10,
+                tiles: 99
+            }])
+        );
+    }
+}
@@ -0,0 +1,502 @@
+use std::{convert::TryFrom, error::Error, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Point;
+
+/// A hand-designed room, stamped into the caves of a generated
+/// [`Floor`](crate::Floor) as-is: a shrine, a boss room, a shop, or anything
+/// else that has to be in the floor exactly the way it was drawn. See
+/// [`VaultConfig`](crate::VaultConfig).
+///
+/// Vaults are placed after the caves have been formed, each with at least one
+/// of it's doors opening onto the part of the floor that can be reached from
+/// the entrance, before the caves are joined up. The tunnels joining up the
+/// caves go around the vaults (or through their doors), never carving through
+/// their walls, and the vaults are never smoothed away.
+///
+/// # Examples
+/// ```rust
+/// use dungeon::{DungeonType, Floor, FloorId, GenerationConfig, Seed, Vault, VaultConfig};
+/// use std::convert::TryInto;
+///
+/// let shrine = Vault {
+///     name: "shrine".to_string(),
+///     layout: "#####\n#...#\n#.@.#\n#...#\n##+##".parse().unwrap(),
+/// };
+/// let config = GenerationConfig {
+///     vaults: VaultConfig {
+///         prefabs: vec![shrine],
+///         ..VaultConfig::default()
+///     },
+///     ..GenerationConfig::default()
+/// };
+///
+/// let floor = Floor::try_new(
+///     FloorId::new(0),
+///     DungeonType::Cave,
+///     50.try_into().unwrap(),
+///     50.try_into().unwrap(),
+///     &config,
+///     Seed::new(0),
+///     false,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(floor.vaults.len(), 1);
+/// for vault in &floor.vaults {
+///     assert_eq!(vault.name, "shrine");
+///     assert_eq!(vault.anchors.len(), 1);
+///     assert!(vault.contains(vault.doors[0]));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Vault {
+    /// The name of the vault, copied to every [`PlacedVault`] so that the
+    /// game can tell which vault was placed where.
+    pub name: String,
+    /// The tiles of the vault.
+    pub layout: VaultLayout,
+}
+
+/// A single tile of a [`VaultLayout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VaultTile {
+    /// A wall, written as a `#`. Stays a wall no matter what.
+    Wall,
+    /// An empty tile, written as a `.`.
+    Floor,
+    /// An empty tile that leads out of the vault, written as a `+`. Every
+    /// vault has at least one; the tunnels joining the vault to the rest of
+    /// the floor end at it's doors.
+    Door,
+    /// An empty tile marking a point of interest, written as a `@`: where the
+    /// altar of a shrine or the boss of a boss room should go. See
+    /// [`PlacedVault::anchors`].
+    Anchor,
+}
+
+/// The tiles of a [`Vault`], drawn as an ascii grid.
+///
+/// Every tile is written as one of the characters listed in [`VaultTile`], or
+/// as a space, which leaves the tile of the floor underneath the vault as it
+/// was; this allows vaults that aren't rectangles. Rows shorter than the
+/// longest one are padded with spaces. Layouts are serialized the same way.
+///
+/// # Examples
+/// ```rust
+/// use dungeon::{VaultLayout, VaultTile};
+///
+/// let layout: VaultLayout = " ### \n##.##\n+.@.+\n##.##\n ###".parse().unwrap();
+///
+/// assert_eq!((layout.width(), layout.height()), (5, 5));
+/// assert_eq!(layout.tile(0, 0), None);
+/// assert_eq!(layout.tile(2, 0), Some(VaultTile::Door));
+/// assert_eq!(layout.tile(2, 2), Some(VaultTile::Anchor));
+/// assert_eq!(layout.to_string().parse::<VaultLayout>().unwrap(), layout);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct VaultLayout {
+    /// How many tiles wide the layout is.
+    width: usize,
+    /// Each tile of the layout, row by row; `None` for the tiles that are
+    /// left untouched.
+    tiles: Vec<Option<VaultTile>>,
+}
+
+impl VaultLayout {
+    /// Parses a layout from an ascii grid; see the type-level documentation
+    /// for the characters. Leading and trailing empty lines are ignored.
+    ///
+    /// # Errors
+    /// Errors if the grid has no tiles, contains an unknown character, has no
+    /// doors, or has a door that doesn't lead out of the vault (with no
+    /// untouched tile or edge of the layout next to it).
+    pub fn from_ascii(ascii: &str) -> Result<Self, VaultLayoutError> {
+        let lines = ascii.lines().collect::<Vec<_>>();
+        let first = lines.iter().position(|line| !line.trim().is_empty());
+        let last = lines.iter().rposition(|line| !line.trim().is_empty());
+        let lines = match (first, last) {
+            (Some(first), Some(last)) => &lines[first..=last],
+            _ => return Err(VaultLayoutError::Empty),
+        };
+
+        let width = lines
+            .iter()
+            .map(|line| line.trim_end().chars().count())
+            .max()
+            .unwrap_or_default();
+        let mut tiles = vec![];
+
+        for (row, line) in lines.iter().enumerate() {
+            let mut row_tiles = vec![None; width];
+            for (column, character) in line.trim_end().chars().enumerate() {
+                row_tiles[column] = match character {
+                    ' ' => None,
+                    '#' => Some(VaultTile::Wall),
+                    '.' => Some(VaultTile::Floor),
+                    '+' => Some(VaultTile::Door),
+                    '@' => Some(VaultTile::Anchor),
+                    _ => {
+                        return Err(VaultLayoutError::InvalidCharacter {
+                            row,
+                            column,
+                            character,
+                        })
+                    }
+                };
+            }
+            tiles.extend(row_tiles);
+        }
+
+        let layout = Self { width, tiles };
+
+        let doors = layout.tiles_where(|tile| tile == VaultTile::Door);
+        if doors.is_empty() {
+            return Err(VaultLayoutError::NoDoors);
+        }
+        for (row, column) in doors {
+            if layout.outside_neighbours(row, column).next().is_none() {
+                return Err(VaultLayoutError::EnclosedDoor { row, column });
+            }
+        }
+
+        Ok(layout)
+    }
+
+    /// How many tiles wide the layout is.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// How many tiles tall the layout is.
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.tiles.len() / self.width
+    }
+
+    /// Returns the tile at the row and column, or `None` if the tile is left
+    /// untouched (including every tile outside of the layout).
+    #[must_use]
+    pub fn tile(&self, row: usize, column: usize) -> Option<VaultTile> {
+        if column < self.width {
+            self.tiles.get(row * self.width + column).copied().flatten()
+        } else {
+            None
+        }
+    }
+
+    /// The row and column of every tile that isn't left untouched and
+    /// matches the predicate, row by row.
+    pub(crate) fn tiles_where(&self, predicate: impl Fn(VaultTile) -> bool) -> Vec<(usize, usize)> {
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| tile.is_some_and(&predicate))
+            .map(|(index, _)| (index / self.width, index % self.width))
+            .collect()
+    }
+
+    /// The offsets (from the tile at the row and column) of the 4 tiles next
+    /// to it that are left untouched by the layout, including the ones
+    /// outside of it.
+    pub(crate) fn outside_neighbours(
+        &self,
+        row: usize,
+        column: usize,
+    ) -> impl Iterator<Item = (isize, isize)> + '_ {
+        IntoIterator::into_iter([(-1, 0), (1, 0), (0, -1), (0, 1)]).filter(
+            move |&(row_offset, column_offset)| {
+                let neighbour_row = row as isize + row_offset;
+                let neighbour_column = column as isize + column_offset;
+                neighbour_row < 0
+                    || neighbour_column < 0
+                    || self
+                        .tile(neighbour_row as usize, neighbour_column as usize)
+                        .is_none()
+            },
+        )
+    }
+}
+
+impl fmt::Display for VaultLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = self
+            .tiles
+            .chunks(self.width)
+            .map(|row| {
+                row.iter()
+                    .map(|tile| match tile {
+                        None => ' ',
+                        Some(VaultTile::Wall) => '#',
+                        Some(VaultTile::Floor) => '.',
+                        Some(VaultTile::Door) => '+',
+                        Some(VaultTile::Anchor) => '@',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+
+        f.write_str(&rows.join("\n"))
+    }
+}
+
+impl FromStr for VaultLayout {
+    type Err = VaultLayoutError;
+
+    fn from_str(ascii: &str) -> Result<Self, Self::Err> {
+        Self::from_ascii(ascii)
+    }
+}
+
+impl TryFrom<String> for VaultLayout {
+    type Error = VaultLayoutError;
+
+    fn try_from(ascii: String) -> Result<Self, Self::Error> {
+        Self::from_ascii(&ascii)
+    }
+}
+
+impl From<VaultLayout> for String {
+    fn from(layout: VaultLayout) -> Self {
+        layout.to_string()
+    }
+}
+
+/// A [`Vault`] that was stamped into a [`Floor`](crate::Floor). See
+/// [`Floor::vaults`](crate::Floor::vaults).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlacedVault {
+    /// The [`Vault::name`] of the vault.
+    pub name: String,
+    /// The point of the top left corner of the vault's layout.
+    pub top_left: Point,
+    /// The [`Vault::layout`] of the vault.
+    pub layout: VaultLayout,
+    /// The points of every [`VaultTile::Door`] of the vault.
+    pub doors: Vec<Point>,
+    /// The points of every [`VaultTile::Anchor`] of the vault.
+    pub anchors: Vec<Point>,
+}
+
+impl PlacedVault {
+    /// Returns `true` if the point is one of the tiles of the vault; the
+    /// tiles left untouched by it's layout aren't part of it.
+    #[must_use]
+    pub fn contains(&self, point: Point) -> bool {
+        let row = point.row.get().as_unbounded() - self.top_left.row.get().as_unbounded();
+        let column = point.column.get().as_unbounded() - self.top_left.column.get().as_unbounded();
+
+        row >= 0 && column >= 0 && self.layout.tile(row as usize, column as usize).is_some()
+    }
+}
+
+/// Error returned when parsing a [`VaultLayout`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VaultLayoutError {
+    /// The layout had no tiles.
+    Empty,
+    /// A character of the ascii grid wasn't one of the characters listed in
+    /// [`VaultTile`] or a space.
+    InvalidCharacter {
+        /// The row, counting from the first non-empty line.
+        row: usize,
+        /// The column.
+        column: usize,
+        /// The invalid character.
+        character: char,
+    },
+    /// The layout had no [`VaultTile::Door`]s, so it could never be reached.
+    NoDoors,
+    /// A [`VaultTile::Door`] had no untouched tile or edge of the layout next
+    /// to it, so it didn't lead out of the vault.
+    EnclosedDoor {
+        /// The row of the door, counting from the first non-empty line.
+        row: usize,
+        /// The column of the door.
+        column: usize,
+    },
+}
+
+impl fmt::Display for VaultLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("vault layout has no tiles"),
+            Self::InvalidCharacter {
+                row,
+                column,
+                character,
+            } => f.write_fmt(format_args!(
+                "invalid character {:?} in vault layout at row {}, column {}",
+                character, row, column
+            )),
+            Self::NoDoors => f.write_str("vault layout has no doors"),
+            Self::EnclosedDoor { row, column } => f.write_fmt(format_args!(
+                "the door at row {}, column {} of the vault layout doesn't lead out of it",
+                row, column
+            )),
+        }
+    }
+}
+
+impl Error for VaultLayoutError {}
+
+#[cfg(test)]
+mod test_vault {
+    use std::convert::TryInto;
+
+    use super::*;
+    use crate::{
+        test_utils::cave_floor, DungeonType, Floor, FloorId, GenerationConfig,
+        GenerationConfigError, Seed, VaultConfig,
+    };
+
+    #[test]
+    fn test_vaults() {
+        let shrine = Vault {
+            name: "shrine".to_string(),
+            layout: "#####\n#...#\n#.@.#\n#...#\n##+##".parse().unwrap(),
+        };
+        let boss = Vault {
+            name: "boss".to_string(),
+            layout: VaultLayout::from_ascii(
+                "  #####\n ##...##\n##.....##\n+...@...+\n##.....##\n ##...##\n  #####",
+            )
+            .unwrap(),
+        };
+        assert_eq!((boss.layout.width(), boss.layout.height()), (9, 7));
+        assert_eq!(boss.layout.tile(0, 8), None);
+
+        let config = GenerationConfig {
+            vaults: VaultConfig {
+                min_vaults: 2,
+                max_vaults: 3,
+                prefabs: vec![shrine, boss.clone()],
+            },
+            ..GenerationConfig::default()
+        };
+        assert!(config.validate().is_ok());
+
+        for dungeon_type in [DungeonType::Cave, DungeonType::Forest] {
+            for seed in 0..3 {
+                let floor = Floor::try_new(
+                    FloorId::new(0),
+                    dungeon_type,
+                    60.try_into().unwrap(),
+                    50.try_into().unwrap(),
+                    &config,
+                    Seed::new(seed),
+                    false,
+                )
+                .unwrap();
+                assert!(!floor.vaults.is_empty());
+                assert_eq!(floor.validate(), Ok(()));
+
+                let from_entrance = floor.distance_map(floor.entrance());
+                for vault in &floor.vaults {
+                    // every vault is stamped exactly as it was drawn, with nothing
+                    // else placed inside of it
+                    for (point, tile) in floor.iter_points_and_tiles() {
+                        if !vault.contains(point) {
+                            continue;
+                        }
+                        let row = point.row.get().as_unbounded()
+                            - vault.top_left.row.get().as_unbounded();
+                        let column = point.column.get().as_unbounded()
+                            - vault.top_left.column.get().as_unbounded();
+
+                        match vault.layout.tile(row as usize, column as usize) {
+                            Some(VaultTile::Wall) => assert!(tile.is_wall()),
+                            Some(_) => assert!(tile.is_empty(), "{:?} in {}", tile, vault.name),
+                            None => unreachable!(),
+                        }
+                        assert!(!floor.spawn_points.contains(&point));
+                    }
+
+                    // and can be reached from the entrance
+                    assert!(vault.doors.iter().chain(&vault.anchors).all(|&point| {
+                        vault.contains(point) && from_entrance.get(point).is_some()
+                    }));
+                    assert_eq!(vault.anchors.len(), 1);
+
+                    for other in floor.vaults.iter().filter(|other| *other != vault) {
+                        assert!(vault.doors.iter().all(|&door| !other.contains(door)));
+                    }
+                }
+
+                let json = serde_json::to_string(&floor).unwrap();
+                assert_eq!(serde_json::from_str::<Floor>(&json).unwrap(), floor);
+            }
+        }
+
+        // without any prefabs, nothing changes
+        let create = |config: &GenerationConfig| cave_floor(40, config, 3);
+        let without_prefabs = create(&GenerationConfig {
+            vaults: VaultConfig {
+                min_vaults: 5,
+                max_vaults: 5,
+                prefabs: vec![],
+            },
+            ..GenerationConfig::default()
+        });
+        assert!(without_prefabs.vaults.is_empty());
+        assert_eq!(without_prefabs, create(&GenerationConfig::default()));
+
+        // vaults that don't fit are left out
+        let huge = Vault {
+            name: "huge".to_string(),
+            layout: format!("{}\n+", "#".repeat(50)).parse().unwrap(),
+        };
+        let floor = create(&GenerationConfig {
+            vaults: VaultConfig {
+                prefabs: vec![huge],
+                ..VaultConfig::default()
+            },
+            ..GenerationConfig::default()
+        });
+        assert!(floor.vaults.is_empty());
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(json.contains("\"layout\":\"#####\\n#...#\\n#.@.#\\n#...#\\n##+##\""));
+        assert_eq!(
+            serde_json::from_str::<GenerationConfig>(&json).unwrap(),
+            config
+        );
+
+        let invalid = GenerationConfig {
+            vaults: VaultConfig {
+                min_vaults: 2,
+                max_vaults: 1,
+                prefabs: vec![boss],
+            },
+            ..GenerationConfig::default()
+        };
+        assert_eq!(
+            invalid.validate(),
+            Err(GenerationConfigError::VaultRange { min: 2, max: 1 })
+        );
+
+        assert_eq!(
+            VaultLayout::from_ascii(" \n\n"),
+            Err(VaultLayoutError::Empty)
+        );
+        assert_eq!(
+            VaultLayout::from_ascii("###\n#.#\n###"),
+            Err(VaultLayoutError::NoDoors)
+        );
+        assert_eq!(
+            VaultLayout::from_ascii("###\n#+#\n###"),
+            Err(VaultLayoutError::EnclosedDoor { row: 1, column: 1 })
+        );
+        assert_eq!(
+            VaultLayout::from_ascii("#+#\n#?#"),
+            Err(VaultLayoutError::InvalidCharacter {
+                row: 1,
+                column: 1,
+                character: '?'
+            })
+        );
+    }
+}
@@ -0,0 +1,621 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    convert::TryFrom,
+};
+
+use bounded_int::BoundedInt;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    floor_generator, DungeonTile, DungeonType, Floor, FloorBuilder, FloorId, FloorRequest,
+    GenerationConfig, GenerationError, Point, Seed, MAX_FLOOR_SIZE, MIN_FLOOR_SIZE,
+};
+
+/// The position of a chunk in a [`World`], in chunks. Chunk `(0, 0)` starts at
+/// [`WorldPoint`] `(0, 0)`, and chunks extend infinitely in every direction.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+pub struct ChunkId {
+    /// The row of the chunk; negative rows are above chunk `(0, 0)`.
+    pub row: i32,
+    /// The column of the chunk; negative columns are left of chunk `(0, 0)`.
+    pub column: i32,
+}
+
+impl ChunkId {
+    /// Returns the [`FloorId`] the chunk is generated with: how many chunks
+    /// away from chunk `(0, 0)` it is (walking up, down, left and right),
+    /// capped at [`u16::MAX`]. The further out a chunk is, the deeper it
+    /// counts as, so the rarer the loot in it's treasure chests gets.
+    #[must_use]
+    pub fn floor_id(self) -> FloorId {
+        let distance = self.row.unsigned_abs() + self.column.unsigned_abs();
+        FloorId::new(u16::try_from(distance).unwrap_or(u16::MAX))
+    }
+}
+
+/// A point somewhere in a [`World`]. Unlike a [`Point`], which is bound to a
+/// single floor, a world point can be anywhere, including at negative
+/// coordinates.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+pub struct WorldPoint {
+    /// The row of the point.
+    pub row: i64,
+    /// The column of the point.
+    pub column: i64,
+}
+
+/// Where a chunk of a [`World`] sits in the world's noise field, so that the
+/// noise of neighbouring chunks lines up. Every chunk spans `1` unit of the
+/// noise field in each direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct NoiseOffset {
+    /// The seed of the world's noise field.
+    pub(crate) seed: u32,
+    /// The row of the chunk, in chunks.
+    pub(crate) row: f64,
+    /// The column of the chunk, in chunks.
+    pub(crate) column: f64,
+}
+
+/// The things that are derived from a world's seed, each getting it's own
+/// stream of random numbers.
+#[derive(Debug, Clone, Copy)]
+enum WorldStream {
+    /// The seed of the world's noise field.
+    Noise,
+    /// The seeds of a chunk.
+    Chunk,
+    /// The position of the gate on the top edge of a chunk.
+    TopGate,
+    /// The position of the gate on the left edge of a chunk.
+    LeftGate,
+}
+
+/// A side of a chunk, where it meets one of it's neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    /// The top edge, shared with the chunk above.
+    Top,
+    /// The bottom edge, shared with the chunk below.
+    Bottom,
+    /// The left edge, shared with the chunk to the left.
+    Left,
+    /// The right edge, shared with the chunk to the right.
+    Right,
+}
+
+/// An open world made of [`Floor`]-sized chunks, for maps far larger than
+/// [`MAX_FLOOR_SIZE`].
+///
+/// Chunks are generated lazily, the first time they are asked for, with the
+/// cellular automata recipe of the world's [`DungeonType`] (the
+/// [algorithms](GenerationConfig::algorithms) and the
+/// [mask](GenerationConfig::mask) of the config aren't used).
+/// Every chunk is generated from the world's seed and it's own position
+/// alone, so the same world is produced no matter which order the chunks are
+/// visited in, and chunks can be unloaded and generated again later.
+///
+/// The noise of every chunk is sampled from a single noise field spanning the
+/// whole world, so the terrain flows from one chunk into the next. Each edge
+/// between two chunks has a single gate: an opening at the same spot on both
+/// sides of the edge, with a tunnel leading from it to the caves around the
+/// chunk's entrance, so that every chunk can be walked to from every other
+/// chunk. Gates are the only open tiles on the edges of a chunk, and
+/// [`Floor::validate`] reports them as
+/// [`OpenEdge`](crate::FloorViolation::OpenEdge)s.
+///
+/// # Examples
+/// ```rust
+/// use dungeon::{ChunkId, DungeonType, GenerationConfig, Seed, World, WorldPoint};
+/// use std::convert::TryInto;
+///
+/// let mut world = World::new(
+///     50.try_into().unwrap(),
+///     50.try_into().unwrap(),
+///     DungeonType::Cave,
+///     GenerationConfig::default(),
+///     Seed::new(0),
+/// )
+/// .unwrap();
+///
+/// // a point far outside of any single floor
+/// let point = WorldPoint { row: -120, column: 1000 };
+/// let (chunk, local) = world.chunk_at(point).unwrap();
+/// assert_eq!(chunk, ChunkId { row: -3, column: 20 });
+///
+/// let tile = world.tile(point).unwrap();
+/// assert_eq!(tile, *world.chunk(chunk).unwrap().at(local));
+/// assert_eq!(world.loaded_chunks().count(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct World {
+    /// The width of every chunk.
+    chunk_width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+    /// The height of every chunk.
+    chunk_height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+    /// The type of the world, which decides how the chunks are generated.
+    dungeon_type: DungeonType,
+    /// The parameters the chunks are generated with.
+    config: GenerationConfig,
+    /// The seed the world is generated from.
+    seed: Seed,
+    /// The chunks that have been generated so far.
+    chunks: BTreeMap<ChunkId, Floor>,
+}
+
+impl World {
+    /// How many seeds are tried for a chunk before giving up on it; see
+    /// [`World::generate_chunk`].
+    pub const CHUNK_ATTEMPTS: u8 = 8;
+
+    /// Creates a new world with chunks of the provided size. No chunks are
+    /// generated until they are asked for.
+    ///
+    /// # Errors
+    /// Errors if the [`GenerationConfig`] is invalid.
+    pub fn new(
+        chunk_width: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        chunk_height: BoundedInt<MIN_FLOOR_SIZE, MAX_FLOOR_SIZE>,
+        dungeon_type: DungeonType,
+        config: GenerationConfig,
+        seed: Seed,
+    ) -> Result<Self, GenerationError> {
+        config.validate()?;
+
+        Ok(Self {
+            chunk_width,
+            chunk_height,
+            dungeon_type,
+            config,
+            seed,
+            chunks: BTreeMap::new(),
+        })
+    }
+
+    /// Returns the seed the world is generated from.
+    #[must_use]
+    pub const fn seed(&self) -> Seed {
+        self.seed
+    }
+
+    /// Returns the chunk at the position, generating it first if it hasn't
+    /// been generated yet.
+    ///
+    /// # Errors
+    /// Errors if the chunk couldn't be generated with any of the seeds tried
+    /// for it, with the error of the last attempt. See
+    /// [`World::generate_chunk`].
+    pub fn chunk(&mut self, id: ChunkId) -> Result<&Floor, GenerationError> {
+        if !self.chunks.contains_key(&id) {
+            let chunk = self.generate_chunk(id)?;
+            self.chunks.insert(id, chunk);
+        }
+
+        Ok(&self.chunks[&id])
+    }
+
+    /// Returns the chunk at the position if it has already been generated.
+    #[must_use]
+    pub fn loaded_chunk(&self, id: ChunkId) -> Option<&Floor> {
+        self.chunks.get(&id)
+    }
+
+    /// Returns an iterator over every chunk that has been generated so far,
+    /// ordered by position.
+    pub fn loaded_chunks(&self) -> impl Iterator<Item = (ChunkId, &Floor)> + '_ {
+        self.chunks.iter().map(|(&id, chunk)| (id, chunk))
+    }
+
+    /// Unloads the chunk at the position, returning it if it was loaded. Asking
+    /// for it again generates the exact same chunk.
+    pub fn unload(&mut self, id: ChunkId) -> Option<Floor> {
+        self.chunks.remove(&id)
+    }
+
+    /// Generates the chunk at the position, without storing it in the world.
+    /// Since it doesn't need mutable access to the world, this can be used to
+    /// generate many chunks in parallel; the results can be stored with
+    /// [`World::insert_chunk`].
+    ///
+    /// Up to [`World::CHUNK_ATTEMPTS`] seeds are tried for the chunk, until
+    /// one of them generates successfully. The seeds are derived from the
+    /// world's seed and the chunk's position, so the same chunk always ends
+    /// up with the same seed, and a chunk that fails once always fails.
+    ///
+    /// # Errors
+    /// Errors if the chunk couldn't be generated with any of the seeds tried
+    /// for it, with the error of the last attempt. See [`Floor::try_new`] for
+    /// the possible errors; additionally errors with
+    /// [`GenerationError::UnreachableTile`] if a gate can't be tunnelled to
+    /// from the caves around the entrance.
+    pub fn generate_chunk(&self, id: ChunkId) -> Result<Floor, GenerationError> {
+        let mut seeds = self.rng(WorldStream::Chunk, id);
+        let noise = NoiseOffset {
+            seed: self.rng(WorldStream::Noise, ChunkId::default()).gen(),
+            row: f64::from(id.row),
+            column: f64::from(id.column),
+        };
+
+        for attempt in 1..=Self::CHUNK_ATTEMPTS {
+            let request = FloorRequest {
+                id: id.floor_id(),
+                dungeon_type: self.dungeon_type,
+                width: self.chunk_width,
+                height: self.chunk_height,
+                config: &self.config,
+                seed: Seed::new(seeds.gen()),
+                entrance: None,
+                gif_output: false,
+            };
+
+            let chunk = FloorBuilder::create_chunk(&request, noise)
+                .and_then(|chunk| floor_generator::check_invariants(&chunk).map(|_| chunk))
+                .and_then(|chunk| self.open_gates(id, chunk));
+
+            match chunk {
+                Err(_) if attempt < Self::CHUNK_ATTEMPTS => {}
+                chunk => return chunk,
+            }
+        }
+
+        unreachable!("the last attempt always returns")
+    }
+
+    /// Stores a chunk generated with [`World::generate_chunk`] at the
+    /// position, replacing any chunk that was already there.
+    pub fn insert_chunk(&mut self, id: ChunkId, chunk: Floor) {
+        self.chunks.insert(id, chunk);
+    }
+
+    /// Returns the chunk the point is in, along with where the point is within
+    /// that chunk, or [`None`] if the point is so far out that the
+    /// [`ChunkId`] would overflow.
+    #[must_use]
+    pub fn chunk_at(&self, point: WorldPoint) -> Option<(ChunkId, Point)> {
+        let width = i64::from(self.chunk_width.as_unbounded());
+        let height = i64::from(self.chunk_height.as_unbounded());
+
+        let chunk = ChunkId {
+            row: i32::try_from(point.row.div_euclid(height)).ok()?,
+            column: i32::try_from(point.column.div_euclid(width)).ok()?,
+        };
+
+        let local = Point::from_index(
+            (point.row.rem_euclid(height) * width + point.column.rem_euclid(width)) as usize,
+            width as usize,
+        )
+        .expect("the point is within the chunk");
+
+        Some((chunk, local))
+    }
+
+    /// Returns the tile at the point, generating the chunk it's in first if it
+    /// hasn't been generated yet.
+    ///
+    /// # Errors
+    /// Errors if the point is outside of the world (see
+    /// [`World::chunk_at`]), or if the chunk the point is in couldn't be
+    /// generated (see [`World::chunk`]).
+    pub fn tile(&mut self, point: WorldPoint) -> Result<DungeonTile, GenerationError> {
+        let (chunk, local) = self
+            .chunk_at(point)
+            .ok_or(GenerationError::OutsideOfWorld(point))?;
+
+        Ok(*self.chunk(chunk)?.at(local))
+    }
+
+    /// Creates the rng for the stream of the world's seed, at the position.
+    fn rng(&self, stream: WorldStream, id: ChunkId) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(
+            self.seed.get() ^ (stream as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15),
+        );
+        rng.set_stream((u64::from(id.row as u32) << 32) | u64::from(id.column as u32));
+        rng
+    }
+
+    /// Returns the point of the gate on the side of the chunk. Both chunks
+    /// along an edge find the gate at the same place along it.
+    fn gate(&self, id: ChunkId, side: Side) -> Point {
+        let width = self.chunk_width.as_unbounded();
+        let height = self.chunk_height.as_unbounded();
+
+        // the top edge of a chunk is the bottom edge of the chunk above it,
+        // and the left edge is the right edge of the chunk to it's left
+        let (stream, edge) = match side {
+            Side::Top => (WorldStream::TopGate, id),
+            Side::Bottom => (
+                WorldStream::TopGate,
+                ChunkId {
+                    row: id.row.wrapping_add(1),
+                    ..id
+                },
+            ),
+            Side::Left => (WorldStream::LeftGate, id),
+            Side::Right => (
+                WorldStream::LeftGate,
+                ChunkId {
+                    column: id.column.wrapping_add(1),
+                    ..id
+                },
+            ),
+        };
+
+        // never in a corner
+        let along = match side {
+            Side::Top | Side::Bottom => self.rng(stream, edge).gen_range(1..width - 1),
+            Side::Left | Side::Right => self.rng(stream, edge).gen_range(1..height - 1),
+        };
+
+        let (row, column) = match side {
+            Side::Top => (0, along),
+            Side::Bottom => (height - 1, along),
+            Side::Left => (along, 0),
+            Side::Right => (along, width - 1),
+        };
+
+        Point::from_index((row * width + column) as usize, width as usize)
+            .expect("the gate is within the chunk")
+    }
+
+    /// Opens the gates on every side of the chunk, tunnelling from each of
+    /// them to the caves around the entrance.
+    ///
+    /// Tunnels are only dug through walls that don't border on any other open
+    /// tiles, so that they don't lead around secret doors or into hidden
+    /// caves. A tunnel can join up with a tunnel dug from another gate.
+    fn open_gates(&self, id: ChunkId, mut chunk: Floor) -> Result<Floor, GenerationError> {
+        let width = chunk.width.as_unbounded() as usize;
+        let height = chunk.height.as_unbounded() as usize;
+        let entrance = chunk.entrance().expect("generated floors have an entrance");
+
+        // the open tiles around the entrance, and every tunnel dug so far
+        let regions = chunk.regions();
+        let mut connected = regions
+            .region_at(entrance)
+            .and_then(|region| regions.get(region))
+            .map(|region| {
+                region
+                    .points
+                    .iter()
+                    .map(|point| point.to_index(width))
+                    .collect::<BTreeSet<_>>()
+            })
+            .unwrap_or_default();
+
+        let point = |index| Point::from_index(index, width).expect("the index is in the chunk");
+        let is_edge = |index: usize| {
+            let (row, column) = (index / width, index % width);
+            row == 0 || column == 0 || row + 1 == height || column + 1 == width
+        };
+        let neighbours = |index| Point::neighbour_indices(index, width, height);
+
+        for side in IntoIterator::into_iter([Side::Top, Side::Bottom, Side::Left, Side::Right]) {
+            let gate = self.gate(id, side).to_index(width);
+            let inside = neighbours(gate)
+                .find(|&index| !is_edge(index))
+                .expect("gates aren't in the corners");
+
+            // a wall can be dug through if the only open tiles around it are
+            // around the entrance (or are part of the tunnel itself)
+            let can_dig = |data: &[DungeonTile], index: usize| {
+                data[index].is_wall()
+                    && !is_edge(index)
+                    && neighbours(index).all(|neighbour| {
+                        data[neighbour].is_wall() || connected.contains(&neighbour)
+                    })
+            };
+
+            let mut came_from = vec![None; chunk.data.len()];
+            came_from[inside] = Some(inside);
+            let mut queue = VecDeque::from(vec![inside]);
+            let mut reached = connected.contains(&inside).then_some(inside);
+
+            if reached.is_none() && can_dig(&chunk.data, inside) {
+                while let Some(index) = queue.pop_front() {
+                    if let Some(found) = neighbours(index).find(|n| connected.contains(n)) {
+                        reached = Some(found);
+                        came_from[found] = Some(index);
+                        break;
+                    }
+
+                    for neighbour in neighbours(index) {
+                        if came_from[neighbour].is_none() && can_dig(&chunk.data, neighbour) {
+                            came_from[neighbour] = Some(index);
+                            queue.push_back(neighbour);
+                        }
+                    }
+                }
+            }
+
+            let mut index = reached.ok_or(GenerationError::UnreachableTile {
+                entrance,
+                tile: point(gate),
+            })?;
+            while index != inside {
+                index = came_from[index].expect("the tunnel leads back to the gate");
+                chunk.data[index] = DungeonTile::Empty;
+                connected.insert(index);
+            }
+            chunk.data[gate] = DungeonTile::Empty;
+            connected.insert(gate);
+        }
+
+        Ok(chunk)
+    }
+}
+
+#[cfg(test)]
+mod test_world {
+    use std::convert::TryInto;
+
+    use super::*;
+
+    fn world(config: GenerationConfig) -> World {
+        World::new(
+            20.try_into().unwrap(),
+            10.try_into().unwrap(),
+            DungeonType::Cave,
+            config,
+            Seed::new(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_chunk_at() {
+        let world = world(GenerationConfig::default());
+
+        assert_eq!(
+            world.chunk_at(WorldPoint {
+                row: -1,
+                column: 20
+            }),
+            Some((
+                ChunkId { row: -1, column: 1 },
+                Point::from_index(9 * 20, 20).unwrap()
+            ))
+        );
+        assert_eq!(
+            world.chunk_at(WorldPoint {
+                row: i64::MAX,
+                column: 0
+            }),
+            None
+        );
+        assert_eq!(
+            world.chunk_at(WorldPoint {
+                row: 0,
+                column: i64::MIN
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn test_chunk_floor_ids() {
+        assert_eq!(ChunkId::default().floor_id(), FloorId::new(0));
+        assert_eq!(ChunkId { row: -2, column: 3 }.floor_id(), FloorId::new(5));
+        assert_eq!(
+            ChunkId {
+                row: i32::MIN,
+                column: i32::MAX
+            }
+            .floor_id(),
+            FloorId::new(u16::MAX)
+        );
+    }
+
+    #[test]
+    fn test_generate_chunk_gives_up() {
+        // no chunk is ever big enough for the exit to be this far away
+        let world = world(GenerationConfig {
+            min_exit_distance: u16::MAX,
+            ..GenerationConfig::default()
+        });
+
+        assert!(matches!(
+            world.generate_chunk(ChunkId::default()),
+            Err(GenerationError::ExitTooClose { min: u16::MAX, .. })
+        ));
+    }
+
+    #[test]
+    fn test_world_chunks() {
+        let new_world = || {
+            World::new(
+                40.try_into().unwrap(),
+                30.try_into().unwrap(),
+                DungeonType::Cave,
+                GenerationConfig::default(),
+                Seed::new(5),
+            )
+            .unwrap()
+        };
+        let ids = (-1..2)
+            .flat_map(|row| (-1..1).map(move |column| ChunkId { row, column }))
+            .collect::<Vec<_>>();
+
+        // chunks don't depend on the order they are generated in
+        let mut world = new_world();
+        let mut reversed = new_world();
+        for &id in &ids {
+            world.chunk(id).unwrap();
+        }
+        for &id in ids.iter().rev() {
+            reversed.chunk(id).unwrap();
+        }
+        assert!(world.loaded_chunks().eq(reversed.loaded_chunks()));
+
+        let unloaded = reversed.unload(ids[2]).unwrap();
+        assert_eq!(reversed.loaded_chunk(ids[2]), None);
+        assert_eq!(reversed.chunk(ids[2]).unwrap(), &unloaded);
+
+        // stitch the chunks together, and walk from the entrance of the first
+        // chunk to every other chunk through the gates between them
+        let (top, left) = (-30, -40);
+        let (height, width) = (90, 80);
+        let open = |world: &mut World, row: i64, column: i64| {
+            !world
+                .tile(WorldPoint {
+                    row: top + row,
+                    column: left + column,
+                })
+                .unwrap()
+                .is_solid()
+        };
+
+        let first = world.chunk(ids[0]).unwrap();
+        let entrance = first.entrance().unwrap();
+        let start = (
+            i64::from(entrance.row.get().as_unbounded()),
+            i64::from(entrance.column.get().as_unbounded()),
+        );
+
+        let mut reached = BTreeSet::new();
+        reached.insert(start);
+        let mut queue = vec![start];
+        while let Some((row, column)) = queue.pop() {
+            let neighbours = [
+                (row - 1, column),
+                (row + 1, column),
+                (row, column - 1),
+                (row, column + 1),
+            ];
+            for (row, column) in IntoIterator::into_iter(neighbours) {
+                if (0..height).contains(&row)
+                    && (0..width).contains(&column)
+                    && !reached.contains(&(row, column))
+                    && open(&mut world, row, column)
+                {
+                    reached.insert((row, column));
+                    queue.push((row, column));
+                }
+            }
+        }
+
+        assert_eq!(world.loaded_chunks().count(), ids.len());
+        for (id, chunk) in world.loaded_chunks() {
+            let entrance = chunk.entrance().unwrap();
+            let (point, local) = (
+                (
+                    i64::from(id.row) * 30 - top + i64::from(entrance.row.get().as_unbounded()),
+                    i64::from(id.column) * 40 - left
+                        + i64::from(entrance.column.get().as_unbounded()),
+                ),
+                world.chunk_at(WorldPoint {
+                    row: i64::from(id.row) * 30 + i64::from(entrance.row.get().as_unbounded()),
+                    column: i64::from(id.column) * 40
+                        + i64::from(entrance.column.get().as_unbounded()),
+                }),
+            );
+            assert_eq!(local, Some((id, entrance)));
+            assert!(reached.contains(&point), "chunk {:?} is cut off", id);
+        }
+    }
+}
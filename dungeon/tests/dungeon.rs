@@ -1,6 +1,6 @@
 use std::{convert::TryInto, num::NonZeroU16};
 
-use dungeon::{Dungeon, DungeonType};
+use dungeon::{Dungeon, DungeonType, GenerationConfig};
 
 #[test]
 fn test_dungeon_creation() {
@@ -9,8 +9,9 @@ fn test_dungeon_creation() {
         150.try_into().unwrap(),
         NonZeroU16::new(10).unwrap(),
         DungeonType::Cave,
+        &GenerationConfig::default(),
         false,
     );
 
-    std::fs::write("dungeon_test.gif", d.to_gif()).unwrap();
+    std::fs::write("dungeon_test.gif", d.to_gif().unwrap()).unwrap();
 }
@@ -23,3 +23,10 @@ pub const PLAYER_MOVEMENT_DELAY_SECONDS: f32 = 0.05;
 
 /// The amount of time, in seconds, it takes the player to travel between tiles.
 pub const PLAYER_MOVING_TIME_SECONDS: f32 = 0.2;
+
+/// How far, in tiles, the player can see.
+pub const PLAYER_SIGHT_RADIUS: u16 = 8;
+
+/// How bright the tiles the player remembers, but can't currently see, are
+/// drawn compared to the tiles they can see.
+pub const REMEMBERED_TILE_BRIGHTNESS: f32 = 0.4;
@@ -4,13 +4,18 @@ pub mod key_press_handling;
 pub mod player;
 
 use bevy::{ecs::schedule::ReportExecutionOrderAmbiguities, prelude::*, render::camera::Camera};
-use dungeon::{Dungeon, DungeonTile, DungeonType, Point};
+use dungeon::{
+    Dungeon, DungeonTile, DungeonType, ExplorationMap, ExplorationState, GenerationConfig, Point,
+};
 use std::{convert::TryInto, num::NonZeroU16, ops::Index};
 
 use crate::{
-    constants::{PLAYER_MOVEMENT_DELAY_SECONDS, PLAYER_MOVING_TIME_SECONDS, TILE_Z_INDEX},
+    constants::{
+        PLAYER_MOVEMENT_DELAY_SECONDS, PLAYER_MOVING_TIME_SECONDS, PLAYER_SIGHT_RADIUS,
+        REMEMBERED_TILE_BRIGHTNESS, TILE_Z_INDEX,
+    },
     key_press_handling::KeyPressTime,
-    player::{Player, PlayerDirection, PlayerState},
+    player::{Keys, Player, PlayerDirection, PlayerState},
     utils::{player_sprite_bundle, point_to_transform},
 };
 
@@ -27,6 +32,7 @@ fn main() {
             50_i32.try_into().unwrap(),
             NonZeroU16::new(1).unwrap(),
             DungeonType::Cave,
+            &GenerationConfig::default(),
             false,
         ))
         .add_plugins(DefaultPlugins)
@@ -53,6 +59,18 @@ fn main() {
                         .label("smooth_player_movement")
                         .after("player_movement_input_handling"),
                 )
+                .with_system(
+                    pick_up_keys
+                        .system()
+                        .label("pick_up_keys")
+                        .after("smooth_player_movement"),
+                )
+                .with_system(
+                    update_exploration
+                        .system()
+                        .label("update_exploration")
+                        .after("pick_up_keys"),
+                )
                 .with_system(
                     camera_player_tracking
                         .exclusive_system()
@@ -73,53 +91,131 @@ fn main() {
 fn setup(
     mut commands: Commands,
     server: Res<AssetServer>,
+    dungeon: Res<Dungeon>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
     server.watch_for_changes().unwrap();
     commands.spawn_bundle(OrthographicCameraBundle::new_2d());
-    commands.insert_resource(Materials {
-        empty_material: materials.add(
-            // Color::WHITE.into()
-            server.load("empty.png").into(),
-        ),
-        wall_material: materials.add(Color::BLACK.into()),
-        secret_door_material: materials.add(Color::RED.into()),
-        secret_passage_material: materials.add(Color::LIME_GREEN.into()),
-        treasure_chest_material: materials.add(Color::BLUE.into()),
-        entrance_material: materials.add(Color::PINK.into()),
-        exit_material: materials.add(Color::PURPLE.into()),
-        player_material: materials.add(server.load("arrow.png").into()),
-    });
+    commands.insert_resource(Materials::new(
+        dungeon.dungeon_type,
+        &server,
+        &mut materials,
+    ));
+}
+
+/// Marks the sprite of a tile of the floor. Which tile it is is looked up in
+/// the floor by it's [`Position`], since the tiles can change (e.g. when a
+/// door is opened or a key is picked up).
+pub struct Tile;
+
+/// The materials used to draw a tile: one for when the player can see it, and
+/// a dimmed one for when the player only remembers it.
+pub struct TileMaterial {
+    visible: Handle<ColorMaterial>,
+    remembered: Handle<ColorMaterial>,
 }
 
-pub struct Tile {
-    #[allow(dead_code)]
-    tile_type: DungeonTile,
+impl TileMaterial {
+    /// Adds the material, along with it's dimmed version, to the assets.
+    fn new(material: ColorMaterial, materials: &mut Assets<ColorMaterial>) -> Self {
+        let color = material.color;
+        let remembered = ColorMaterial {
+            color: Color::rgba(
+                color.r() * REMEMBERED_TILE_BRIGHTNESS,
+                color.g() * REMEMBERED_TILE_BRIGHTNESS,
+                color.b() * REMEMBERED_TILE_BRIGHTNESS,
+                color.a(),
+            ),
+            texture: material.texture.clone(),
+        };
+
+        Self {
+            visible: materials.add(material),
+            remembered: materials.add(remembered),
+        }
+    }
 }
 
 pub struct Materials {
-    empty_material: Handle<ColorMaterial>,
-    wall_material: Handle<ColorMaterial>,
-    secret_door_material: Handle<ColorMaterial>,
-    secret_passage_material: Handle<ColorMaterial>,
-    treasure_chest_material: Handle<ColorMaterial>,
-    entrance_material: Handle<ColorMaterial>,
-    exit_material: Handle<ColorMaterial>,
+    empty_material: TileMaterial,
+    wall_material: TileMaterial,
+    secret_door_material: TileMaterial,
+    open_secret_door_material: TileMaterial,
+    secret_passage_material: TileMaterial,
+    treasure_chest_material: TileMaterial,
+    entrance_material: TileMaterial,
+    exit_material: TileMaterial,
+    key_material: TileMaterial,
+    /// Used for every tile the player hasn't seen yet.
+    unseen_material: Handle<ColorMaterial>,
     player_material: Handle<ColorMaterial>,
 }
 
+impl Materials {
+    /// Creates the materials used to draw the tiles of a dungeon of the
+    /// provided type.
+    fn new(
+        dungeon_type: DungeonType,
+        server: &AssetServer,
+        materials: &mut Assets<ColorMaterial>,
+    ) -> Self {
+        let (empty_material, wall_material) = match dungeon_type {
+            DungeonType::Forest => (
+                // forest floor
+                TileMaterial::new(Color::rgb(0.34, 0.49, 0.2).into(), materials),
+                // trees
+                TileMaterial::new(Color::DARK_GREEN.into(), materials),
+            ),
+            _ => (
+                TileMaterial::new(
+                    // Color::WHITE.into()
+                    server.load("empty.png").into(),
+                    materials,
+                ),
+                TileMaterial::new(Color::BLACK.into(), materials),
+            ),
+        };
+
+        Self {
+            empty_material,
+            wall_material,
+            secret_door_material: TileMaterial::new(Color::RED.into(), materials),
+            open_secret_door_material: TileMaterial::new(Color::ORANGE.into(), materials),
+            secret_passage_material: TileMaterial::new(Color::LIME_GREEN.into(), materials),
+            treasure_chest_material: TileMaterial::new(Color::BLUE.into(), materials),
+            entrance_material: TileMaterial::new(Color::PINK.into(), materials),
+            exit_material: TileMaterial::new(Color::PURPLE.into(), materials),
+            key_material: TileMaterial::new(Color::CYAN.into(), materials),
+            unseen_material: materials.add(Color::BLACK.into()),
+            player_material: materials.add(server.load("arrow.png").into()),
+        }
+    }
+
+    /// Returns the material to draw the tile with, depending on how much of
+    /// it the player knows about.
+    fn tile(&self, tile: DungeonTile, state: ExplorationState) -> Handle<ColorMaterial> {
+        match state {
+            ExplorationState::Unseen => self.unseen_material.clone(),
+            ExplorationState::Remembered => self[tile].remembered.clone(),
+            ExplorationState::Visible => self[tile].visible.clone(),
+        }
+    }
+}
+
 impl Index<DungeonTile> for Materials {
-    type Output = Handle<ColorMaterial>;
+    type Output = TileMaterial;
 
     fn index(&self, index: DungeonTile) -> &Self::Output {
         match index {
             DungeonTile::Empty => &self.empty_material,
             DungeonTile::Wall => &self.wall_material,
+            DungeonTile::SecretDoor { is_open: true, .. } => &self.open_secret_door_material,
             DungeonTile::SecretDoor { .. } => &self.secret_door_material,
             DungeonTile::SecretPassage => &self.secret_passage_material,
             DungeonTile::TreasureChest { .. } => &self.treasure_chest_material,
             DungeonTile::Entrance => &self.entrance_material,
             DungeonTile::Exit => &self.exit_material,
+            DungeonTile::Key { .. } => &self.key_material,
         }
     }
 }
@@ -134,22 +230,33 @@ fn spawn_player_and_board(
     materials: Res<Materials>,
 ) {
     let floor = dungeon.floors.first().unwrap();
+    let entrance = floor.entrance().unwrap();
 
-    for (point, tile) in floor.iter_points_and_tiles() {
-        if matches!(tile, DungeonTile::Entrance) {
-            commands
-                .spawn_bundle(player_sprite_bundle(&materials, point, floor))
-                .insert(Player)
-                .insert(PlayerDirection::Up)
-                .insert(PlayerState::Still)
-                .insert(Position(point));
-        }
+    let mut exploration = ExplorationMap::new(floor);
+    exploration.update_from_fov(floor, entrance, Some(PLAYER_SIGHT_RADIUS));
+
+    commands
+        .spawn_bundle(player_sprite_bundle(&materials, entrance, floor))
+        .insert(Player)
+        .insert(PlayerDirection::Up)
+        .insert(PlayerState::Still)
+        .insert(Keys::default())
+        .insert(Position(entrance));
 
+    for (point, tile) in floor.iter_points_and_tiles() {
         commands
-            .spawn_bundle(utils::tile_sprite_bundle(&materials, tile, point, floor))
-            .insert(Tile { tile_type: *tile })
+            .spawn_bundle(utils::tile_sprite_bundle(
+                &materials,
+                tile,
+                exploration.get(point),
+                point,
+                floor,
+            ))
+            .insert(Tile)
             .insert(Position(point));
     }
+
+    commands.insert_resource(exploration);
 }
 pub mod utils;
 
@@ -200,6 +307,53 @@ fn smooth_player_movement(
     }
 }
 
+/// Picks up the key the player is standing on, if there is one.
+fn pick_up_keys(
+    mut dungeon: ResMut<Dungeon>,
+    mut player: Query<(&Position, &mut Keys), (With<Player>, Changed<Position>)>,
+) {
+    for (position, mut keys) in player.iter_mut() {
+        // only borrow the dungeon mutably when there is a key, so that it
+        // isn't marked as changed on every step
+        if dungeon.floors[0].at(position.0).is_key() {
+            keys.0.extend(dungeon.floors[0].take_key(position.0));
+        }
+    }
+}
+
+/// Updates what the player has seen every time they move onto a new tile or
+/// the floor changes, and redraws the tiles accordingly: unseen tiles are
+/// black, and tiles the player remembers but can't currently see are dimmed.
+fn update_exploration(
+    dungeon: Res<Dungeon>,
+    materials: Res<Materials>,
+    mut exploration: ResMut<ExplorationMap>,
+    player_position: Query<&Position, With<Player>>,
+    player_moved: Query<Entity, (With<Player>, Changed<Position>)>,
+    mut tiles: Query<(&Position, &mut Handle<ColorMaterial>), With<Tile>>,
+) {
+    let floor = dungeon.floors.first().unwrap();
+
+    if player_moved.iter().next().is_none() && !dungeon.is_changed() {
+        return;
+    }
+
+    let position = match player_position.single() {
+        Ok(position) => position,
+        Err(_) => return,
+    };
+
+    exploration.update_from_fov(floor, position.0, Some(PLAYER_SIGHT_RADIUS));
+
+    for (position, mut material) in tiles.iter_mut() {
+        let new_material = materials.tile(*floor.at(position.0), exploration.get(position.0));
+        // only touch the handle if it changed, to avoid needless change detection
+        if *material != new_material {
+            *material = new_material;
+        }
+    }
+}
+
 /// makes the camera follow the player.
 /// TODO: when the player is in corners, make the camera stay in the same spot.
 /// NOTE: this will first require fixing how the tiles are drawn
@@ -217,13 +371,12 @@ fn camera_player_tracking(
 /// the input.
 fn player_movement_input_handling(
     key_press_time: ResMut<KeyPressTime>,
-    dungeon: Res<Dungeon>,
+    mut dungeon: ResMut<Dungeon>,
     mut player_state: Query<&mut PlayerState, With<Player>>,
     player_position: Query<&Position, With<Player>>,
     mut player_direction: Query<&mut PlayerDirection, With<Player>>,
+    mut player_keys: Query<&mut Keys, With<Player>>,
 ) {
-    let floor = &dungeon.floors[0];
-
     let mut player_state = player_state.single_mut().unwrap();
 
     if let Ok(player_position) = player_position.single() {
@@ -258,11 +411,24 @@ fn player_movement_input_handling(
                 || time_pressed >= PLAYER_MOVEMENT_DELAY_SECONDS
             {
                 *player_direction = new_direction;
-                *player_state = PlayerState::Moving {
-                    destination: match new_direction.try_move_to_point(&player_position.0, floor) {
+                let destination =
+                    match new_direction.try_move_to_point(&player_position.0, &dungeon.floors[0]) {
                         Some(p) => p,
-                        None => return,
-                    },
+                        None => {
+                            // walking into a closed door opens it; the dungeon is
+                            // only borrowed mutably when the door can be opened, so
+                            // that it isn't marked as changed on every bump
+                            if let Some(door) = new_direction.point_in_front(&player_position.0) {
+                                let mut keys = player_keys.single_mut().unwrap();
+                                if can_open_door(&dungeon, door, &keys) {
+                                    open_door(&mut dungeon, door, &mut keys);
+                                }
+                            }
+                            return;
+                        }
+                    };
+                *player_state = PlayerState::Moving {
+                    destination,
                     timer: Timer::from_seconds(PLAYER_MOVING_TIME_SECONDS, false),
                 }
             }
@@ -271,3 +437,21 @@ fn player_movement_input_handling(
         }
     }
 }
+
+/// Returns `true` if there is a closed door at the point that the player can
+/// open: either it isn't locked, or the player has it's key.
+fn can_open_door(dungeon: &Dungeon, door: Point, keys: &Keys) -> bool {
+    let tile = *dungeon.floors[0].at(door);
+    tile.is_secret_door() && !tile.is_passable() && (!tile.is_locked() || keys.0.contains(&door))
+}
+
+/// Opens the closed door at the point, unlocking it first (and using up the
+/// key) if it's locked. Only call this if [`can_open_door`] returns `true`.
+fn open_door(dungeon: &mut Dungeon, door: Point, keys: &mut Keys) {
+    let floor = &mut dungeon.floors[0];
+    if floor.at(door).is_locked() {
+        keys.0.remove(&door);
+        floor.unlock_door(door).unwrap();
+    }
+    floor.open_door(door).unwrap();
+}
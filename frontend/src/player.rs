@@ -1,10 +1,14 @@
-use std::f32::consts::PI;
+use std::{collections::BTreeSet, f32::consts::PI};
 
 use bevy::prelude::*;
 use dungeon::{Floor, Point};
 
 pub struct Player;
 
+/// The doors the player has picked up the keys to.
+#[derive(Debug, Default)]
+pub struct Keys(pub BTreeSet<Point>);
+
 #[derive(Debug)]
 pub enum PlayerState {
     Moving { destination: Point, timer: Timer },
@@ -49,16 +53,22 @@ impl PlayerDirection {
 
     /// Tries to move to the point provided, in the direction of `self`.
     pub fn try_move_to_point(&self, from: &Point, floor: &Floor) -> Option<Point> {
-        let new_point = match self {
-            PlayerDirection::Up => from.sub_row(1).ok()?,
-            PlayerDirection::Right => from.add_column(1).ok()?,
-            PlayerDirection::Down => from.add_row(1).ok()?,
-            PlayerDirection::Left => from.sub_column(1).ok()?,
-        };
-        if floor.at(new_point).is_solid() {
-            None
-        } else {
+        let new_point = self.point_in_front(from)?;
+        if floor.at(new_point).is_passable() {
             Some(new_point)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the point next to the point provided, in the direction of
+    /// `self`.
+    pub fn point_in_front(&self, from: &Point) -> Option<Point> {
+        match self {
+            PlayerDirection::Up => from.sub_row(1).ok(),
+            PlayerDirection::Right => from.add_column(1).ok(),
+            PlayerDirection::Down => from.add_row(1).ok(),
+            PlayerDirection::Left => from.sub_column(1).ok(),
         }
     }
 }
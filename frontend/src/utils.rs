@@ -1,5 +1,5 @@
 use bevy::prelude::*;
-use dungeon::{DungeonTile, Point};
+use dungeon::{DungeonTile, ExplorationState, Point};
 
 use crate::{
     constants::{PLAYER_Z_INDEX, SPRITE_SIZE, TILE_Z_INDEX},
@@ -9,11 +9,12 @@ use crate::{
 pub fn tile_sprite_bundle(
     materials: &Res<Materials>,
     tile: &DungeonTile,
+    state: ExplorationState,
     point: Point,
     floor: &dungeon::Floor,
 ) -> SpriteBundle {
     SpriteBundle {
-        material: materials[*tile].clone(),
+        material: materials.tile(*tile, state),
         sprite: Sprite::new(Vec2::new(SPRITE_SIZE, SPRITE_SIZE)),
         transform: point_to_transform(point, floor, TILE_Z_INDEX),
         ..Default::default()